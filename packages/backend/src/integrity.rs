@@ -1,6 +1,8 @@
 use hdi::prelude::*;
 use serde::{Serialize, Deserialize};
 
+use crate::tamagochi;
+
 #[hdk_entry_defs]
 #[unit_enum(UnitEntryTypes)]
 pub enum EntryTypes {
@@ -23,9 +25,9 @@ pub enum EntryTypes {
     #[entry_def(required_validations = 5, visibility = "public")]
     PostLike(PostLike),
     #[entry_def(required_validations = 5, visibility = "public")]
-    PostComment(PostComment),
+    Comment(Comment),
     #[entry_def(required_validations = 5, visibility = "public")]
-    PostReport(PostReport),
+    Report(Report),
     #[entry_def(required_validations = 5, visibility = "public")]
     UserBlock(UserBlock),
     #[entry_def(required_validations = 5, visibility = "public")]
@@ -41,8 +43,6 @@ pub enum EntryTypes {
     #[entry_def(required_validations = 5, visibility = "public")]
     Review(Review),
     #[entry_def(required_validations = 5, visibility = "public")]
-    ProductComment(ProductComment),
-    #[entry_def(required_validations = 5, visibility = "public")]
     AdBanner(AdBanner),
     #[entry_def(required_validations = 5, visibility = "public")]
     Wish(Wish),
@@ -52,6 +52,40 @@ pub enum EntryTypes {
     TamagochiVisit(TamagochiVisit),
     #[entry_def(required_validations = 5, visibility = "public")]
     ProfileCover(ProfileCover),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    Story(Story),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    StoryView(StoryView),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    StickerPack(StickerPack),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    ModerationDecision(ModerationDecision),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    RoleGrant(RoleGrant),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    RoleRevocation(RoleRevocation),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    VerificationRequest(VerificationRequest),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    VerificationAttestation(VerificationAttestation),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    TamagochiMemorial(TamagochiMemorial),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    TamagochiInventory(TamagochiInventory),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    LeaderboardSnapshot(LeaderboardSnapshot),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    BreedingProposal(BreedingProposal),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    BreedingRecord(BreedingRecord),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    AchievementUnlock(AchievementUnlock),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    FriendRequest(FriendRequest),
+    #[entry_def(required_validations = 5, visibility = "private")]
+    RutKey(RutKey),
+    #[entry_def(required_validations = 5, visibility = "public")]
+    BannerApproval(BannerApproval),
 }
 
 #[hdk_link_types]
@@ -67,15 +101,42 @@ pub enum LinkTypes {
     Chat,
     UserToPosts,
     Feed,
+    FeedPath,
     PostToClaps,
     PostToLikes,
     PostToComments,
     CommentToReplies,
-    PostToReports,
+    TargetToReports,
+    AgentToReports, // Reportado -> reportes, para ver reincidentes
+    PostUpdates,
+    HashtagToPosts,
+    HashtagUsage,
+    AgentToMentions,
     UserToBlocks,
     UserToTamagochi,
+    TamagochiUpdates,
     UserToDeaths,
-    UserToFriends,
+    TamagochiDeathUpdates,
+    DeathToMemorials,
+    UserToInventory,
+    InventoryUpdates,
+    RideToRewards, // Tag = agente que cobró el premio
+    VisitToResponse, // Tag "accepted" / "declined"
+    LeaderboardSnapshots, // leaderboard.<día> -> snapshot, tag = dueño
+    UserToLeaderboardSnapshots,
+    UserToBreedingProposals, // Pareja -> propuesta
+    BreedingProposalToResponse, // Tag "accepted" / "declined"
+    TamagochiToBreedings, // Para el cooldown de cada padre
+    UserToEggs,
+    EggToHatchling,
+    AgentToAchievements, // Tag = id del logro
+    UserToFriends, // Cada lado crea el suyo; amistad = ambos links
+    UserToPreferences,
+    PreferencesUpdates,
+    UserToFriendRequests, // Destinatario -> solicitud
+    FriendRequestToResponse, // Tag "accepted" / "declined"
+    GeoCellToAgents, // geo.<lat>.<lon> -> agente, tag = coordenadas
+    AgentToGeoCell, // Celda actual del agente, mismo tag
     ProductToBookings,
     ServiceToBookings,
     ServiceToRides,
@@ -83,7 +144,7 @@ pub enum LinkTypes {
     ProductToReviews,
     ServiceToReviews,
     ProductToComments,
-    CommentToReplies,
+    CommentUpdates,
     Banner,
     UserToWishes,
     WishToHelpers,
@@ -91,6 +152,20 @@ pub enum LinkTypes {
     UserToTamagochiVisits,
     TamagochiToVisits,
     UserToProfileCover,
+    UserToStories,
+    StoryToViews,
+    UserToStickerPacks,
+    StickerPacks,
+    ModerationQueue,
+    TargetToModeration,
+    AgentToRoleGrants,
+    RoleGrantToRevocations,
+    VerificationRequests,
+    UserToVerificationRequests,
+    VerificationRequestToAttestations,
+    RutIndex, // rut_index.<hash> -> agente verificado; tag = attestation
+    VisitToPlayClaims, // Visita -> visitante, al cobrar la experiencia tras la aceptación
+    ApprovedBanners, // active_banners -> banner; tag = aprobación
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -114,10 +189,10 @@ pub enum UnitEntryTypes {
     PostClap,
     #[serde(rename = "post_like")]
     PostLike,
-    #[serde(rename = "post_comment")]
-    PostComment,
-    #[serde(rename = "post_report")]
-    PostReport,
+    #[serde(rename = "comment")]
+    Comment,
+    #[serde(rename = "report")]
+    Report,
     #[serde(rename = "user_block")]
     UserBlock,
     #[serde(rename = "tamagochi")]
@@ -132,8 +207,6 @@ pub enum UnitEntryTypes {
     RideRequest,
     #[serde(rename = "review")]
     Review,
-    #[serde(rename = "product_comment")]
-    ProductComment,
     #[serde(rename = "ad_banner")]
     AdBanner,
     #[serde(rename = "wish")]
@@ -144,6 +217,40 @@ pub enum UnitEntryTypes {
     TamagochiVisit,
     #[serde(rename = "profile_cover")]
     ProfileCover,
+    #[serde(rename = "story")]
+    Story,
+    #[serde(rename = "story_view")]
+    StoryView,
+    #[serde(rename = "sticker_pack")]
+    StickerPack,
+    #[serde(rename = "moderation_decision")]
+    ModerationDecision,
+    #[serde(rename = "role_grant")]
+    RoleGrant,
+    #[serde(rename = "role_revocation")]
+    RoleRevocation,
+    #[serde(rename = "verification_request")]
+    VerificationRequest,
+    #[serde(rename = "verification_attestation")]
+    VerificationAttestation,
+    #[serde(rename = "tamagochi_memorial")]
+    TamagochiMemorial,
+    #[serde(rename = "tamagochi_inventory")]
+    TamagochiInventory,
+    #[serde(rename = "leaderboard_snapshot")]
+    LeaderboardSnapshot,
+    #[serde(rename = "breeding_proposal")]
+    BreedingProposal,
+    #[serde(rename = "breeding_record")]
+    BreedingRecord,
+    #[serde(rename = "achievement_unlock")]
+    AchievementUnlock,
+    #[serde(rename = "friend_request")]
+    FriendRequest,
+    #[serde(rename = "rut_key")]
+    RutKey,
+    #[serde(rename = "banner_approval")]
+    BannerApproval,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
@@ -151,10 +258,12 @@ pub struct User {
     pub email: String,
     pub password_hash: String,
     pub name: String,
-    pub rut: Option<String>,
+    #[serde(default)]
+    pub rut_masked: Option<String>, // Para mostrar, ej: "12.***.***-K"; copiado de la attestation aprobada
     pub profile_image_hash: Option<String>,
     pub id_card_image_hash: Option<String>,
     pub is_verified: bool,
+    pub verification_hash: Option<ActionHash>, // VerificationAttestation aprobada; requerida si is_verified
     pub lat: Option<f64>,
     pub lon: Option<f64>,
     pub created_at: i64,
@@ -238,16 +347,93 @@ pub struct Post {
     pub sticker_data: Vec<StickerData>, // Stickers sobre la imagen/video
     pub created_at: i64,
     pub location: Option<PostLocation>,
+    pub edited_at: Option<i64>, // Última edición (texto/stickers)
+}
+
+/// Claps one user can give a single post
+pub const MAX_CLAPS_PER_USER: u32 = 50;
+
+/// One aggregated record per (user, post), updated in place on each clap
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct PostClap {
+    pub post_hash: EntryHash,
+    pub user_id: AgentPubKey,
+    pub count: u32, // 1-MAX_CLAPS_PER_USER
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// What a comment hangs off; posts and products share one comment subsystem
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "hash", rename_all = "snake_case")]
+pub enum CommentTarget {
+    Post(EntryHash),
+    Product(EntryHash),
+}
+
+pub const MAX_COMMENT_CHARS: usize = 2000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct Comment {
+    pub target: CommentTarget,
+    pub author_id: AgentPubKey,
+    pub text: String,
+    pub parent_comment_hash: Option<EntryHash>, // Para respuestas
+    pub created_at: i64,
+    pub edited_at: Option<i64>,
+    pub deleted: bool, // Borrado por el autor; se conserva si tiene respuestas
+}
+
+/// Stories expire 24 hours after creation
+pub const STORY_TTL_SECONDS: i64 = 24 * 3600;
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct Story {
+    pub author_id: AgentPubKey,
+    pub text: Option<String>,
+    pub image_hashes: Vec<String>,
+    pub video_hash: Option<String>,
+    pub sticker_data: Vec<StickerData>,
+    pub created_at: i64,
+    pub expires_at: i64, // created_at + STORY_TTL_SECONDS
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct StoryView {
+    pub story_hash: EntryHash,
+    pub viewer_id: AgentPubKey,
+    pub viewed_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StickerKind {
+    Emoji,
+    Image, // content es el hash de una Image subida (nunca una URL externa)
+    Text,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StickerData {
-    pub sticker_type: String, // "emoji", "image", "text"
-    pub content: String, // Emoji, URL de imagen, o texto
+    pub sticker_type: StickerKind,
+    pub content: String, // Emoji, hash de imagen, o texto
     pub x: f64, // Posición X (0-1)
     pub y: f64, // Posición Y (0-1)
-    pub scale: f64, // Escala del sticker
-    pub rotation: f64, // Rotación en grados
+    pub scale: f64, // Escala del sticker (0.1-5)
+    pub rotation: f64, // Rotación en grados (-360 a 360)
+}
+
+pub const MAX_STICKERS: usize = 20;
+pub const MAX_STICKER_EMOJI_CHARS: usize = 16;
+pub const MAX_STICKER_TEXT_CHARS: usize = 100;
+pub const MAX_STICKER_PACK_SIZE: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct StickerPack {
+    pub creator_id: AgentPubKey,
+    pub name: String,
+    pub image_hashes: Vec<String>, // Hashes de Image, uno por sticker
+    pub created_at: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -257,15 +443,192 @@ pub struct PostLocation {
     pub address: Option<String>,
 }
 
+/// Anything that can be reported; users are reported by agent key, everything else by entry hash
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "hash", rename_all = "snake_case")]
+pub enum ReportTarget {
+    Post(EntryHash),
+    User(AgentPubKey),
+    Product(EntryHash),
+    Service(EntryHash),
+    Message(EntryHash),
+    Wish(EntryHash),
+    TamagochiVisit(EntryHash),
+}
+
+impl ReportTarget {
+    /// Link base for the target's reports and moderation decisions
+    pub fn base(&self) -> AnyLinkableHash {
+        match self {
+            ReportTarget::User(agent) => agent.clone().into(),
+            ReportTarget::Post(hash)
+            | ReportTarget::Product(hash)
+            | ReportTarget::Service(hash)
+            | ReportTarget::Message(hash)
+            | ReportTarget::Wish(hash)
+            | ReportTarget::TamagochiVisit(hash) => hash.clone().into(),
+        }
+    }
+    
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReportTarget::Post(_) => "post",
+            ReportTarget::User(_) => "user",
+            ReportTarget::Product(_) => "product",
+            ReportTarget::Service(_) => "service",
+            ReportTarget::Message(_) => "message",
+            ReportTarget::Wish(_) => "wish",
+            ReportTarget::TamagochiVisit(_) => "tamagochi_visit",
+        }
+    }
+    
+    /// Inverse of `kind` + `base`, for targets stored as link tag + link target
+    pub fn from_parts(kind: &str, base: AnyLinkableHash) -> Option<Self> {
+        if kind == "user" {
+            return base.into_agent_pub_key().map(ReportTarget::User);
+        }
+        let hash = base.into_entry_hash()?;
+        match kind {
+            "post" => Some(ReportTarget::Post(hash)),
+            "product" => Some(ReportTarget::Product(hash)),
+            "service" => Some(ReportTarget::Service(hash)),
+            "message" => Some(ReportTarget::Message(hash)),
+            "wish" => Some(ReportTarget::Wish(hash)),
+            "tamagochi_visit" => Some(ReportTarget::TamagochiVisit(hash)),
+            _ => None,
+        }
+    }
+}
+
+pub const REPORT_REASONS: [&str; 7] = ["spam", "inappropriate", "violence", "harassment", "fraud", "impersonation", "other"];
+pub const MAX_REPORT_EVIDENCE: usize = 5;
+pub const MAX_REPORT_DESCRIPTION_CHARS: usize = 1000;
+
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
-pub struct PostReport {
-    pub post_hash: EntryHash,
+pub struct Report {
+    pub target: ReportTarget,
+    pub reported_agent: AgentPubKey, // Autor del contenido reportado
+    #[serde(default)]
+    pub target_action: Option<ActionHash>, // Acción que creó el contenido; None al reportar un usuario
     pub reporter_id: AgentPubKey,
-    pub reason: String, // "spam", "inappropriate", "violence", "harassment", "other"
+    pub reason: String, // Ver REPORT_REASONS
     pub description: Option<String>,
+    pub evidence_image_hashes: Vec<String>, // Capturas subidas con upload_image
     pub created_at: i64,
 }
 
+/// DNA properties (happ/dna.yaml); missing keys fall back to defaults. Without a `progenitor`
+/// the feeds still work, but every privileged action is refused
+#[derive(Serialize, Deserialize, SerializedBytes, Debug, Clone)]
+pub struct DnaProperties {
+    #[serde(default = "default_report_hide_threshold")]
+    pub report_hide_threshold: u32, // Reportes distintos para ocultar un post hasta revisión
+    #[serde(default)]
+    pub progenitor: Option<AgentPubKey>, // Admin raíz; no necesita RoleGrant. Se fija en cada despliegue
+}
+
+fn default_report_hide_threshold() -> u32 {
+    3
+}
+
+pub fn dna_properties() -> ExternResult<DnaProperties> {
+    DnaProperties::try_from(dna_info()?.modifiers.properties)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Invalid DNA properties: {}", e))))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin, // Incluye todos los demás roles y puede otorgarlos
+    Moderator,
+    Verifier,
+}
+
+/// Grants expire so validation, which can't see revocation links, still stops honouring a revoked role
+pub const ROLE_GRANT_TTL_SECONDS: i64 = 30 * 24 * 3600;
+
+/// Signed grant; `authority` is the granter's own Admin grant (None for the progenitor)
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct RoleGrant {
+    pub grantee: AgentPubKey,
+    pub role: Role,
+    pub granted_by: AgentPubKey,
+    pub authority: Option<ActionHash>,
+    pub granted_at: i64,
+    pub expires_at: i64, // Renovar con grant_role antes de que venza
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct RoleRevocation {
+    pub grant_hash: ActionHash,
+    pub revoked_by: AgentPubKey,
+    pub authority: Option<ActionHash>,
+    pub revoked_at: i64,
+}
+
+/// Submitted by the user; reviewed by agents holding the Verifier role, who read the RUT off the ID card
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct VerificationRequest {
+    pub user_id: AgentPubKey,
+    pub id_card_image_hash: String,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Approved,
+    Rejected,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct VerificationAttestation {
+    pub request_hash: ActionHash,
+    pub user_id: AgentPubKey,
+    pub verifier_id: AgentPubKey,
+    pub status: VerificationStatus,
+    pub reason: Option<String>, // Motivo del rechazo
+    pub rut_key_hash: Option<String>, // RUT con la clave de verificadores; sólo al aprobar
+    #[serde(default)]
+    pub rut_masked: Option<String>, // Forma visible del RUT, ej: "12.***.***-K"; sólo al aprobar
+    pub authority: Option<ActionHash>, // RoleGrant del verificador
+    pub attested_at: i64,
+}
+
+/// Verifiers' key for RUT hashes, shared out of band; private so it never reaches the DHT
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct RutKey {
+    pub key: String,
+}
+
+pub const RUT_INDEX_ROOT: &str = "rut_index";
+
+/// One anchor per keyed RUT hash; its only link points at the verified owner
+pub fn rut_index_path(rut_key_hash: &str) -> ExternResult<EntryHash> {
+    Path::from(format!("{}.{}", RUT_INDEX_ROOT, rut_key_hash)).path_entry_hash()
+}
+
+/// Targets over the report threshold; tag = kind of target
+pub const MODERATION_QUEUE_ROOT: &str = "moderation_queue";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    Keep, // Visible; nuevos reportes pueden volver a encolarlo
+    Hide, // Oculto, reversible con otra decisión
+    Remove, // Oculto definitivamente
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct ModerationDecision {
+    pub target: ReportTarget,
+    pub moderator_id: AgentPubKey,
+    pub action: ModerationAction,
+    pub note: Option<String>,
+    pub authority: Option<ActionHash>, // RoleGrant del moderador
+    pub decided_at: i64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
 pub struct UserBlock {
     pub blocker_id: AgentPubKey,
@@ -288,9 +651,90 @@ pub struct Tamagochi {
     pub last_fed_at: i64,
     pub last_cleaned_at: i64,
     pub last_played_at: i64,
+    #[serde(default)]
+    pub last_updated_at: i64, // Último tick simulado (ver tamagochi::simulate)
+    #[serde(default)]
+    pub neglected_ticks: u32, // Ticks seguidos sucio y triste; muere por "neglect"
+    #[serde(default)]
+    pub care_streak: u32, // Días seguidos alimentado, bañado o jugado por el dueño
+    #[serde(default)]
+    pub last_care_day: i64, // Día (epoch / 86400) del último cuidado
+    #[serde(default)]
+    pub genome: Option<Genome>, // None en mascotas anteriores a la genética
+    #[serde(default)]
+    pub bred_from: Option<ActionHash>, // BreedingRecord del huevo; None si se creó de cero
+    #[serde(default)]
+    pub previous: Option<ActionHash>, // Versión anterior; None solo al nacer
     pub is_alive: bool,
 }
 
+/// Inherited traits; see tamagochi::breed for how children are derived
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Genome {
+    pub colour: String,
+    pub species: String,
+    pub rarity: String, // "common", "uncommon", "rare", "legendary"
+    pub experience_growth: i32, // % extra sobre lo ganado
+    pub happiness_growth: i32,
+    pub energy_growth: i32,
+    pub generation: u32,
+}
+
+impl Genome {
+    pub fn from_engine(genome: &tamagochi::Genome) -> Genome {
+        Genome {
+            colour: genome.colour.as_str().to_string(),
+            species: genome.species.as_str().to_string(),
+            rarity: genome.rarity().as_str().to_string(),
+            experience_growth: genome.growth.experience,
+            happiness_growth: genome.growth.happiness,
+            energy_growth: genome.growth.energy,
+            generation: genome.generation,
+        }
+    }
+
+    pub fn to_engine(&self) -> tamagochi::Genome {
+        let default = tamagochi::Genome::default();
+        tamagochi::Genome {
+            colour: tamagochi::Colour::parse(&self.colour).unwrap_or(default.colour),
+            species: tamagochi::Species::parse(&self.species).unwrap_or(default.species),
+            growth: tamagochi::Growth {
+                experience: self.experience_growth,
+                happiness: self.happiness_growth,
+                energy: self.energy_growth,
+            },
+            generation: self.generation,
+        }
+    }
+}
+
+pub const BREEDING_COOLDOWN_SECONDS: i64 = 7 * 24 * 3600; // Por mascota
+pub const BREEDING_PROPOSAL_TTL_SECONDS: i64 = 2 * 24 * 3600;
+
+/// Signed by the proposer; the partner co-signs by writing the BreedingRecord
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct BreedingProposal {
+    pub proposer_id: AgentPubKey,
+    pub partner_id: AgentPubKey,
+    pub proposer_tamagochi: EntryHash,
+    pub partner_tamagochi: EntryHash,
+    pub proposer_version: ActionHash, // Versiones adultas de cada mascota (prueba de la etapa)
+    pub partner_version: ActionHash,
+    pub egg_owner_id: AgentPubKey, // Uno de los dos
+    pub proposed_at: i64,
+}
+
+/// The egg; hatched later by its owner with hatch_egg
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct BreedingRecord {
+    pub proposal_hash: ActionHash, // Su hash es la semilla de la genética
+    pub proposer_id: AgentPubKey,
+    pub partner_id: AgentPubKey,
+    pub egg_owner_id: AgentPubKey,
+    pub genome: Genome,
+    pub bred_at: i64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
 pub struct TamagochiDeath {
     pub owner_id: AgentPubKey,
@@ -298,6 +742,262 @@ pub struct TamagochiDeath {
     pub death_reason: String, // "starvation", "neglect", "old_age", "killed"
     pub died_at: i64,
     pub age_seconds: i64,
+    #[serde(default)]
+    pub stage: String, // Etapa al morir
+    #[serde(default)]
+    pub level: u32,
+    #[serde(default)]
+    pub epitaph: Option<String>, // Lo escribe el dueño para el cementerio
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VisitInteraction {
+    Pet,
+    Play,
+    Gift, // Regala un item del inventario del visitante
+}
+
+pub const VISIT_COOLDOWN_SECONDS: i64 = 4 * 3600; // Por mascota visitada
+pub const MAX_DAILY_VISITS: usize = 10;
+
+/// Interactions only touch the host pet once the owner accepts them
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct TamagochiVisit {
+    pub visitor_id: AgentPubKey,
+    pub tamagochi_owner_id: AgentPubKey,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub interaction: Option<VisitInteraction>,
+    #[serde(default)]
+    pub gift_item_id: Option<String>,
+    pub visited_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InventorySlot {
+    pub item_id: String, // Id del catálogo (tamagochi::CATALOGUE)
+    pub quantity: u32,
+}
+
+pub const MAX_DAILY_REWARDS: u32 = 10;
+
+/// What turned the previous inventory version into this one; validation replays it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InventoryChange {
+    Buy { item_id: String, quantity: u32 },
+    Use { item_id: String }, // En la propia mascota o regalado en una visita
+    Reward { activity: String, evidence: ActionHash }, // Post, WishHelp o link RideToRewards
+}
+
+/// One per owner, updated in place; coins and items are earned through app activity
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct TamagochiInventory {
+    pub owner_id: AgentPubKey,
+    pub coins: u32,
+    pub items: Vec<InventorySlot>,
+    pub rewards_day: i64, // Día (epoch / 86400) del contador de premios
+    pub rewards_today: u32,
+    pub last_reward_at: i64, // Evidencia más reciente ya premiada
+    pub previous: Option<ActionHash>, // Versión anterior; None sólo en la primera
+    pub change: Option<InventoryChange>, // None sólo en el inventario vacío, que nunca se guarda
+    pub updated_at: i64,
+}
+
+impl TamagochiInventory {
+    pub fn empty(owner_id: AgentPubKey) -> Self {
+        TamagochiInventory {
+            owner_id,
+            coins: 0,
+            items: Vec::new(),
+            rewards_day: 0,
+            rewards_today: 0,
+            last_reward_at: 0,
+            previous: None,
+            change: None,
+            updated_at: 0,
+        }
+    }
+}
+
+/// Shared by validation and the coordinator: the inventory `change` makes out of `previous`.
+/// `day` is the day of the write; `evidence_at` when the rewarded activity happened.
+pub fn next_inventory(previous: &TamagochiInventory, change: &InventoryChange, day: i64, evidence_at: Option<i64>) -> Result<TamagochiInventory, String> {
+    let mut next = previous.clone();
+    next.change = Some(change.clone());
+    
+    match change {
+        InventoryChange::Buy { item_id, quantity } => {
+            let item = tamagochi::find_item(item_id).ok_or("Unknown item")?;
+            if *quantity == 0 {
+                return Err("Quantity must be at least 1".into());
+            }
+            let cost = item.price.checked_mul(*quantity).ok_or("Not enough coins")?;
+            next.coins = next.coins.checked_sub(cost).ok_or("Not enough coins")?;
+            add_inventory_slot(&mut next.items, item.id, *quantity);
+        }
+        InventoryChange::Use { item_id } => {
+            let slot = next.items.iter_mut().find(|slot| slot.item_id == *item_id).ok_or("You don't have this item")?;
+            slot.quantity -= 1;
+            next.items.retain(|slot| slot.quantity > 0);
+        }
+        InventoryChange::Reward { activity, .. } => {
+            let activity = tamagochi::Activity::parse(activity).ok_or("Unknown activity")?;
+            let evidence_at = evidence_at.ok_or("Rewards need evidence")?;
+            if evidence_at <= previous.last_reward_at {
+                return Err("This activity was already rewarded".into());
+            }
+            if evidence_at.div_euclid(24 * 3600) != day {
+                return Err("Only today's activity is rewarded".into());
+            }
+            if next.rewards_day != day {
+                next.rewards_day = day;
+                next.rewards_today = 0;
+            }
+            if next.rewards_today >= MAX_DAILY_REWARDS {
+                return Err("Daily reward limit reached".into());
+            }
+            let reward = tamagochi::reward(activity);
+            next.coins = next.coins.saturating_add(reward.coins);
+            if let Some(item_id) = reward.item {
+                add_inventory_slot(&mut next.items, item_id, 1);
+            }
+            next.rewards_today += 1;
+            next.last_reward_at = evidence_at;
+        }
+    }
+    
+    Ok(next)
+}
+
+fn add_inventory_slot(items: &mut Vec<InventorySlot>, item_id: &str, quantity: u32) {
+    match items.iter_mut().find(|slot| slot.item_id == item_id) {
+        Some(slot) => slot.quantity = slot.quantity.saturating_add(quantity),
+        None => items.push(InventorySlot { item_id: item_id.to_string(), quantity }),
+    }
+}
+
+/// The sender's UserToFriends link is created with the request; the friendship
+/// exists once the recipient accepts and links back
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct FriendRequest {
+    pub sender_id: AgentPubKey,
+    pub recipient_id: AgentPubKey,
+    pub message: Option<String>,
+    pub created_at: i64,
+}
+
+pub const LEADERBOARD_ROOT: &str = "leaderboard";
+
+/// One anchor per day; snapshots hang from the day they were taken
+pub fn leaderboard_bucket_path(day: i64) -> ExternResult<EntryHash> {
+    Path::from(format!("{}.{}", LEADERBOARD_ROOT, day)).path_entry_hash()
+}
+
+/// Periodic copy of a pet's ranking stats so leaderboards don't read every tamagochi
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct LeaderboardSnapshot {
+    pub owner_id: AgentPubKey,
+    pub tamagochi_hash: EntryHash, // Hash original (estable) del tamagochi
+    pub tamagochi_version: ActionHash, // Versión copiada; la validación compara los números
+    pub tamagochi_name: String,
+    pub stage: String,
+    pub level: u32,
+    pub experience: u32,
+    pub born_at: i64,
+    pub care_streak: u32,
+    pub last_care_day: i64,
+    pub lat: Option<f64>, // Redondeada; solo si el dueño comparte ubicación
+    pub lon: Option<f64>,
+    pub taken_at: i64,
+}
+
+/// What kind of record on the agent's own chain counts towards an achievement
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementEvidence {
+    Post,
+    Clap, // Un PostClap creado (un post aplaudido)
+    WishHelp,
+    Review,
+    RideReward, // Link RideToRewards: viaje completado y cobrado
+    AdultTamagochi,
+}
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub evidence: AchievementEvidence,
+    pub required: u32,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement { id: "first_post", name: "Hello world", description: "Publish your first post", evidence: AchievementEvidence::Post, required: 1 },
+    Achievement { id: "storyteller", name: "Storyteller", description: "Publish 50 posts", evidence: AchievementEvidence::Post, required: 50 },
+    Achievement { id: "cheerleader", name: "Cheerleader", description: "Clap 25 different posts", evidence: AchievementEvidence::Clap, required: 25 },
+    Achievement { id: "helping_hand", name: "Helping hand", description: "Help with a wish", evidence: AchievementEvidence::WishHelp, required: 1 },
+    Achievement { id: "wish_granter", name: "Wish granter", description: "Help with 10 wishes", evidence: AchievementEvidence::WishHelp, required: 10 },
+    Achievement { id: "first_review", name: "Critic", description: "Write your first review", evidence: AchievementEvidence::Review, required: 1 },
+    Achievement { id: "trusted_reviewer", name: "Trusted reviewer", description: "Write 10 reviews", evidence: AchievementEvidence::Review, required: 10 },
+    Achievement { id: "first_ride", name: "On the road", description: "Complete a ride", evidence: AchievementEvidence::RideReward, required: 1 },
+    Achievement { id: "road_warrior", name: "Road warrior", description: "Complete 25 rides", evidence: AchievementEvidence::RideReward, required: 25 },
+    Achievement { id: "all_grown_up", name: "All grown up", description: "Raise a tamagochi to adulthood", evidence: AchievementEvidence::AdultTamagochi, required: 1 },
+];
+
+pub fn find_achievement(id: &str) -> Option<&'static Achievement> {
+    ACHIEVEMENTS.iter().find(|a| a.id == id)
+}
+
+/// What `record` proves towards `kind`, None if nothing; records sharing a key count once
+/// (claps on the same post, rewards for the same ride)
+pub fn achievement_evidence_key(kind: AchievementEvidence, record: &Record) -> ExternResult<Option<AnyLinkableHash>> {
+    let created = matches!(record.action(), Action::Create(_));
+    let itself = AnyLinkableHash::from(record.action_address().clone());
+    Ok(match kind {
+        AchievementEvidence::Post => (created && record.entry().to_app_option::<Post>().ok().flatten().is_some()).then_some(itself),
+        AchievementEvidence::Clap => match record.entry().to_app_option::<PostClap>().ok().flatten() {
+            Some(clap) if created => Some(clap.post_hash.into()),
+            _ => None,
+        },
+        AchievementEvidence::WishHelp => (created && record.entry().to_app_option::<WishHelp>().ok().flatten().is_some()).then_some(itself),
+        AchievementEvidence::Review => (created && record.entry().to_app_option::<Review>().ok().flatten().is_some()).then_some(itself),
+        // El link solo es válido sobre un viaje completado del agente (ver validate_ride_reward_link)
+        AchievementEvidence::RideReward => match record.action() {
+            Action::CreateLink(link) => match LinkTypes::from_type(link.zome_index, link.link_type)? {
+                Some(LinkTypes::RideToRewards) => Some(link.base_address.clone()),
+                _ => None,
+            },
+            _ => None,
+        },
+        // Los tamagochis nacen huevo y cada update se valida contra el anterior, así que
+        // una versión adulta es una evolución real
+        AchievementEvidence::AdultTamagochi => record.entry().to_app_option::<Tamagochi>().ok().flatten()
+            .filter(|t| t.stage == tamagochi::Stage::Adult.as_str())
+            .map(|_| itself),
+    })
+}
+
+/// Carries the records that prove the achievement so anyone can re-check it
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct AchievementUnlock {
+    pub agent_id: AgentPubKey,
+    pub achievement_id: String, // Ver ACHIEVEMENTS
+    pub evidence: Vec<ActionHash>, // Acciones de la propia cadena del agente
+    pub unlocked_at: i64,
+}
+
+pub const MAX_EPITAPH_CHARS: usize = 140;
+pub const MAX_MEMORIAL_CHARS: usize = 500;
+
+/// Message a friend leaves on a death record
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct TamagochiMemorial {
+    pub death_hash: EntryHash,
+    pub author_id: AgentPubKey,
+    pub message: String,
+    pub created_at: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
@@ -306,9 +1006,82 @@ pub struct UserPreferences {
     pub app_color: String, // Color principal de la app (hex)
     pub tamagochi_enabled: bool,
     pub location_sharing_enabled: bool, // Si permite que otros vean su ubicación
+    #[serde(default = "default_language")]
+    pub language: String, // Ver SUPPORTED_LANGUAGES
+    #[serde(default = "default_distance_unit")]
+    pub distance_unit: String, // "km" o "mi"
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    #[serde(default = "default_currency")]
+    pub default_currency: String, // ISO 4217, ej: "CLP"
+    #[serde(default = "default_profile_visibility")]
+    pub profile_visibility: String, // Ver PROFILE_VISIBILITIES
     pub updated_at: i64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NotificationSettings {
+    pub messages: bool,
+    pub friend_requests: bool,
+    pub comments: bool,
+    pub claps: bool,
+    pub mentions: bool,
+    pub tamagochi: bool, // Hambre, visitas, cría
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            messages: true,
+            friend_requests: true,
+            comments: true,
+            claps: true,
+            mentions: true,
+            tamagochi: true,
+        }
+    }
+}
+
+pub const SUPPORTED_LANGUAGES: [&str; 3] = ["es", "en", "pt"];
+pub const DISTANCE_UNITS: [&str; 2] = ["km", "mi"];
+pub const PROFILE_VISIBILITIES: [&str; 3] = ["public", "friends", "private"];
+
+pub fn default_language() -> String {
+    "es".to_string()
+}
+
+pub fn default_distance_unit() -> String {
+    "km".to_string()
+}
+
+pub fn default_currency() -> String {
+    "CLP".to_string()
+}
+
+pub fn default_profile_visibility() -> String {
+    "public".to_string()
+}
+
+pub fn check_preferences(prefs: &UserPreferences) -> Result<(), String> {
+    let color = prefs.app_color.strip_prefix('#').unwrap_or("");
+    if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("App color must be a hex color like #1f7aec".into());
+    }
+    if !SUPPORTED_LANGUAGES.contains(&prefs.language.as_str()) {
+        return Err("Unsupported language".into());
+    }
+    if !DISTANCE_UNITS.contains(&prefs.distance_unit.as_str()) {
+        return Err("Distance unit must be km or mi".into());
+    }
+    if prefs.default_currency.len() != 3 || !prefs.default_currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err("Currency must be a 3-letter ISO code".into());
+    }
+    if !PROFILE_VISIBILITIES.contains(&prefs.profile_visibility.as_str()) {
+        return Err("Invalid profile visibility".into());
+    }
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
 pub struct Booking {
     pub booker_id: AgentPubKey,
@@ -353,15 +1126,6 @@ pub struct Review {
     pub created_at: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
-pub struct ProductComment {
-    pub product_id: EntryHash,
-    pub author_id: AgentPubKey,
-    pub text: String,
-    pub parent_comment_hash: Option<EntryHash>, // For replies
-    pub created_at: i64,
-}
-
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
 pub struct AdBanner {
     pub advertiser_id: AgentPubKey, // User/company paying for the ad
@@ -376,6 +1140,17 @@ pub struct AdBanner {
     pub created_at: i64,
 }
 
+/// Banners are only listed once a moderator approves them
+pub const ACTIVE_BANNERS_ROOT: &str = "active_banners";
+
+#[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
+pub struct BannerApproval {
+    pub banner_hash: EntryHash,
+    pub approver_id: AgentPubKey,
+    pub authority: Option<ActionHash>, // RoleGrant del moderador
+    pub approved_at: i64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, EntryDefRegistration)]
 pub struct Wish {
     pub author_id: AgentPubKey,
@@ -408,3 +1183,1390 @@ pub struct VehicleInfo {
     pub currency: String, // Moneda (ej: "CLP", "USD")
 }
 
+// ========== Validation ==========
+
+#[hdk_extern]
+pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
+    match op.flattened::<EntryTypes, LinkTypes>()? {
+        FlatOp::StoreEntry(OpEntry::CreateEntry { app_entry, action }) => {
+            let now = action.timestamp.as_seconds_since_epoch();
+            match app_entry {
+                EntryTypes::Post(post) => {
+                    if post.author_id != action.author {
+                        return Ok(ValidateCallbackResult::Invalid("Post author must be the action author".into()));
+                    }
+                    validate_stickers(&post.sticker_data)
+                }
+                EntryTypes::Story(story) => {
+                    let result = validate_stickers(&story.sticker_data)?;
+                    if result != ValidateCallbackResult::Valid {
+                        return Ok(result);
+                    }
+                    validate_create_story(&story, &action.author, now)
+                }
+                EntryTypes::StoryView(view) => validate_create_story_view(&view, &action.author, now),
+                EntryTypes::StickerPack(pack) => validate_sticker_pack(&pack, &action.author),
+                EntryTypes::Comment(comment) => validate_comment(&comment, &action.author),
+                EntryTypes::PostClap(clap) => validate_post_clap(&clap, &action.author),
+                EntryTypes::Report(report) => validate_report(&report, &action.author),
+                EntryTypes::ModerationDecision(decision) => validate_moderation_decision(&decision, &action.author, now),
+                EntryTypes::BannerApproval(approval) => validate_banner_approval(&approval, &action.author, now),
+                EntryTypes::RoleGrant(grant) => validate_role_grant(&grant, &action.author, now),
+                EntryTypes::RoleRevocation(revocation) => validate_role_revocation(&revocation, &action.author, now),
+                EntryTypes::User(user) => validate_user_verification(&user, &action.author),
+                EntryTypes::VerificationRequest(request) => validate_verification_request(&request, &action.author),
+                EntryTypes::VerificationAttestation(attestation) => validate_verification_attestation(&attestation, &action.author, now),
+                EntryTypes::TamagochiDeath(death) => validate_tamagochi_death(&death, &action.author),
+                EntryTypes::TamagochiMemorial(memorial) => validate_tamagochi_memorial(&memorial, &action.author),
+                EntryTypes::TamagochiInventory(inventory) => validate_inventory(&inventory, &action.author, &action.prev_action, now, None),
+                EntryTypes::TamagochiVisit(visit) => validate_tamagochi_visit(&visit, &action.author, &action.prev_action, now),
+                EntryTypes::LeaderboardSnapshot(snapshot) => validate_leaderboard_snapshot(&snapshot, &action.author, &action.prev_action, now),
+                EntryTypes::Tamagochi(tamagochi) => validate_new_tamagochi(&tamagochi, &action.author, &action.prev_action, now),
+                EntryTypes::RideRequest(ride) => validate_ride_request(&ride, &action.author),
+                EntryTypes::BreedingProposal(proposal) => validate_breeding_proposal(&proposal, &action.author, &action.prev_action, now),
+                EntryTypes::BreedingRecord(record) => validate_breeding_record(&record, &action.author, &action.prev_action, now),
+                EntryTypes::AchievementUnlock(unlock) => validate_achievement_unlock(&unlock, &action.author),
+                EntryTypes::FriendRequest(request) => validate_friend_request(&request, &action.author),
+                EntryTypes::UserPreferences(prefs) => validate_preferences(&prefs, &action.author),
+                _ => Ok(ValidateCallbackResult::Valid),
+            }
+        }
+        FlatOp::StoreEntry(OpEntry::UpdateEntry { app_entry, action, original_action_hash, .. }) => {
+            // Every updatable entry is owned by its creator; the *_id fields are checked below
+            let result = validate_original_author(&original_action_hash, &action.author)?;
+            if result != ValidateCallbackResult::Valid {
+                return Ok(result);
+            }
+            match app_entry {
+                EntryTypes::Post(post) => {
+                    if post.author_id != action.author {
+                        return Ok(ValidateCallbackResult::Invalid("Post author can't change".into()));
+                    }
+                    validate_stickers(&post.sticker_data)
+                }
+                EntryTypes::Comment(comment) => validate_comment(&comment, &action.author),
+                EntryTypes::PostClap(clap) => {
+                    let result = validate_post_clap(&clap, &action.author)?;
+                    if result != ValidateCallbackResult::Valid {
+                        return Ok(result);
+                    }
+                    validate_post_clap_update(&clap, &original_action_hash)
+                }
+                EntryTypes::User(user) => validate_user_verification(&user, &action.author),
+                EntryTypes::TamagochiDeath(death) => validate_tamagochi_death(&death, &action.author),
+                EntryTypes::TamagochiInventory(inventory) => {
+                    let now = action.timestamp.as_seconds_since_epoch();
+                    validate_inventory(&inventory, &action.author, &action.prev_action, now, Some(&original_action_hash))
+                }
+                EntryTypes::Tamagochi(tamagochi) => {
+                    let now = action.timestamp.as_seconds_since_epoch();
+                    validate_tamagochi_update(&tamagochi, &action.author, &action.prev_action, now, &original_action_hash)
+                }
+                EntryTypes::UserPreferences(prefs) => validate_preferences(&prefs, &action.author),
+                _ => Ok(ValidateCallbackResult::Valid),
+            }
+        }
+        FlatOp::RegisterCreateLink { link_type, base_address, target_address, tag, action } => {
+            let now = action.timestamp.as_seconds_since_epoch();
+            match link_type {
+                LinkTypes::StoryToViews => {
+                    let story_hash = base_address
+                        .into_entry_hash()
+                        .ok_or(wasm_error!(WasmErrorInner::Guest("Story link base must be an entry".into())))?;
+                    validate_story_not_expired(&story_hash, now)
+                }
+                LinkTypes::UserToFriends => {
+                    if base_address != AnyLinkableHash::from(action.author.clone()) {
+                        return Ok(ValidateCallbackResult::Invalid("Only you can add to your own friend list".into()));
+                    }
+                    Ok(ValidateCallbackResult::Valid)
+                }
+                LinkTypes::UserToPreferences | LinkTypes::PreferencesUpdates => {
+                    validate_preferences_link(link_type, &base_address, &target_address, &action.author)
+                }
+                LinkTypes::GeoCellToAgents => {
+                    if target_address != AnyLinkableHash::from(action.author.clone()) {
+                        return Ok(ValidateCallbackResult::Invalid("You can only index your own location".into()));
+                    }
+                    Ok(ValidateCallbackResult::Valid)
+                }
+                LinkTypes::AgentToGeoCell => {
+                    if base_address != AnyLinkableHash::from(action.author.clone()) {
+                        return Ok(ValidateCallbackResult::Invalid("You can only index your own location".into()));
+                    }
+                    Ok(ValidateCallbackResult::Valid)
+                }
+                LinkTypes::VisitToResponse => validate_visit_response_link(&base_address, &target_address, &tag, &action.author),
+                LinkTypes::VisitToPlayClaims => validate_visit_play_claim_link(&base_address, &target_address, &action.author),
+                LinkTypes::PostUpdates => validate_post_update_link(&base_address, &target_address, &action.author),
+                LinkTypes::CommentUpdates => validate_comment_update_link(&base_address, &target_address, &action.author),
+                LinkTypes::InventoryUpdates => validate_inventory_update_link(&base_address, &target_address, &action.author),
+                LinkTypes::TamagochiDeathUpdates => validate_death_update_link(&base_address, &target_address, &action.author),
+                LinkTypes::RutIndex => validate_rut_index_link(&base_address, &target_address, tag, &action.author),
+                LinkTypes::TargetToReports => validate_report_link(&base_address, &target_address, tag, &action.author),
+                LinkTypes::AgentToReports => validate_agent_report_link(&base_address, &target_address, &action.author),
+                LinkTypes::TargetToModeration => validate_decision_link(&base_address, &target_address, &action.author),
+                LinkTypes::ModerationQueue => validate_queue_link(&base_address, &target_address, tag),
+                LinkTypes::ApprovedBanners => validate_approved_banner_link(&base_address, &target_address, tag, &action.author),
+                LinkTypes::AgentToRoleGrants => validate_role_grant_link(&base_address, &target_address, &action.author),
+                LinkTypes::RoleGrantToRevocations => validate_revocation_link(&base_address, &target_address, &action.author),
+                LinkTypes::BreedingProposalToResponse => validate_breeding_response_link(&base_address, &target_address, &tag, &action.author),
+                LinkTypes::EggToHatchling => validate_hatchling_link(&base_address, &target_address, &action.author),
+                LinkTypes::RideToRewards => validate_ride_reward_link(&base_address, &target_address, tag, &action.author),
+                LinkTypes::FriendRequestToResponse => validate_friend_response_link(&base_address, &target_address, &tag, &action.author),
+                LinkTypes::LeaderboardSnapshots | LinkTypes::UserToLeaderboardSnapshots => {
+                    validate_snapshot_link(link_type, &base_address, &target_address, tag, &action.author)
+                }
+                _ => Ok(ValidateCallbackResult::Valid),
+            }
+        }
+        // Either friend may end the friendship, removing both directions
+        FlatOp::RegisterDeleteLink { link_type: LinkTypes::UserToFriends, base_address, target_address, action, .. } => {
+            let author: AnyLinkableHash = action.author.clone().into();
+            if base_address != author && target_address != author {
+                return Ok(ValidateCallbackResult::Invalid("Only the two friends can remove a friendship".into()));
+            }
+            Ok(ValidateCallbackResult::Valid)
+        }
+        // Grants, revocations, blocks, reports and the moderation/RUT indexes are only withdrawn by whoever created them
+        FlatOp::RegisterDeleteLink {
+            link_type:
+                LinkTypes::AgentToRoleGrants
+                | LinkTypes::RoleGrantToRevocations
+                | LinkTypes::UserToBlocks
+                | LinkTypes::TargetToReports
+                | LinkTypes::ModerationQueue
+                | LinkTypes::RutIndex
+                | LinkTypes::ApprovedBanners,
+            original_action,
+            action,
+            ..
+        } => {
+            if original_action.author != action.author {
+                return Ok(ValidateCallbackResult::Invalid("Only the link's author can delete it".into()));
+            }
+            Ok(ValidateCallbackResult::Valid)
+        }
+        // Nobody deletes someone else's entries (posts, likes, blocks)
+        FlatOp::RegisterDelete(OpDelete { action }) => validate_original_author(&action.deletes_address, &action.author),
+        _ => Ok(ValidateCallbackResult::Valid),
+    }
+}
+
+// Updates and deletes must come from whoever created the original entry
+fn validate_original_author(original_action_hash: &ActionHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let original = must_get_action(original_action_hash.clone())?;
+    if original.action().author() != author {
+        return Ok(ValidateCallbackResult::Invalid("Only the original author can change or delete this entry".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_stickers(stickers: &[StickerData]) -> ExternResult<ValidateCallbackResult> {
+    match check_stickers(stickers) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(reason) => Ok(ValidateCallbackResult::Invalid(reason)),
+    }
+}
+
+fn validate_sticker_pack(pack: &StickerPack, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if pack.creator_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Sticker pack creator must be the action author".into()));
+    }
+    match check_sticker_pack(&pack.name, &pack.image_hashes) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(reason) => Ok(ValidateCallbackResult::Invalid(reason)),
+    }
+}
+
+/// Shared by validation and the coordinator so clients get the same error early
+pub fn check_stickers(stickers: &[StickerData]) -> Result<(), String> {
+    if stickers.len() > MAX_STICKERS {
+        return Err(format!("Too many stickers (max {})", MAX_STICKERS));
+    }
+    
+    for sticker in stickers {
+        let chars = sticker.content.chars().count();
+        match sticker.sticker_type {
+            StickerKind::Emoji => {
+                if chars == 0 || chars > MAX_STICKER_EMOJI_CHARS || sticker.content.chars().any(|c| c.is_ascii_alphanumeric()) {
+                    return Err("Invalid emoji sticker".into());
+                }
+            }
+            StickerKind::Image => {
+                if !is_image_hash(&sticker.content) {
+                    return Err("Image stickers must reference an uploaded image hash".into());
+                }
+            }
+            StickerKind::Text => {
+                if sticker.content.trim().is_empty() || chars > MAX_STICKER_TEXT_CHARS {
+                    return Err(format!("Text stickers must have 1-{} characters", MAX_STICKER_TEXT_CHARS));
+                }
+            }
+        }
+        
+        if !(0.0..=1.0).contains(&sticker.x) || !(0.0..=1.0).contains(&sticker.y) {
+            return Err("Sticker position must be between 0 and 1".into());
+        }
+        if !(0.1..=5.0).contains(&sticker.scale) {
+            return Err("Sticker scale must be between 0.1 and 5".into());
+        }
+        if !(-360.0..=360.0).contains(&sticker.rotation) {
+            return Err("Sticker rotation must be between -360 and 360 degrees".into());
+        }
+    }
+    
+    Ok(())
+}
+
+pub fn check_sticker_pack(name: &str, image_hashes: &[String]) -> Result<(), String> {
+    if name.trim().is_empty() || name.chars().count() > 50 {
+        return Err("Sticker pack name must have 1-50 characters".into());
+    }
+    if image_hashes.is_empty() || image_hashes.len() > MAX_STICKER_PACK_SIZE {
+        return Err(format!("Sticker packs need 1-{} stickers", MAX_STICKER_PACK_SIZE));
+    }
+    if !image_hashes.iter().all(|h| is_image_hash(h)) {
+        return Err("Sticker pack entries must be uploaded image hashes".into());
+    }
+    Ok(())
+}
+
+// Image hashes are hex-encoded SHA-256 (see calculate_image_hash)
+fn is_image_hash(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn validate_post_clap(clap: &PostClap, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if clap.user_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Claps must be given by the action author".into()));
+    }
+    if clap.count == 0 || clap.count > MAX_CLAPS_PER_USER {
+        return Ok(ValidateCallbackResult::Invalid(format!("Clap count must be 1-{}", MAX_CLAPS_PER_USER)));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Claps on a post only add up; the record can't move to another post
+fn validate_post_clap_update(clap: &PostClap, previous_action_hash: &ActionHash) -> ExternResult<ValidateCallbackResult> {
+    let record = must_get_valid_record(previous_action_hash.clone())?;
+    let previous = match record.entry().to_app_option::<PostClap>().ok().flatten() {
+        Some(previous) => previous,
+        None => return Ok(ValidateCallbackResult::Invalid("Clap update must replace a clap".into())),
+    };
+    if clap.post_hash != previous.post_hash || clap.user_id != previous.user_id {
+        return Ok(ValidateCallbackResult::Invalid("Clap update must keep its post and user".into()));
+    }
+    if clap.count <= previous.count {
+        return Ok(ValidateCallbackResult::Invalid("Clap count can only increase".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_report(report: &Report, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if report.reporter_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Reports must be signed by the reporter".into()));
+    }
+    if report.reported_agent == *author {
+        return Ok(ValidateCallbackResult::Invalid("Cannot report yourself".into()));
+    }
+    let result = validate_reported_agent(report)?;
+    if result != ValidateCallbackResult::Valid {
+        return Ok(result);
+    }
+    match check_report(&report.reason, &report.description, &report.evidence_image_hashes) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(reason) => Ok(ValidateCallbackResult::Invalid(reason)),
+    }
+}
+
+// The reported agent is the reported user, or whoever signed the reported content (messages: only their recipient reports)
+fn validate_reported_agent(report: &Report) -> ExternResult<ValidateCallbackResult> {
+    let entry_hash = match &report.target {
+        ReportTarget::User(agent) => {
+            if *agent != report.reported_agent {
+                return Ok(ValidateCallbackResult::Invalid("Reported agent must be the reported user".into()));
+            }
+            return Ok(ValidateCallbackResult::Valid);
+        }
+        ReportTarget::Post(hash)
+        | ReportTarget::Product(hash)
+        | ReportTarget::Service(hash)
+        | ReportTarget::Message(hash)
+        | ReportTarget::Wish(hash)
+        | ReportTarget::TamagochiVisit(hash) => hash,
+    };
+    let action_hash = match &report.target_action {
+        Some(hash) => hash.clone(),
+        None => return Ok(ValidateCallbackResult::Invalid("Reports must cite the action that created the content".into())),
+    };
+    let record = must_get_valid_record(action_hash)?;
+    if record.action().entry_hash() != Some(entry_hash) {
+        return Ok(ValidateCallbackResult::Invalid("Report action does not create the reported content".into()));
+    }
+    
+    let entry = record.entry();
+    let matches_kind = match &report.target {
+        ReportTarget::User(_) => false,
+        ReportTarget::Post(_) => entry.to_app_option::<Post>().ok().flatten().is_some(),
+        ReportTarget::Product(_) => entry.to_app_option::<Product>().ok().flatten().is_some(),
+        ReportTarget::Service(_) => entry.to_app_option::<Service>().ok().flatten().is_some(),
+        ReportTarget::Message(_) => match entry.to_app_option::<Message>().ok().flatten() {
+            Some(message) => message.receiver_id == report.reporter_id,
+            None => false,
+        },
+        ReportTarget::Wish(_) => entry.to_app_option::<Wish>().ok().flatten().is_some(),
+        ReportTarget::TamagochiVisit(_) => entry.to_app_option::<TamagochiVisit>().ok().flatten().is_some(),
+    };
+    if !matches_kind {
+        return Ok(ValidateCallbackResult::Invalid(format!("Reported content is not a {} the reporter can report", report.target.kind())));
+    }
+    if *record.action().author() != report.reported_agent {
+        return Ok(ValidateCallbackResult::Invalid("Reported agent must be the content's author".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+pub fn check_report(reason: &str, description: &Option<String>, evidence_image_hashes: &[String]) -> Result<(), String> {
+    if !REPORT_REASONS.contains(&reason) {
+        return Err("Invalid reason".into());
+    }
+    if description.as_ref().map_or(false, |d| d.chars().count() > MAX_REPORT_DESCRIPTION_CHARS) {
+        return Err(format!("Description is too long (max {} characters)", MAX_REPORT_DESCRIPTION_CHARS));
+    }
+    if evidence_image_hashes.len() > MAX_REPORT_EVIDENCE {
+        return Err(format!("Too many evidence images (max {})", MAX_REPORT_EVIDENCE));
+    }
+    if !evidence_image_hashes.iter().all(|h| is_image_hash(h)) {
+        return Err("Evidence must reference uploaded image hashes".into());
+    }
+    Ok(())
+}
+
+fn validate_moderation_decision(decision: &ModerationDecision, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if decision.moderator_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Moderation decisions must be signed by the moderator".into()));
+    }
+    validate_role_authority(author, &decision.authority, Role::Moderator, now)
+}
+
+// Reporters link their own report from the target it names, tagged with their key for the one-report check
+fn validate_report_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let report_hash = match target.clone().into_entry_hash() {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Report links must target a report".into())),
+    };
+    match Report::try_from(must_get_entry(report_hash)?.content) {
+        Ok(report) if report.reporter_id == *author && report.target.base() == *base && tag.into_inner() == author.get_raw_39().to_vec() => {
+            Ok(ValidateCallbackResult::Valid)
+        }
+        _ => Ok(ValidateCallbackResult::Invalid("Report link does not match the report".into())),
+    }
+}
+
+// Repeat-offender links come from the reporter and hang from the agent their report names
+fn validate_agent_report_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let report_hash = match target.clone().into_entry_hash() {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Report links must target a report".into())),
+    };
+    match Report::try_from(must_get_entry(report_hash)?.content) {
+        Ok(report) if report.reporter_id == *author && *base == AnyLinkableHash::from(report.reported_agent.clone()) => {
+            Ok(ValidateCallbackResult::Valid)
+        }
+        _ => Ok(ValidateCallbackResult::Invalid("Report link does not match the report".into())),
+    }
+}
+
+// Decisions hang only from the target they decide, linked by the moderator who signed them
+fn validate_decision_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let decision_hash = match target.clone().into_entry_hash() {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Moderation links must target a decision".into())),
+    };
+    let decision = match ModerationDecision::try_from(must_get_entry(decision_hash)?.content) {
+        Ok(decision) if decision.target.base() == *base => decision,
+        _ => return Ok(ValidateCallbackResult::Invalid("Moderation link does not match the decision".into())),
+    };
+    validate_moderation_decision(&decision, author, decision.decided_at)
+}
+
+// Queue entries point from the queue anchor at a target whose kind is in the tag
+fn validate_queue_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: LinkTag) -> ExternResult<ValidateCallbackResult> {
+    if *base != AnyLinkableHash::from(Path::from(MODERATION_QUEUE_ROOT).path_entry_hash()?) {
+        return Ok(ValidateCallbackResult::Invalid("Queue links hang from the moderation queue".into()));
+    }
+    let kind = String::from_utf8(tag.into_inner()).unwrap_or_default();
+    if ReportTarget::from_parts(&kind, target.clone()).is_none() {
+        return Ok(ValidateCallbackResult::Invalid("Queue link tag must name the target's kind".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_banner_approval(approval: &BannerApproval, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if approval.approver_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Banner approvals must be signed by the approver".into()));
+    }
+    if AdBanner::try_from(must_get_entry(approval.banner_hash.clone())?.content).is_err() {
+        return Ok(ValidateCallbackResult::Invalid("Only ad banners can be approved".into()));
+    }
+    validate_role_authority(author, &approval.authority, Role::Moderator, now)
+}
+
+// Listing a banner cites the approval its author signed for it
+fn validate_approved_banner_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if *base != AnyLinkableHash::from(Path::from(ACTIVE_BANNERS_ROOT).path_entry_hash()?) {
+        return Ok(ValidateCallbackResult::Invalid("Approved banners hang from the banner list".into()));
+    }
+    let approval_hash = match ActionHash::try_from_raw_39(tag.into_inner()) {
+        Ok(hash) => hash,
+        Err(_) => return Ok(ValidateCallbackResult::Invalid("Banner listings must cite an approval".into())),
+    };
+    let record = must_get_valid_record(approval_hash)?;
+    match record.entry().to_app_option::<BannerApproval>().ok().flatten() {
+        Some(approval) if approval.approver_id == *author && *target == AnyLinkableHash::from(approval.banner_hash.clone()) => {
+            Ok(ValidateCallbackResult::Valid)
+        }
+        _ => Ok(ValidateCallbackResult::Invalid("Banner listing does not match the approval".into())),
+    }
+}
+
+// Only a user holding an approved attestation issued to them may present as verified
+fn validate_user_verification(user: &User, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if !user.is_verified {
+        if user.rut_masked.is_some() {
+            return Ok(ValidateCallbackResult::Invalid("Only verified users show a RUT".into()));
+        }
+        return Ok(ValidateCallbackResult::Valid);
+    }
+    let attestation_hash = match &user.verification_hash {
+        Some(hash) => hash.clone(),
+        None => return Ok(ValidateCallbackResult::Invalid("Verified users need a verification attestation".into())),
+    };
+    let record = must_get_valid_record(attestation_hash)?;
+    let attestation = match record.entry().to_app_option::<VerificationAttestation>().ok().flatten() {
+        Some(attestation) => attestation,
+        None => return Ok(ValidateCallbackResult::Invalid("Verification hash is not an attestation".into())),
+    };
+    // The keyed RUT hash lives in the attestation, so it is owned by the user it names
+    if attestation.user_id != *author || attestation.status != VerificationStatus::Approved || attestation.rut_key_hash.is_none() {
+        return Ok(ValidateCallbackResult::Invalid("Verification attestation does not approve this user".into()));
+    }
+    // The displayed RUT is the one the verifier masked
+    if user.rut_masked != attestation.rut_masked {
+        return Ok(ValidateCallbackResult::Invalid("Masked RUT does not match the attestation".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Only the verified owner claims a RUT, citing the attestation that carries its hash
+fn validate_rut_index_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if *target != AnyLinkableHash::from(author.clone()) {
+        return Ok(ValidateCallbackResult::Invalid("A RUT can only be claimed for yourself".into()));
+    }
+    let attestation_hash = match ActionHash::try_from_raw_39(tag.into_inner()) {
+        Ok(hash) => hash,
+        Err(_) => return Ok(ValidateCallbackResult::Invalid("RUT claims must cite an attestation".into())),
+    };
+    let record = must_get_valid_record(attestation_hash)?;
+    let attestation = match record.entry().to_app_option::<VerificationAttestation>().ok().flatten() {
+        Some(attestation) if attestation.user_id == *author && attestation.status == VerificationStatus::Approved => attestation,
+        _ => return Ok(ValidateCallbackResult::Invalid("RUT claims need an approved attestation for you".into())),
+    };
+    match attestation.rut_key_hash {
+        Some(rut_key_hash) if *base == AnyLinkableHash::from(rut_index_path(&rut_key_hash)?) => Ok(ValidateCallbackResult::Valid),
+        _ => Ok(ValidateCallbackResult::Invalid("RUT claim does not match the attestation".into())),
+    }
+}
+
+fn validate_verification_request(request: &VerificationRequest, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if request.user_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Verification requests must be made by the user".into()));
+    }
+    if !is_image_hash(&request.id_card_image_hash) {
+        return Ok(ValidateCallbackResult::Invalid("ID card must reference an uploaded image hash".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_verification_attestation(attestation: &VerificationAttestation, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if attestation.verifier_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Attestations must be signed by the verifier".into()));
+    }
+    if attestation.user_id == *author {
+        return Ok(ValidateCallbackResult::Invalid("Cannot verify yourself".into()));
+    }
+    let record = must_get_valid_record(attestation.request_hash.clone())?;
+    match record.entry().to_app_option::<VerificationRequest>().ok().flatten() {
+        Some(request) if request.user_id == attestation.user_id => {}
+        _ => return Ok(ValidateCallbackResult::Invalid("Attestation does not match a request from this user".into())),
+    }
+    // Approvals carry the keyed RUT hash (hex SHA-256, same shape as an image hash); rejections carry none
+    let rut_ok = match attestation.status {
+        VerificationStatus::Approved => {
+            attestation.rut_key_hash.as_deref().map_or(false, is_image_hash) && attestation.rut_masked.as_deref().map_or(false, is_masked_rut)
+        }
+        VerificationStatus::Rejected => attestation.rut_key_hash.is_none() && attestation.rut_masked.is_none(),
+    };
+    if !rut_ok {
+        return Ok(ValidateCallbackResult::Invalid("Approvals need the keyed RUT hash and the masked RUT".into()));
+    }
+    validate_role_authority(author, &attestation.authority, Role::Verifier, now)
+}
+
+// Shape of mask_rut's output: leading digits, hidden middle, verifier digit ("12.***.***-K")
+fn is_masked_rut(value: &str) -> bool {
+    match value.split_once(".***.***-") {
+        Some((lead, dv)) => {
+            (1..=2).contains(&lead.len())
+                && lead.chars().all(|c| c.is_ascii_digit())
+                && dv.len() == 1
+                && dv.chars().all(|c| c.is_ascii_digit() || c == 'K')
+        }
+        None => false,
+    }
+}
+
+fn validate_tamagochi_death(death: &TamagochiDeath, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if death.owner_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Death records belong to the owner".into()));
+    }
+    if death.epitaph.as_ref().map_or(false, |e| e.chars().count() > MAX_EPITAPH_CHARS) {
+        return Ok(ValidateCallbackResult::Invalid(format!("Epitaph is too long (max {} characters)", MAX_EPITAPH_CHARS)));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_tamagochi_memorial(memorial: &TamagochiMemorial, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if memorial.author_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Memorials must be signed by their author".into()));
+    }
+    let chars = memorial.message.trim().chars().count();
+    if chars == 0 || chars > MAX_MEMORIAL_CHARS {
+        return Ok(ValidateCallbackResult::Invalid(format!("Memorial messages must have 1-{} characters", MAX_MEMORIAL_CHARS)));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_tamagochi_visit(visit: &TamagochiVisit, author: &AgentPubKey, prev_action: &ActionHash, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if visit.visitor_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Visits must be signed by the visitor".into()));
+    }
+    if visit.tamagochi_owner_id == *author {
+        return Ok(ValidateCallbackResult::Invalid("Cannot visit your own tamagochi".into()));
+    }
+    if (visit.interaction == Some(VisitInteraction::Gift)) != visit.gift_item_id.is_some() {
+        return Ok(ValidateCallbackResult::Invalid("Gift visits must carry exactly one item".into()));
+    }
+    
+    // Rate limit from the visitor's own chain, which deleting links can't reset
+    let since = Timestamp::from_micros((now - 24 * 3600) * 1_000_000);
+    let activity = must_get_agent_activity(author.clone(), ChainFilter::new(prev_action.clone()).until_timestamp(since))?;
+    let visit_type: EntryType = UnitEntryTypes::TamagochiVisit.try_into()?;
+    let mut visits_today = 0;
+    for item in activity {
+        let action = item.action.action();
+        let elapsed = now - action.timestamp().as_seconds_since_epoch();
+        if action.entry_type() != Some(&visit_type) || elapsed >= 24 * 3600 {
+            continue;
+        }
+        visits_today += 1;
+        if elapsed < VISIT_COOLDOWN_SECONDS {
+            if let Some(entry_hash) = action.entry_hash() {
+                if let Ok(earlier) = TamagochiVisit::try_from(must_get_entry(entry_hash.clone())?.content) {
+                    if earlier.tamagochi_owner_id == visit.tamagochi_owner_id {
+                        return Ok(ValidateCallbackResult::Invalid("You visited this tamagochi recently".into()));
+                    }
+                }
+            }
+        }
+    }
+    if visits_today >= MAX_DAILY_VISITS {
+        return Ok(ValidateCallbackResult::Invalid("Daily visit limit reached".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn get_visit(visit_hash: &AnyLinkableHash) -> ExternResult<Option<TamagochiVisit>> {
+    match visit_hash.clone().into_entry_hash() {
+        Some(hash) => Ok(TamagochiVisit::try_from(must_get_entry(hash)?.content).ok()),
+        None => Ok(None),
+    }
+}
+
+// Only the visited pet's owner answers a visit, pointing the answer at themselves
+fn validate_visit_response_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: &LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    match get_visit(base)? {
+        Some(visit) if visit.tamagochi_owner_id == *author && *target == AnyLinkableHash::from(author.clone()) => {}
+        _ => return Ok(ValidateCallbackResult::Invalid("Only the owner can respond to this visit".into())),
+    }
+    if tag.as_ref() != b"accepted" && tag.as_ref() != b"declined" {
+        return Ok(ValidateCallbackResult::Invalid("Visit responses are accepted or declined".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_ride_request(ride: &RideRequest, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if ride.requester_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Rides are requested by their passenger".into()));
+    }
+    if ride.driver_id.as_ref() == Some(author) {
+        return Ok(ValidateCallbackResult::Invalid("Cannot drive your own ride".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Requester and driver each collect for themselves, only on a completed ride with a driver
+fn validate_ride_reward_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let ride = match base.clone().into_entry_hash() {
+        Some(hash) => RideRequest::try_from(must_get_entry(hash)?.content).ok(),
+        None => None,
+    };
+    let ride = match ride {
+        Some(ride) if ride.status == "completed" => ride,
+        _ => return Ok(ValidateCallbackResult::Invalid("Ride rewards need a completed ride".into())),
+    };
+    let driver_id = match &ride.driver_id {
+        Some(driver_id) if *driver_id != ride.requester_id => driver_id,
+        _ => return Ok(ValidateCallbackResult::Invalid("Ride rewards need a ride with a driver".into())),
+    };
+    if ride.requester_id != *author && driver_id != author {
+        return Ok(ValidateCallbackResult::Invalid("You were not part of this ride".into()));
+    }
+    if *target != AnyLinkableHash::from(author.clone()) || tag.into_inner() != author.get_raw_39().to_vec() {
+        return Ok(ValidateCallbackResult::Invalid("Ride rewards are collected for yourself".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Only the recipient answers a friend request
+fn validate_friend_response_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: &LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let request = match base.clone().into_action_hash() {
+        Some(hash) => must_get_valid_record(hash)?.entry().to_app_option::<FriendRequest>().ok().flatten(),
+        None => None,
+    };
+    match request {
+        Some(request) if request.recipient_id == *author && *target == AnyLinkableHash::from(author.clone()) => {}
+        _ => return Ok(ValidateCallbackResult::Invalid("Only the recipient can answer this friend request".into())),
+    }
+    if tag.as_ref() != b"accepted" && tag.as_ref() != b"declined" {
+        return Ok(ValidateCallbackResult::Invalid("Friend request responses are accepted or declined".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Only the invited partner answers a breeding proposal
+fn validate_breeding_response_link(base: &AnyLinkableHash, target: &AnyLinkableHash, tag: &LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let proposal = match base.clone().into_action_hash() {
+        Some(hash) => must_get_valid_record(hash)?.entry().to_app_option::<BreedingProposal>().ok().flatten(),
+        None => None,
+    };
+    match proposal {
+        Some(proposal) if proposal.partner_id == *author && *target == AnyLinkableHash::from(author.clone()) => {}
+        _ => return Ok(ValidateCallbackResult::Invalid("Only the invited partner can respond to this proposal".into())),
+    }
+    if tag.as_ref() != b"accepted" && tag.as_ref() != b"declined" {
+        return Ok(ValidateCallbackResult::Invalid("Breeding responses are accepted or declined".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// The coordinator checks the owner accepted; here, only the visitor claims for themselves
+fn validate_visit_play_claim_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    match get_visit(base)? {
+        Some(visit) if visit.visitor_id == *author && *target == AnyLinkableHash::from(author.clone()) => Ok(ValidateCallbackResult::Valid),
+        _ => Ok(ValidateCallbackResult::Invalid("Only the visitor can claim this visit".into())),
+    }
+}
+
+// Founders are common; anything else must come from an egg owned by the author
+fn validate_tamagochi_genome(tamagochi: &Tamagochi, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if tamagochi.owner_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Tamagochis belong to whoever creates them".into()));
+    }
+    let genome = match &tamagochi.genome {
+        Some(genome) => genome,
+        None if tamagochi.bred_from.is_none() => return Ok(ValidateCallbackResult::Valid),
+        None => return Ok(ValidateCallbackResult::Invalid("Hatched pets carry their egg's genome".into())),
+    };
+    if Genome::from_engine(&genome.to_engine()) != *genome {
+        return Ok(ValidateCallbackResult::Invalid("Unknown or inconsistent genome traits".into()));
+    }
+    let growth = [genome.experience_growth, genome.happiness_growth, genome.energy_growth];
+    if growth.iter().any(|g| !(tamagochi::MIN_GROWTH..=tamagochi::MAX_GROWTH).contains(g)) {
+        return Ok(ValidateCallbackResult::Invalid("Growth out of range".into()));
+    }
+    
+    match &tamagochi.bred_from {
+        None => {
+            if genome.generation != 0 || genome.to_engine().rarity() != tamagochi::Rarity::Common {
+                return Ok(ValidateCallbackResult::Invalid("Only bred pets can have inherited traits".into()));
+            }
+            if growth.iter().any(|g| g.abs() > tamagochi::GROWTH_DRIFT) {
+                return Ok(ValidateCallbackResult::Invalid("Founder growth out of range".into()));
+            }
+        }
+        Some(breeding_hash) => {
+            let record = must_get_valid_record(breeding_hash.clone())?;
+            match record.entry().to_app_option::<BreedingRecord>().ok().flatten() {
+                Some(breeding) if breeding.egg_owner_id == *author && breeding.genome == *genome => {}
+                _ => return Ok(ValidateCallbackResult::Invalid("Genome does not match the egg it hatched from".into())),
+            }
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Every pet starts as a fresh egg; growing up only happens in validated updates
+fn validate_new_tamagochi(tamagochi: &Tamagochi, author: &AgentPubKey, prev_action: &ActionHash, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if tamagochi.stage != tamagochi::Stage::Egg.as_str()
+        || tamagochi.experience != 0
+        || tamagochi.level != tamagochi::Stage::Egg.level()
+        || !tamagochi.is_alive
+        || tamagochi.previous.is_some()
+    {
+        return Ok(ValidateCallbackResult::Invalid("New tamagochis start as a fresh egg".into()));
+    }
+    if tamagochi.born_at > now || now - tamagochi.born_at > 3600 || tamagochi.last_updated_at != tamagochi.born_at {
+        return Ok(ValidateCallbackResult::Invalid("A tamagochi is born when it is created".into()));
+    }
+    
+    let result = validate_tamagochi_genome(tamagochi, author)?;
+    if result != ValidateCallbackResult::Valid {
+        return Ok(result);
+    }
+    validate_hatch(tamagochi, author, prev_action)
+}
+
+// Genes, origin and birth are fixed; each version builds on the newest one and moves
+// the simulation at most as far as one update can
+fn validate_tamagochi_update(tamagochi: &Tamagochi, author: &AgentPubKey, prev_action: &ActionHash, now: i64, original_action_hash: &ActionHash) -> ExternResult<ValidateCallbackResult> {
+    let original_record = must_get_valid_record(original_action_hash.clone())?;
+    let (tamagochi_hash, original) = match (original_record.action().entry_hash(), original_record.entry().to_app_option::<Tamagochi>().ok().flatten()) {
+        (Some(hash), Some(original)) => (hash.clone(), original),
+        _ => return Ok(ValidateCallbackResult::Invalid("Only tamagochis can be updated into tamagochis".into())),
+    };
+    if tamagochi.owner_id != original.owner_id
+        || tamagochi.genome != original.genome
+        || tamagochi.bred_from != original.bred_from
+        || tamagochi.born_at != original.born_at
+    {
+        return Ok(ValidateCallbackResult::Invalid("A tamagochi's owner, genes and origin can't change".into()));
+    }
+    
+    let previous_hash = match &tamagochi.previous {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Tamagochi updates must cite their previous version".into())),
+    };
+    let previous = match get_pet_version(&tamagochi_hash, previous_hash, author)? {
+        Some(previous) => previous,
+        None => return Ok(ValidateCallbackResult::Invalid("Previous tamagochi version not found".into())),
+    };
+    let activity = must_get_agent_activity(author.clone(), ChainFilter::new(prev_action.clone()).until_hash(previous_hash.clone()))?;
+    if activity.iter().any(|item| item.action.as_hash() != previous_hash && is_pet_version(item.action.action(), &tamagochi_hash)) {
+        return Ok(ValidateCallbackResult::Invalid("Tamagochi versions must build on the newest one".into()));
+    }
+    
+    if tamagochi.experience < previous.experience || tamagochi.experience - previous.experience > tamagochi::max_experience_gain() {
+        return Ok(ValidateCallbackResult::Invalid("Too much experience for one update".into()));
+    }
+    // Mascotas anteriores al motor pueden tener más etapa que experiencia; no retroceden
+    let previous_stage = tamagochi::Stage::parse(&previous.stage).unwrap_or(tamagochi::Stage::Egg);
+    let reached = tamagochi::Stage::for_experience(tamagochi.experience).max(previous_stage);
+    match tamagochi::Stage::parse(&tamagochi.stage) {
+        Some(stage) if stage >= previous_stage && stage <= reached && tamagochi.level == stage.level() => {}
+        _ => return Ok(ValidateCallbackResult::Invalid("Stage does not follow from experience".into())),
+    }
+    if tamagochi.is_alive && !previous.is_alive {
+        return Ok(ValidateCallbackResult::Invalid("Dead tamagochis stay dead".into()));
+    }
+    if tamagochi.last_updated_at < previous.last_updated_at || tamagochi.last_updated_at > now {
+        return Ok(ValidateCallbackResult::Invalid("Simulation time must move forward".into()));
+    }
+    
+    // Stats stay in range and care times only move forward, up to now
+    let stats = [tamagochi.energy, tamagochi.hunger, tamagochi.hygiene, tamagochi.happiness];
+    if stats.iter().any(|stat| *stat > tamagochi::MAX_STAT) {
+        return Ok(ValidateCallbackResult::Invalid(format!("Stats go from 0 to {}", tamagochi::MAX_STAT)));
+    }
+    let care_times = [
+        (tamagochi.last_fed_at, previous.last_fed_at),
+        (tamagochi.last_cleaned_at, previous.last_cleaned_at),
+        (tamagochi.last_played_at, previous.last_played_at),
+    ];
+    if care_times.iter().any(|(at, before)| at < before || *at > now) {
+        return Ok(ValidateCallbackResult::Invalid("Care times must move forward".into()));
+    }
+    // Leaderboards copy the streak, so it only grows by one per new care day
+    let today = now.div_euclid(tamagochi::DAY_SECONDS);
+    if !tamagochi::follows_care_streak((previous.care_streak, previous.last_care_day), (tamagochi.care_streak, tamagochi.last_care_day), today) {
+        return Ok(ValidateCallbackResult::Invalid("Care streak can only grow by one per day of care".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// An egg hatches once: no earlier pet on the author's chain came from it
+fn validate_hatch(tamagochi: &Tamagochi, author: &AgentPubKey, prev_action: &ActionHash) -> ExternResult<ValidateCallbackResult> {
+    let breeding_hash = match &tamagochi.bred_from {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Valid),
+    };
+    let laid_at = must_get_valid_record(breeding_hash.clone())?.action().timestamp();
+    
+    let activity = must_get_agent_activity(author.clone(), ChainFilter::new(prev_action.clone()).until_timestamp(laid_at))?;
+    let tamagochi_type: EntryType = UnitEntryTypes::Tamagochi.try_into()?;
+    for item in activity {
+        let action = item.action.action();
+        if !matches!(action, Action::Create(_)) || action.entry_type() != Some(&tamagochi_type) {
+            continue;
+        }
+        if let Some(entry_hash) = action.entry_hash() {
+            if let Ok(earlier) = Tamagochi::try_from(must_get_entry(entry_hash.clone())?.content) {
+                if earlier.bred_from.as_ref() == Some(breeding_hash) {
+                    return Ok(ValidateCallbackResult::Invalid("This egg already hatched".into()));
+                }
+            }
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Only the egg's owner links it to the pet that hatched from it
+fn validate_hatchling_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let breeding_hash = match base.clone().into_action_hash() {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Hatchling links start at an egg".into())),
+    };
+    let breeding = must_get_valid_record(breeding_hash.clone())?.entry().to_app_option::<BreedingRecord>().ok().flatten();
+    let hatchling = match target.clone().into_entry_hash() {
+        Some(hash) => Tamagochi::try_from(must_get_entry(hash)?.content).ok(),
+        None => None,
+    };
+    match (breeding, hatchling) {
+        (Some(breeding), Some(hatchling))
+            if breeding.egg_owner_id == *author && hatchling.owner_id == *author && hatchling.bred_from == Some(breeding_hash) =>
+        {
+            Ok(ValidateCallbackResult::Valid)
+        }
+        _ => Ok(ValidateCallbackResult::Invalid("Only the egg's owner can hatch it".into())),
+    }
+}
+
+/// Whether `action` writes a version of the pet first created as `tamagochi_hash`
+pub fn is_pet_version(action: &Action, tamagochi_hash: &EntryHash) -> bool {
+    match action {
+        Action::Create(create) => create.entry_hash == *tamagochi_hash,
+        Action::Update(update) => update.original_entry_address == *tamagochi_hash,
+        _ => false,
+    }
+}
+
+// The pet at `version` (a Create or Update of `tamagochi_hash`) if `owner` wrote it
+fn get_pet_version(tamagochi_hash: &EntryHash, version: &ActionHash, owner: &AgentPubKey) -> ExternResult<Option<Tamagochi>> {
+    let record = must_get_valid_record(version.clone())?;
+    if !is_pet_version(record.action(), tamagochi_hash) || record.action().author() != owner {
+        return Ok(None);
+    }
+    Ok(record.entry().to_app_option::<Tamagochi>().ok().flatten().filter(|tamagochi| tamagochi.owner_id == *owner))
+}
+
+// Proposing with a pet or accepting with it both count as breeding it, so the owner's
+// own chain shows whether it is still resting
+fn is_resting(author: &AgentPubKey, prev_action: &ActionHash, tamagochi_hash: &EntryHash, now: i64) -> ExternResult<bool> {
+    let since = Timestamp::from_micros((now - BREEDING_COOLDOWN_SECONDS) * 1_000_000);
+    let activity = must_get_agent_activity(author.clone(), ChainFilter::new(prev_action.clone()).until_timestamp(since))?;
+    let proposal_type: EntryType = UnitEntryTypes::BreedingProposal.try_into()?;
+    let record_type: EntryType = UnitEntryTypes::BreedingRecord.try_into()?;
+    
+    for item in activity {
+        let action = item.action.action();
+        if now - action.timestamp().as_seconds_since_epoch() >= BREEDING_COOLDOWN_SECONDS {
+            continue;
+        }
+        let entry_hash = match action.entry_hash() {
+            Some(hash) => hash.clone(),
+            None => continue,
+        };
+        if action.entry_type() == Some(&proposal_type) {
+            if let Ok(proposal) = BreedingProposal::try_from(must_get_entry(entry_hash)?.content) {
+                if proposal.proposer_tamagochi == *tamagochi_hash {
+                    return Ok(true);
+                }
+            }
+        } else if action.entry_type() == Some(&record_type) {
+            if let Ok(record) = BreedingRecord::try_from(must_get_entry(entry_hash)?.content) {
+                let proposal = must_get_valid_record(record.proposal_hash)?.entry().to_app_option::<BreedingProposal>().ok().flatten();
+                if proposal.map_or(false, |p| p.partner_tamagochi == *tamagochi_hash) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+fn validate_breeding_proposal(proposal: &BreedingProposal, author: &AgentPubKey, prev_action: &ActionHash, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if proposal.proposer_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Breeding proposals must be signed by the proposer".into()));
+    }
+    if proposal.partner_id == *author || proposal.proposer_tamagochi == proposal.partner_tamagochi {
+        return Ok(ValidateCallbackResult::Invalid("Breeding needs two different owners".into()));
+    }
+    if proposal.egg_owner_id != proposal.proposer_id && proposal.egg_owner_id != proposal.partner_id {
+        return Ok(ValidateCallbackResult::Invalid("The egg must go to one of the owners".into()));
+    }
+    if proposal.proposed_at > now || now - proposal.proposed_at > 3600 {
+        return Ok(ValidateCallbackResult::Invalid("Proposal time must be when it was made".into()));
+    }
+    
+    // Adult is the last stage, so any adult version proves it
+    let adult = tamagochi::Stage::Adult.as_str();
+    for (tamagochi_hash, version, owner) in [
+        (&proposal.proposer_tamagochi, &proposal.proposer_version, &proposal.proposer_id),
+        (&proposal.partner_tamagochi, &proposal.partner_version, &proposal.partner_id),
+    ] {
+        match get_pet_version(tamagochi_hash, version, owner)? {
+            Some(tamagochi) if tamagochi.stage == adult => {}
+            _ => return Ok(ValidateCallbackResult::Invalid("Both pets must be adults of their owners".into())),
+        }
+    }
+    if is_resting(author, prev_action, &proposal.proposer_tamagochi, now)? {
+        return Ok(ValidateCallbackResult::Invalid("Your tamagochi needs to rest before breeding again".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// The child genome is recomputed from both parents, so rarity can't be forged
+fn validate_breeding_record(record: &BreedingRecord, author: &AgentPubKey, prev_action: &ActionHash, now: i64) -> ExternResult<ValidateCallbackResult> {
+    let proposal_record = must_get_valid_record(record.proposal_hash.clone())?;
+    let proposal = match proposal_record.entry().to_app_option::<BreedingProposal>().ok().flatten() {
+        Some(proposal) => proposal,
+        None => return Ok(ValidateCallbackResult::Invalid("Breeding record must cite a proposal".into())),
+    };
+    if proposal.partner_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Only the invited partner can accept a breeding".into()));
+    }
+    if record.proposer_id != proposal.proposer_id
+        || record.partner_id != proposal.partner_id
+        || record.egg_owner_id != proposal.egg_owner_id
+    {
+        return Ok(ValidateCallbackResult::Invalid("Breeding record does not match its proposal".into()));
+    }
+    if record.bred_at > now || now - record.bred_at > 3600 || record.bred_at - proposal.proposed_at > BREEDING_PROPOSAL_TTL_SECONDS {
+        return Ok(ValidateCallbackResult::Invalid("This proposal has expired".into()));
+    }
+    if is_resting(author, prev_action, &proposal.partner_tamagochi, now)? {
+        return Ok(ValidateCallbackResult::Invalid("Your tamagochi needs to rest before breeding again".into()));
+    }
+    
+    let mut parents = Vec::new();
+    for tamagochi_hash in [&proposal.proposer_tamagochi, &proposal.partner_tamagochi] {
+        let entry = must_get_entry(tamagochi_hash.clone())?;
+        match Tamagochi::try_from(entry.content) {
+            Ok(tamagochi) => parents.push(tamagochi.genome.map(|g| g.to_engine()).unwrap_or_default()),
+            Err(_) => return Ok(ValidateCallbackResult::Invalid("Breeding parents must be tamagochis".into())),
+        }
+    }
+    let child = tamagochi::breed(&parents[0], &parents[1], record.proposal_hash.get_raw_39());
+    if record.genome != Genome::from_engine(&child) {
+        return Ok(ValidateCallbackResult::Invalid("Child genome does not follow from its parents".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_preferences(prefs: &UserPreferences, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if prefs.owner_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Preferences belong to their owner".into()));
+    }
+    match check_preferences(prefs) {
+        Ok(()) => Ok(ValidateCallbackResult::Valid),
+        Err(reason) => Ok(ValidateCallbackResult::Invalid(reason)),
+    }
+}
+
+// Owners link their own preferences: from themselves, and from the original to each update
+fn validate_preferences_link(link_type: LinkTypes, base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let mut entries = vec![target];
+    match link_type {
+        LinkTypes::PreferencesUpdates => entries.push(base),
+        _ => {
+            if *base != AnyLinkableHash::from(author.clone()) {
+                return Ok(ValidateCallbackResult::Invalid("Preferences hang from their owner".into()));
+            }
+        }
+    }
+    
+    for hash in entries {
+        let prefs_hash = match hash.clone().into_entry_hash() {
+            Some(hash) => hash,
+            None => return Ok(ValidateCallbackResult::Invalid("Preference links join preference entries".into())),
+        };
+        match UserPreferences::try_from(must_get_entry(prefs_hash)?.content) {
+            Ok(prefs) if prefs.owner_id == *author => {}
+            _ => return Ok(ValidateCallbackResult::Invalid("Only the owner can link their preferences".into())),
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_friend_request(request: &FriendRequest, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if request.sender_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Friend requests must be signed by the sender".into()));
+    }
+    if request.recipient_id == *author {
+        return Ok(ValidateCallbackResult::Invalid("Cannot befriend yourself".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_achievement_unlock(unlock: &AchievementUnlock, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if unlock.agent_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Achievements are unlocked by their owner".into()));
+    }
+    let achievement = match find_achievement(&unlock.achievement_id) {
+        Some(achievement) => achievement,
+        None => return Ok(ValidateCallbackResult::Invalid(format!("Unknown achievement: {}", unlock.achievement_id))),
+    };
+    
+    if (unlock.evidence.len() as u32) < achievement.required {
+        return Ok(ValidateCallbackResult::Invalid(format!("{} needs {} pieces of evidence", achievement.id, achievement.required)));
+    }
+    
+    let mut keys: Vec<AnyLinkableHash> = Vec::new();
+    for hash in &unlock.evidence {
+        let record = must_get_valid_record(hash.clone())?;
+        let key = match achievement_evidence_key(achievement.evidence, &record)? {
+            Some(key) if record.action().author() == author => key,
+            _ => return Ok(ValidateCallbackResult::Invalid("Achievement evidence must come from the agent's own chain".into())),
+        };
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    if (keys.len() as u32) < achievement.required {
+        return Ok(ValidateCallbackResult::Invalid(format!("{} needs {} distinct pieces of evidence", achievement.id, achievement.required)));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_leaderboard_snapshot(snapshot: &LeaderboardSnapshot, author: &AgentPubKey, prev_action: &ActionHash, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if snapshot.owner_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Leaderboard snapshots are published by the pet owner".into()));
+    }
+    if snapshot.lat.is_some() != snapshot.lon.is_some() {
+        return Ok(ValidateCallbackResult::Invalid("Snapshot location needs both lat and lon".into()));
+    }
+    if snapshot.taken_at > now || now - snapshot.taken_at > 3600 {
+        return Ok(ValidateCallbackResult::Invalid("Snapshot time must be when it was published".into()));
+    }
+    
+    // Stats are copied from the cited version of the owner's pet, never typed in
+    let tamagochi = match get_pet_version(&snapshot.tamagochi_hash, &snapshot.tamagochi_version, author)? {
+        Some(tamagochi) if tamagochi.is_alive => tamagochi,
+        _ => return Ok(ValidateCallbackResult::Invalid("Snapshot must cite a version of your living pet".into())),
+    };
+    if snapshot.tamagochi_name != tamagochi.name
+        || snapshot.stage != tamagochi.stage
+        || snapshot.level != tamagochi.level
+        || snapshot.experience != tamagochi.experience
+        || snapshot.born_at != tamagochi.born_at
+        || snapshot.care_streak != tamagochi.care_streak
+        || snapshot.last_care_day != tamagochi.last_care_day
+    {
+        return Ok(ValidateCallbackResult::Invalid("Snapshot does not match the pet".into()));
+    }
+    
+    // An older version would let a stale (or dead) pet keep its rank
+    let activity = must_get_agent_activity(author.clone(), ChainFilter::new(prev_action.clone()).until_hash(snapshot.tamagochi_version.clone()))?;
+    let tamagochi_type: EntryType = UnitEntryTypes::Tamagochi.try_into()?;
+    if activity.iter().any(|item| *item.action.as_hash() != snapshot.tamagochi_version && item.action.action().entry_type() == Some(&tamagochi_type)) {
+        return Ok(ValidateCallbackResult::Invalid("Snapshots must copy the newest version of the pet".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Board links point at the author's own snapshot: from its day's bucket (tagged with the owner) or from the owner
+fn validate_snapshot_link(link_type: LinkTypes, base: &AnyLinkableHash, target: &AnyLinkableHash, tag: LinkTag, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let snapshot = match target.clone().into_entry_hash() {
+        Some(hash) => LeaderboardSnapshot::try_from(must_get_entry(hash)?.content).ok(),
+        None => None,
+    };
+    let snapshot = match snapshot {
+        Some(snapshot) if snapshot.owner_id == *author => snapshot,
+        _ => return Ok(ValidateCallbackResult::Invalid("Leaderboard links must point at your own snapshot".into())),
+    };
+    
+    let expected_base = match link_type {
+        LinkTypes::LeaderboardSnapshots => {
+            if tag.into_inner() != author.get_raw_39().to_vec() {
+                return Ok(ValidateCallbackResult::Invalid("Leaderboard links are tagged with their owner".into()));
+            }
+            AnyLinkableHash::from(leaderboard_bucket_path(snapshot.taken_at.div_euclid(24 * 3600))?)
+        }
+        _ => AnyLinkableHash::from(author.clone()),
+    };
+    if *base != expected_base {
+        return Ok(ValidateCallbackResult::Invalid("Snapshot linked from the wrong place".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Each version replays its change on top of the newest previous one on the author's chain
+fn validate_inventory(inventory: &TamagochiInventory, author: &AgentPubKey, prev_action: &ActionHash, now: i64, original: Option<&ActionHash>) -> ExternResult<ValidateCallbackResult> {
+    if inventory.owner_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Inventories belong to their owner".into()));
+    }
+    
+    let previous = match (&inventory.previous, original) {
+        (None, None) => TamagochiInventory::empty(author.clone()),
+        (Some(previous_hash), Some(original_hash)) => {
+            let record = must_get_valid_record(previous_hash.clone())?;
+            let same_chain = previous_hash == original_hash
+                || matches!(record.action(), Action::Update(update) if update.original_action_address == *original_hash);
+            let previous = match record.entry().to_app_option::<TamagochiInventory>().ok().flatten() {
+                Some(previous) if same_chain && previous.owner_id == *author => previous,
+                _ => return Ok(ValidateCallbackResult::Invalid("Previous inventory version not found".into())),
+            };
+            // Building on an older version would spend the same coins twice
+            let activity = must_get_agent_activity(author.clone(), ChainFilter::new(prev_action.clone()).until_hash(previous_hash.clone()))?;
+            let inventory_type: EntryType = UnitEntryTypes::TamagochiInventory.try_into()?;
+            if activity.iter().any(|item| item.action.as_hash() != previous_hash && item.action.action().entry_type() == Some(&inventory_type)) {
+                return Ok(ValidateCallbackResult::Invalid("Inventory versions must build on the newest one".into()));
+            }
+            previous
+        }
+        _ => return Ok(ValidateCallbackResult::Invalid("Only updates cite a previous inventory version".into())),
+    };
+    
+    let change = match &inventory.change {
+        Some(change) => change,
+        None => return Ok(ValidateCallbackResult::Invalid("Inventory versions must record their change".into())),
+    };
+    let evidence_at = match change {
+        InventoryChange::Reward { activity, evidence } => match reward_evidence_at(activity, evidence, author)? {
+            Some(at) => Some(at),
+            None => return Ok(ValidateCallbackResult::Invalid("Reward evidence is not your own activity".into())),
+        },
+        _ => None,
+    };
+    
+    let expected = match next_inventory(&previous, change, now.div_euclid(24 * 3600), evidence_at) {
+        Ok(expected) => expected,
+        Err(reason) => return Ok(ValidateCallbackResult::Invalid(reason)),
+    };
+    if inventory.coins != expected.coins
+        || inventory.items != expected.items
+        || inventory.rewards_day != expected.rewards_day
+        || inventory.rewards_today != expected.rewards_today
+        || inventory.last_reward_at != expected.last_reward_at
+    {
+        return Ok(ValidateCallbackResult::Invalid("Inventory does not match its change".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// When the author did the rewarded activity; None if the evidence isn't theirs or doesn't match
+fn reward_evidence_at(activity: &str, evidence: &ActionHash, author: &AgentPubKey) -> ExternResult<Option<i64>> {
+    let record = must_get_valid_record(evidence.clone())?;
+    let action = record.action();
+    if action.author() != author {
+        return Ok(None);
+    }
+    let matches = match tamagochi::Activity::parse(activity) {
+        Some(tamagochi::Activity::Post) => record.entry().to_app_option::<Post>().ok().flatten().is_some(),
+        Some(tamagochi::Activity::WishHelp) => record.entry().to_app_option::<WishHelp>().ok().flatten().is_some(),
+        Some(tamagochi::Activity::Ride) => match action {
+            Action::CreateLink(create_link) => {
+                matches!(LinkTypes::from_type(create_link.zome_index, create_link.link_type)?, Some(LinkTypes::RideToRewards))
+            }
+            _ => false,
+        },
+        None => false,
+    };
+    Ok(if matches { Some(action.timestamp().as_seconds_since_epoch()) } else { None })
+}
+
+// Update links chain one owner's inventory versions
+fn validate_inventory_update_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    for hash in [base, target] {
+        let inventory_hash = match hash.clone().into_entry_hash() {
+            Some(hash) => hash,
+            None => return Ok(ValidateCallbackResult::Invalid("Inventory update links join inventories".into())),
+        };
+        match TamagochiInventory::try_from(must_get_entry(inventory_hash)?.content) {
+            Ok(inventory) if inventory.owner_id == *author => {}
+            _ => return Ok(ValidateCallbackResult::Invalid("Only the owner can update an inventory".into())),
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_role_grant(grant: &RoleGrant, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if grant.granted_by != *author {
+        return Ok(ValidateCallbackResult::Invalid("Role grants must be signed by the granter".into()));
+    }
+    if grant.expires_at <= grant.granted_at || grant.expires_at - grant.granted_at > ROLE_GRANT_TTL_SECONDS {
+        return Ok(ValidateCallbackResult::Invalid(format!("Role grants last at most {} seconds", ROLE_GRANT_TTL_SECONDS)));
+    }
+    validate_role_authority(author, &grant.authority, Role::Admin, now)
+}
+
+fn validate_role_revocation(revocation: &RoleRevocation, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if revocation.revoked_by != *author {
+        return Ok(ValidateCallbackResult::Invalid("Role revocations must be signed by the revoker".into()));
+    }
+    let record = must_get_valid_record(revocation.grant_hash.clone())?;
+    if record.entry().to_app_option::<RoleGrant>().ok().flatten().is_none() {
+        return Ok(ValidateCallbackResult::Invalid("Revocation target is not a role grant".into()));
+    }
+    validate_role_authority(author, &revocation.authority, Role::Admin, now)
+}
+
+// Grant links hang off the grantee and are written by the granter
+fn validate_role_grant_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let grant_hash = match target.clone().into_action_hash() {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Role grant link must target an action".into())),
+    };
+    let record = must_get_valid_record(grant_hash)?;
+    match record.entry().to_app_option::<RoleGrant>().ok().flatten() {
+        Some(grant) if *base == AnyLinkableHash::from(grant.grantee.clone()) && grant.granted_by == *author => {
+            Ok(ValidateCallbackResult::Valid)
+        }
+        _ => Ok(ValidateCallbackResult::Invalid("Role grant link does not match the grant".into())),
+    }
+}
+
+// Only the revoker may attach a revocation, and only to the grant it revokes
+fn validate_revocation_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    let revocation_hash = match target.clone().into_action_hash() {
+        Some(hash) => hash,
+        None => return Ok(ValidateCallbackResult::Invalid("Revocation link must target an action".into())),
+    };
+    let record = must_get_valid_record(revocation_hash)?;
+    match record.entry().to_app_option::<RoleRevocation>().ok().flatten() {
+        Some(revocation) if *base == AnyLinkableHash::from(revocation.grant_hash.clone()) && revocation.revoked_by == *author => {
+            Ok(ValidateCallbackResult::Valid)
+        }
+        _ => Ok(ValidateCallbackResult::Invalid("Revocation link does not match the revocation".into())),
+    }
+}
+
+/// The progenitor holds every role; anyone else must cite an unexpired grant of `required` (or Admin) made to them.
+/// Revocation links aren't visible here deterministically: the coordinator skips revoked grants,
+/// and expiry bounds how long a revoked grant can still be cited.
+fn validate_role_authority(author: &AgentPubKey, authority: &Option<ActionHash>, required: Role, now: i64) -> ExternResult<ValidateCallbackResult> {
+    let progenitor = match dna_properties()?.progenitor {
+        Some(progenitor) => progenitor,
+        None => return Ok(ValidateCallbackResult::Invalid("Privileged actions are disabled until a progenitor is set".into())),
+    };
+    if progenitor == *author {
+        return Ok(ValidateCallbackResult::Valid);
+    }
+    let grant_hash = match authority {
+        Some(hash) => hash.clone(),
+        None => return Ok(ValidateCallbackResult::Invalid("Missing role authority".into())),
+    };
+    let record = must_get_valid_record(grant_hash)?;
+    let grant = match record.entry().to_app_option::<RoleGrant>().ok().flatten() {
+        Some(grant) => grant,
+        None => return Ok(ValidateCallbackResult::Invalid("Role authority is not a role grant".into())),
+    };
+    if grant.grantee != *author || (grant.role != required && grant.role != Role::Admin) {
+        return Ok(ValidateCallbackResult::Invalid(format!("Requires the {:?} role", required)));
+    }
+    if now > grant.expires_at {
+        return Ok(ValidateCallbackResult::Invalid("Role grant has expired".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Only the post's author links it to a newer version of their own post
+fn validate_post_update_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    for hash in [base, target] {
+        let post_hash = match hash.clone().into_entry_hash() {
+            Some(hash) => hash,
+            None => return Ok(ValidateCallbackResult::Invalid("Post update links join posts".into())),
+        };
+        match Post::try_from(must_get_entry(post_hash)?.content) {
+            Ok(post) if post.author_id == *author => {}
+            _ => return Ok(ValidateCallbackResult::Invalid("Only the author can update a post".into())),
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// Same for comments: edits and soft deletes come only from the comment's author
+fn validate_comment_update_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    for hash in [base, target] {
+        let comment_hash = match hash.clone().into_entry_hash() {
+            Some(hash) => hash,
+            None => return Ok(ValidateCallbackResult::Invalid("Comment update links join comments".into())),
+        };
+        match Comment::try_from(must_get_entry(comment_hash)?.content) {
+            Ok(comment) if comment.author_id == *author => {}
+            _ => return Ok(ValidateCallbackResult::Invalid("Only the author can update a comment".into())),
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_comment(comment: &Comment, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    if comment.author_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Comment author must be the action author".into()));
+    }
+    if !comment.deleted && (comment.text.trim().is_empty() || comment.text.chars().count() > MAX_COMMENT_CHARS) {
+        return Ok(ValidateCallbackResult::Invalid(format!("Comments must have 1-{} characters", MAX_COMMENT_CHARS)));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_create_story(story: &Story, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if story.author_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Story author must be the action author".into()));
+    }
+    if story.image_hashes.is_empty() && story.video_hash.is_none() {
+        return Ok(ValidateCallbackResult::Invalid("Story needs an image or video".into()));
+    }
+    if story.expires_at != story.created_at + STORY_TTL_SECONDS {
+        return Ok(ValidateCallbackResult::Invalid("Story must expire 24 hours after creation".into()));
+    }
+    if story.expires_at <= now {
+        return Ok(ValidateCallbackResult::Invalid("Story already expired".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_create_story_view(view: &StoryView, author: &AgentPubKey, now: i64) -> ExternResult<ValidateCallbackResult> {
+    if view.viewer_id != *author {
+        return Ok(ValidateCallbackResult::Invalid("Story view must be recorded by the viewer".into()));
+    }
+    validate_story_not_expired(&view.story_hash, now)
+}
+
+// Epitaph edits are chained from the original record, by its owner only
+fn validate_death_update_link(base: &AnyLinkableHash, target: &AnyLinkableHash, author: &AgentPubKey) -> ExternResult<ValidateCallbackResult> {
+    for hash in [base, target] {
+        let death_hash = match hash.clone().into_entry_hash() {
+            Some(hash) => hash,
+            None => return Ok(ValidateCallbackResult::Invalid("Death update links join death records".into())),
+        };
+        match TamagochiDeath::try_from(must_get_entry(death_hash)?.content) {
+            Ok(death) if death.owner_id == *author => {}
+            _ => return Ok(ValidateCallbackResult::Invalid("Only the owner can update a death record".into())),
+        }
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_story_not_expired(story_hash: &EntryHash, now: i64) -> ExternResult<ValidateCallbackResult> {
+    let entry = must_get_entry(story_hash.clone())?;
+    let story = match Story::try_from(entry.content) {
+        Ok(story) => story,
+        Err(_) => return Ok(ValidateCallbackResult::Invalid("Target is not a story".into())),
+    };
+    if now > story.expires_at {
+        return Ok(ValidateCallbackResult::Invalid("Story expired".into()));
+    }
+    Ok(ValidateCallbackResult::Valid)
+}
@@ -1,9 +1,40 @@
 use hdk::prelude::*;
 use integrity::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::f64::consts::PI;
 
 mod integrity;
+mod tamagochi;
+
+const FEED_ROOT: &str = "feed";
+const HOME_FEED_LINK_SLACK_SECONDS: i64 = 60; // El link UserToPosts se escribe en la misma llamada que el post
+const SECONDS_PER_HOUR: i64 = 3_600;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+const TRENDING_WINDOW_DAYS: i64 = 7;
+const HASHTAG_ROOT: &str = "hashtags";
+const STICKER_PACKS_ROOT: &str = "sticker_packs";
+const HASHTAG_USAGE_ROOT: &str = "hashtag_usage";
+const MAX_HASHTAG_LEN: usize = 50;
+const MENTION_IN_POST: &str = "post";
+const MENTION_IN_COMMENT: &str = "comment";
+const MAX_COMMENT_DEPTH: u32 = 5;
+const VERIFICATION_REQUESTS_ROOT: &str = "verification_requests";
+const RUT_HASH_ROUNDS: u32 = 10_000;
+const VISIT_CLAIM_WINDOW_SECONDS: i64 = 7 * SECONDS_PER_DAY; // Visitas aceptadas más viejas no se cobran
+const LEADERBOARD_SNAPSHOT_SECONDS: i64 = 6 * SECONDS_PER_HOUR;
+const LEADERBOARD_WINDOW_SECONDS: i64 = 2 * SECONDS_PER_DAY;
+const MAX_LEADERBOARD_SIZE: usize = 100;
+const GEO_ROOT: &str = "geo";
+const GEO_CELL_DEGREES: f64 = 0.25; // ~28 km de latitud
+const MAX_GEO_RADIUS_KM: f64 = 50.0;
+const SUGGESTION_RADIUS_KM: f64 = 25.0;
+const MAX_FRIEND_SUGGESTIONS: usize = 50;
+const MAX_SUGGESTION_FRIENDS: usize = 50; // Amigos cuyos amigos se revisan
+const MAX_SUGGESTION_CANDIDATES: usize = 200; // Amigos de amigos cuya lista se lee
+const MAX_INTERACTION_SCAN: usize = 50; // Registros recientes revisados por tipo
+const ROLE_GRANT_RENEWAL_SECONDS: i64 = 7 * SECONDS_PER_DAY; // Antes de vencer se puede renovar
+const MAX_SCORED_INTERACTIONS: u32 = 5; // Para que un solo chat largo no domine
 
 // Helper trait for to_radians
 trait ToRadians {
@@ -16,7 +47,7 @@ impl ToRadians for f64 {
     }
 }
 
-use integrity::{Post, PostLocation, PostClap, PostLike, PostComment, PostReport, StickerData, UserBlock, Tamagochi, TamagochiDeath, UserPreferences, Booking, RideRequest, Review, VehicleInfo, ProductComment, AdBanner, Wish, WishHelp, TamagochiVisit, ProfileCover};
+use integrity::{dna_properties, Role, RoleGrant, RoleRevocation, ROLE_GRANT_TTL_SECONDS, VerificationRequest, VerificationAttestation, VerificationStatus, RutKey, rut_index_path, ModerationAction, ModerationDecision, MODERATION_QUEUE_ROOT, Story, StoryView, STORY_TTL_SECONDS, StickerPack, StickerKind, check_stickers, check_sticker_pack, Comment, CommentTarget, MAX_COMMENT_CHARS, Post, PostLocation, PostClap, MAX_CLAPS_PER_USER, PostLike, Report, ReportTarget, check_report, StickerData, UserBlock, Tamagochi, TamagochiDeath, TamagochiMemorial, MAX_EPITAPH_CHARS, MAX_MEMORIAL_CHARS, TamagochiInventory, InventoryChange, next_inventory, MAX_DAILY_REWARDS, VisitInteraction, LeaderboardSnapshot, Genome, BreedingProposal, BreedingRecord, BREEDING_COOLDOWN_SECONDS, BREEDING_PROPOSAL_TTL_SECONDS, Achievement, AchievementEvidence, AchievementUnlock, FriendRequest, UserPreferences, Booking, RideRequest, Review, VehicleInfo, AdBanner, BannerApproval, ACTIVE_BANNERS_ROOT, Wish, WishHelp, TamagochiVisit, ProfileCover, leaderboard_bucket_path};
 
 #[hdk_extern]
 pub fn hello(_: ()) -> ExternResult<String> {
@@ -54,10 +85,11 @@ pub fn register_user(input: RegisterInput) -> ExternResult<AgentPubKey> {
         email: input.email.clone(),
         password_hash,
         name: input.name,
-        rut: None,
+        rut_masked: None,
         profile_image_hash: None,
         id_card_image_hash: None,
         is_verified: false,
+        verification_hash: None,
         lat: None,
         lon: None,
         created_at: sys_time()?.as_seconds_since_epoch(),
@@ -111,7 +143,6 @@ pub fn get_user_profile(_: ()) -> ExternResult<Option<User>> {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UpdateUserInput {
     pub name: Option<String>,
-    pub rut: Option<String>,
     pub lat: Option<f64>,
     pub lon: Option<f64>,
 }
@@ -125,12 +156,6 @@ pub fn update_user_profile(input: UpdateUserInput) -> ExternResult<User> {
     if let Some(name) = input.name {
         user.name = name;
     }
-    if let Some(rut) = input.rut {
-        if !validate_rut(&rut) {
-            return Err(wasm_error!(WasmErrorInner::Guest("Invalid RUT format".into())));
-        }
-        user.rut = Some(rut);
-    }
     if let Some(lat) = input.lat {
         user.lat = Some(lat);
     }
@@ -144,24 +169,216 @@ pub fn update_user_profile(input: UpdateUserInput) -> ExternResult<User> {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct VerifyUserInput {
-    pub rut: String,
     pub id_card_image_hash: String,
 }
 
+/// Submits a verification request; the user only becomes verified once a verifier approves it.
+/// The RUT isn't sent: the verifier reads it off the ID card.
 #[hdk_extern]
-pub fn verify_user(input: VerifyUserInput) -> ExternResult<User> {
+pub fn verify_user(input: VerifyUserInput) -> ExternResult<ActionHash> {
     let agent = agent_info()?.agent_latest_pubkey();
-    let mut user = get_user_by_agent(&agent)?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    
+    // Una solicitud pendiente a la vez
+    if let Some(item) = get_my_verification(())? {
+        if item.attestation.is_none() {
+            return Err(wasm_error!(WasmErrorInner::Guest("A verification request is already pending".into())));
+        }
+    }
+    
+    let request = VerificationRequest {
+        user_id: agent.clone(),
+        id_card_image_hash: input.id_card_image_hash,
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let request_hash = create_entry(EntryTypes::VerificationRequest(request))?;
+    
+    // Visible to verifiers until reviewed
+    create_link(
+        Path::from(VERIFICATION_REQUESTS_ROOT).path_entry_hash()?,
+        request_hash.clone(),
+        LinkTypes::VerificationRequests,
+        (),
+    )?;
+    
+    // Link user to request
+    create_link(
+        agent,
+        request_hash.clone(),
+        LinkTypes::UserToVerificationRequests,
+        (),
+    )?;
+    
+    Ok(request_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VerificationRequestItem {
+    pub request_hash: ActionHash,
+    pub request: VerificationRequest,
+    pub attestation: Option<VerificationAttestation>, // None mientras está pendiente
+}
+
+#[hdk_extern]
+pub fn get_my_verification(_: ()) -> ExternResult<Option<VerificationRequestItem>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let links = get_links(agent, LinkTypes::UserToVerificationRequests, None)?;
+    
+    let mut latest: Option<VerificationRequestItem> = None;
+    for link in links {
+        let request_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let item = get_verification_item(request_hash)?;
+        if latest.as_ref().map_or(true, |l| item.request.created_at > l.request.created_at) {
+            latest = Some(item);
+        }
+    }
+    
+    Ok(latest)
+}
 
-    if !validate_rut(&input.rut) {
-        return Err(wasm_error!(WasmErrorInner::Guest("Invalid RUT format".into())));
+#[hdk_extern]
+pub fn get_pending_verifications(_: ()) -> ExternResult<Vec<VerificationRequestItem>> {
+    ensure_role(Role::Verifier)?;
+    
+    let links = get_links(Path::from(VERIFICATION_REQUESTS_ROOT).path_entry_hash()?, LinkTypes::VerificationRequests, None)?;
+    let mut pending = Vec::new();
+    
+    for link in links {
+        let request_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let item = get_verification_item(request_hash)?;
+        if item.attestation.is_none() {
+            pending.push(item);
+        }
     }
+    
+    // Oldest first
+    pending.sort_by_key(|item| item.request.created_at);
+    Ok(pending)
+}
 
-    user.rut = Some(input.rut);
-    user.id_card_image_hash = Some(input.id_card_image_hash);
-    user.is_verified = true;
+/// Stores the verifiers' RUT key as a private entry; every verifier must use the same key
+#[hdk_extern]
+pub fn set_rut_key(key: String) -> ExternResult<()> {
+    ensure_role(Role::Verifier)?;
+    if key.len() < 32 {
+        return Err(wasm_error!(WasmErrorInner::Guest("RUT key must be at least 32 characters".into())));
+    }
+    create_entry(EntryTypes::RutKey(RutKey { key }))?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttestVerificationInput {
+    pub request_hash: ActionHash,
+    pub approve: bool,
+    pub reason: Option<String>,
+    pub rut: Option<String>, // Leído del carnet; obligatorio para aprobar
+}
+
+#[hdk_extern]
+pub fn attest_verification(input: AttestVerificationInput) -> ExternResult<ActionHash> {
+    let (verifier_id, authority) = ensure_role(Role::Verifier)?;
+    
+    let item = get_verification_item(input.request_hash.clone())?;
+    if item.request.user_id == verifier_id {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot verify yourself".into())));
+    }
+    if item.attestation.is_some() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Request already reviewed".into())));
+    }
+    if !input.approve && input.reason.as_deref().map_or(true, |r| r.trim().is_empty()) {
+        return Err(wasm_error!(WasmErrorInner::Guest("A reason is required to reject".into())));
+    }
+    
+    // Approving hashes the RUT with the verifiers' key and checks no other account holds it;
+    // only the masked form is kept for display
+    let (rut_key_hash, rut_masked) = if input.approve {
+        let rut = input.rut.as_deref().unwrap_or_default();
+        if !validate_rut(rut) {
+            return Err(wasm_error!(WasmErrorInner::Guest("Invalid RUT format".into())));
+        }
+        let rut_key_hash = hash_rut(rut, &get_rut_key()?);
+        ensure_rut_available(&rut_key_hash, &item.request.user_id)?;
+        (Some(rut_key_hash), Some(mask_rut(rut)))
+    } else {
+        (None, None)
+    };
+    
+    let attestation = VerificationAttestation {
+        request_hash: input.request_hash.clone(),
+        user_id: item.request.user_id,
+        verifier_id,
+        status: if input.approve { VerificationStatus::Approved } else { VerificationStatus::Rejected },
+        reason: input.reason,
+        rut_key_hash,
+        rut_masked,
+        authority,
+        attested_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let attestation_hash = create_entry(EntryTypes::VerificationAttestation(attestation))?;
+    
+    // Link request to attestation
+    create_link(
+        input.request_hash.clone(),
+        attestation_hash.clone(),
+        LinkTypes::VerificationRequestToAttestations,
+        (),
+    )?;
+    
+    // Reviewed requests leave the verifiers' list
+    let links = get_links(Path::from(VERIFICATION_REQUESTS_ROOT).path_entry_hash()?, LinkTypes::VerificationRequests, None)?;
+    for link in links {
+        if link.target == input.request_hash.clone().into() {
+            delete_link(link.create_link_hash)?;
+        }
+    }
+    
+    Ok(attestation_hash)
+}
 
+/// Applies an approved attestation to the caller's profile; validation rejects is_verified without one
+#[hdk_extern]
+pub fn complete_verification(request_hash: ActionHash) -> ExternResult<User> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut user = get_user_by_agent(&agent)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    
+    let item = get_verification_item(request_hash.clone())?;
+    if item.request.user_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Not your verification request".into())));
+    }
+    
+    let (attestation_hash, rut_key_hash, rut_masked) = match get_verification_attestation(&request_hash)? {
+        Some((hash, attestation)) if attestation.status == VerificationStatus::Approved => {
+            let rut_key_hash = attestation.rut_key_hash
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Attestation has no RUT".into())))?;
+            (hash, rut_key_hash, attestation.rut_masked)
+        }
+        Some(_) => return Err(wasm_error!(WasmErrorInner::Guest("Verification was rejected".into()))),
+        None => return Err(wasm_error!(WasmErrorInner::Guest("Verification is still pending".into()))),
+    };
+    
+    // Claim the RUT so no other account can verify with it; the tag cites the attestation
+    ensure_rut_available(&rut_key_hash, &agent)?;
+    create_link(
+        rut_index_path(&rut_key_hash)?,
+        agent.clone(),
+        LinkTypes::RutIndex,
+        LinkTag::new(attestation_hash.get_raw_39().to_vec()),
+    )?;
+    
+    user.rut_masked = rut_masked;
+    user.id_card_image_hash = Some(item.request.id_card_image_hash);
+    user.is_verified = true;
+    user.verification_hash = Some(attestation_hash);
+    
     let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
     Ok(user)
 }
@@ -336,10 +553,17 @@ pub fn get_products(seller_id: Option<AgentPubKey>) -> ExternResult<Vec<Product>
     let agent = seller_id.unwrap_or_else(|| agent_info()?.agent_latest_pubkey());
     
     let links = get_links(agent, LinkTypes::UserToProducts, None)?;
+    let mut hidden = hidden_targets()?;
     let mut products = Vec::new();
 
     for link in links {
-        let entry_hash = link.target;
+        let entry_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if is_hidden(&ReportTarget::Product(entry_hash.clone()), &mut hidden)? {
+            continue;
+        }
         let element = get(entry_hash, GetOptions::default())?
             .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Product not found".into())))?;
         
@@ -355,78 +579,7 @@ pub fn get_products(seller_id: Option<AgentPubKey>) -> ExternResult<Vec<Product>
 #[hdk_extern]
 pub fn comment_on_product(input: (EntryHash, String, Option<EntryHash>)) -> ExternResult<EntryHash> {
     let (product_id, text, parent_comment_hash) = input;
-    let author_id = agent_info()?.agent_latest_pubkey();
-    
-    let comment = ProductComment {
-        product_id,
-        author_id,
-        text,
-        parent_comment_hash,
-        created_at: sys_time()?.as_seconds_since_epoch(),
-    };
-    
-    let comment_hash = create_entry(EntryTypes::ProductComment(comment.clone()))?;
-    
-    // Link comment to product
-    create_link(
-        product_id,
-        comment_hash,
-        LinkTypes::ProductToComments,
-        (),
-    )?;
-    
-    // Link to parent comment if it's a reply
-    if let Some(parent_hash) = parent_comment_hash {
-        create_link(
-            parent_hash,
-            comment_hash,
-            LinkTypes::CommentToReplies,
-            (),
-        )?;
-    }
-    
-    Ok(comment_hash)
-}
-
-#[hdk_extern]
-pub fn get_product_comments(product_id: EntryHash) -> ExternResult<Vec<ProductComment>> {
-    let links = get_links(product_id, LinkTypes::ProductToComments, None)?;
-    let mut comments = Vec::new();
-    
-    for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?
-            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Comment not found".into())))?;
-        
-        if let Some(Entry::App(entry_bytes)) = element.entry() {
-            let comment: ProductComment = entry_bytes.try_into()?;
-            comments.push(comment);
-        }
-    }
-    
-    // Sort by created_at
-    comments.sort_by_key(|c| c.created_at);
-    Ok(comments)
-}
-
-#[hdk_extern]
-pub fn get_comment_replies(comment_hash: EntryHash) -> ExternResult<Vec<ProductComment>> {
-    let links = get_links(comment_hash, LinkTypes::CommentToReplies, None)?;
-    let mut replies = Vec::new();
-    
-    for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?
-            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Reply not found".into())))?;
-        
-        if let Some(Entry::App(entry_bytes)) = element.entry() {
-            let reply: ProductComment = entry_bytes.try_into()?;
-            replies.push(reply);
-        }
-    }
-    
-    replies.sort_by_key(|r| r.created_at);
-    Ok(replies)
+    create_comment(CommentTarget::Product(product_id), text, parent_comment_hash)
 }
 
 // ========== Ad Banners ==========
@@ -457,6 +610,7 @@ pub fn create_ad_banner(input: CreateAdBannerInput) -> ExternResult<EntryHash> {
         created_at: sys_time()?.as_seconds_since_epoch(),
     };
     
+    // Not listed until a moderator approves it (approve_ad_banner)
     let banner_hash = create_entry(EntryTypes::AdBanner(banner.clone()))?;
     
     // Link to banner feed
@@ -470,15 +624,60 @@ pub fn create_ad_banner(input: CreateAdBannerInput) -> ExternResult<EntryHash> {
     Ok(banner_hash)
 }
 
+/// Lists a banner in get_active_banners (moderators only)
+#[hdk_extern]
+pub fn approve_ad_banner(banner_hash: EntryHash) -> ExternResult<ActionHash> {
+    let (approver_id, authority) = ensure_role(Role::Moderator)?;
+    
+    let element = get(banner_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Banner not found".into())))?;
+    if element.entry().to_app_option::<AdBanner>()?.is_none() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Banner not found".into())));
+    }
+    
+    let approval = BannerApproval {
+        banner_hash: banner_hash.clone(),
+        approver_id,
+        authority,
+        approved_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    let approval_hash = create_entry(EntryTypes::BannerApproval(approval))?;
+    
+    // The tag cites the approval so validation can check it
+    create_link(
+        Path::from(ACTIVE_BANNERS_ROOT).path_entry_hash()?,
+        banner_hash,
+        LinkTypes::ApprovedBanners,
+        LinkTag::new(approval_hash.get_raw_39().to_vec()),
+    )?;
+    
+    Ok(approval_hash)
+}
+
+/// Approved banners that are active and within their dates
 #[hdk_extern]
 pub fn get_active_banners(_: ()) -> ExternResult<Vec<AdBanner>> {
-    // Get all active banners
-    // This is simplified - in production, use proper query/index
-    let mut banners = Vec::new();
+    let links = get_links(Path::from(ACTIVE_BANNERS_ROOT).path_entry_hash()?, LinkTypes::ApprovedBanners, None)?;
     let now = sys_time()?.as_seconds_since_epoch();
+    let mut seen = HashSet::new();
+    let mut banners = Vec::new();
     
-    // For now, return empty - will need proper banner index in production
-    // This would query all banners where active = true and now >= start_date and now <= end_date
+    for link in links {
+        let banner_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if !seen.insert(banner_hash.clone()) {
+            continue;
+        }
+        if let Some(element) = get(banner_hash, GetOptions::default())? {
+            if let Some(banner) = element.entry().to_app_option::<AdBanner>()? {
+                if banner.active && now >= banner.start_date && now <= banner.end_date {
+                    banners.push(banner);
+                }
+            }
+        }
+    }
     
     Ok(banners)
 }
@@ -605,35 +804,14 @@ pub fn send_message(input: SendMessageInput) -> ExternResult<EntryHash> {
     let sender_id = agent_info()?.agent_latest_pubkey();
     let chat_id = format_chat_id(&sender_id, &input.receiver_id);
     
-    // Check if receiver has blocked sender (check from receiver's perspective)
-    // Note: This is simplified - in production, check from receiver's agent
-    // For now, we'll check if sender has blocked receiver (prevent sending to blocked users)
-    let sender_has_blocked = {
-        let links = get_links(sender_id, LinkTypes::UserToBlocks, None)?;
-        let mut blocked = false;
-        for link in links {
-            let element = get(link.target, GetOptions::default())?;
-            if let Some(element) = element {
-                if let Some(Entry::App(entry_bytes)) = element.entry() {
-                    match EntryTypes::try_from(entry_bytes.clone()) {
-                        Ok(EntryTypes::UserBlock(block)) => {
-                            if block.blocked_id == input.receiver_id {
-                                blocked = true;
-                                break;
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
-        blocked
-    };
-    
-    if sender_has_blocked {
+    // Prevent sending to users the sender blocked
+    if has_blocked(&sender_id, &input.receiver_id)? {
         return Err(wasm_error!(WasmErrorInner::Guest("Cannot send message to blocked user".into())));
     }
     
+    // Check if receiver has blocked sender (from the receiver's UserToBlocks links)
+    ensure_not_blocked_by(&input.receiver_id, &sender_id)?;
+    
     let message = Message {
         sender_id,
         receiver_id: input.receiver_id,
@@ -664,10 +842,17 @@ pub fn get_messages(chat_id: String) -> ExternResult<Vec<Message>> {
     let tag = LinkTag::new(chat_id.as_bytes().to_vec());
     
     let links = get_links(agent, LinkTypes::Chat, Some(tag))?;
+    let mut hidden = hidden_targets()?;
     let mut messages = Vec::new();
 
     for link in links {
-        let entry_hash = link.target;
+        let entry_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if is_hidden(&ReportTarget::Message(entry_hash.clone()), &mut hidden)? {
+            continue;
+        }
         let element = get(entry_hash, GetOptions::default())?
             .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Message not found".into())))?;
         
@@ -775,9 +960,20 @@ pub struct GetUsersNearbyInput {
 
 #[hdk_extern]
 pub fn get_users_nearby(input: GetUsersNearbyInput) -> ExternResult<Vec<User>> {
-    // Get all users (in production, use spatial index)
-    // For now, return empty vec - will implement proper spatial query later
-    Ok(Vec::new())
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut users = Vec::new();
+    
+    // Closest first; users who blocked or were blocked are skipped
+    for (user_id, _) in nearby_agents(input.lat, input.lon, input.radius_km)? {
+        if user_id == agent || has_blocked(&agent, &user_id)? || has_blocked(&user_id, &agent)? {
+            continue;
+        }
+        if let Some(user) = get_user_by_agent(&user_id)? {
+            users.push(user);
+        }
+    }
+    
+    Ok(users)
 }
 
 // ========== Posts / Feed ==========
@@ -795,6 +991,9 @@ pub struct CreatePostInput {
 pub fn create_post(input: CreatePostInput) -> ExternResult<EntryHash> {
     let author_id = agent_info()?.agent_latest_pubkey();
     
+    check_stickers(&input.sticker_data)
+        .map_err(|reason| wasm_error!(WasmErrorInner::Guest(reason)))?;
+    
     let post = Post {
         author_id,
         text: input.text,
@@ -803,6 +1002,7 @@ pub fn create_post(input: CreatePostInput) -> ExternResult<EntryHash> {
         sticker_data: input.sticker_data,
         created_at: sys_time()?.as_seconds_since_epoch(),
         location: input.location,
+        edited_at: None,
     };
 
     let post_hash = create_entry(EntryTypes::Post(post.clone()))?;
@@ -815,99 +1015,439 @@ pub fn create_post(input: CreatePostInput) -> ExternResult<EntryHash> {
         (),
     )?;
     
-    // Link to global feed
+    // Link to global feed, bucketed by hour
+    let bucket = feed_bucket_path(post.created_at)?;
+    bucket.ensure()?;
     create_link(
-        author_id,
-        post_hash,
+        bucket.path_entry_hash()?,
+        post_hash.clone(),
         LinkTypes::Feed,
         (),
     )?;
+    
+    if let Some(text) = &post.text {
+        index_hashtags(&post_hash, &extract_hashtags(text), post.created_at)?;
+        index_mentions(&post_hash, &extract_mentions(text), MENTION_IN_POST)?;
+    }
+    
+    reward_activity(tamagochi::Activity::Post, post_hash.clone())?;
+    award_achievements(AchievementEvidence::Post)?;
 
     Ok(post_hash)
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeedCursor {
+    pub created_at: i64,
+    pub post_hash: EntryHash, // Desempate entre posts del mismo segundo
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetFeedInput {
+    pub cursor: Option<FeedCursor>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeedItem {
+    pub post_hash: EntryHash,
+    pub post: Post,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeedPage {
+    pub items: Vec<FeedItem>,
+    pub next_cursor: Option<FeedCursor>,
+}
+
 #[hdk_extern]
-pub fn get_feed(limit: Option<u32>) -> ExternResult<Vec<Post>> {
-    // Get all posts from feed (all users)
-    // In production, use proper query with pagination
-    let limit = limit.unwrap_or(50);
-    
-    // Get all links to feed
+pub fn get_feed(input: GetFeedInput) -> ExternResult<FeedPage> {
+    let limit = input.limit.unwrap_or(50).min(100) as usize;
     let agent = agent_info()?.agent_latest_pubkey();
-    let links = get_links(agent, LinkTypes::Feed, None)?;
+    let start = match &input.cursor {
+        Some(cursor) => cursor.created_at,
+        None => sys_time()?.as_seconds_since_epoch(),
+    };
     
-    let mut posts = Vec::new();
-    let mut count = 0;
+    // Skip authors the caller blocked or who blocked the caller
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
+    let mut hidden_posts = hidden_targets()?;
     
-    for link in links {
-        if count >= limit {
-            break;
-        }
-        
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?;
+    let mut items = Vec::new();
+    
+    // Walk day buckets, then hour buckets, newest first
+    let root = Path::from(FEED_ROOT).typed(LinkTypes::FeedPath)?;
+    let days = feed_bucket_children(&root, start.div_euclid(SECONDS_PER_DAY))?;
+    
+    for (_, day_path) in days {
+        let hours = feed_bucket_children(&day_path, start.div_euclid(SECONDS_PER_HOUR))?;
         
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::Post(post)) => {
-                        posts.push(post);
-                        count += 1;
+        for (_, hour_path) in hours {
+            let links = get_links(hour_path.path_entry_hash()?, LinkTypes::Feed, None)?;
+            let mut bucket = Vec::new();
+            
+            for link in links {
+                let post_hash = match link.target.into_entry_hash() {
+                    Some(hash) => hash,
+                    None => continue,
+                };
+                // Deleted posts resolve to None
+                let post = match get_latest_post(&post_hash)? {
+                    Some(post) => post,
+                    None => continue,
+                };
+                
+                // Only posts strictly older than the cursor
+                if let Some(cursor) = &input.cursor {
+                    if (post.created_at, &post_hash) >= (cursor.created_at, &cursor.post_hash) {
+                        continue;
                     }
-                    _ => {}
                 }
+                if is_author_hidden(&post.author_id, &agent, &my_blocked, &mut hidden_authors)? {
+                    continue;
+                }
+                if is_post_hidden(&post_hash, &mut hidden_posts)? {
+                    continue;
+                }
+                bucket.push(FeedItem { post_hash, post });
+            }
+            
+            // Sort by created_at descending (newest first)
+            bucket.sort_by(|a, b| (b.post.created_at, &b.post_hash).cmp(&(a.post.created_at, &a.post_hash)));
+            items.extend(bucket);
+            
+            if items.len() >= limit {
+                items.truncate(limit);
+                let next_cursor = items.last().map(|item| FeedCursor {
+                    created_at: item.post.created_at,
+                    post_hash: item.post_hash.clone(),
+                });
+                return Ok(FeedPage { items, next_cursor });
             }
         }
     }
     
-    // Sort by created_at descending (newest first)
-    posts.sort_by_key(|p| -(p.created_at as i64));
-    
-    Ok(posts)
+    // Reached the oldest bucket
+    Ok(FeedPage { items, next_cursor: None })
 }
 
 #[hdk_extern]
-pub fn get_user_posts(user_id: AgentPubKey, limit: Option<u32>) -> ExternResult<Vec<Post>> {
-    // Get posts from specific user
-    let limit = limit.unwrap_or(50);
-    
-    let links = get_links(user_id, LinkTypes::UserToPosts, None)?;
-    let mut posts = Vec::new();
-    let mut count = 0;
+pub fn get_home_feed(input: GetFeedInput) -> ExternResult<FeedPage> {
+    let limit = input.limit.unwrap_or(50).min(100) as usize;
+    let agent = agent_info()?.agent_latest_pubkey();
     
-    for link in links {
-        if count >= limit {
-            break;
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
+    let mut hidden_posts = hidden_targets()?;
+    let mut seen = HashSet::new();
+    
+    // Candidates from every friend's UserToPosts links, without fetching the posts yet.
+    // The link is written right after the post, so its timestamp bounds created_at from above.
+    let mut candidates: Vec<(i64, EntryHash)> = Vec::new();
+    for friend_id in get_friends(())? {
+        if is_author_hidden(&friend_id, &agent, &my_blocked, &mut hidden_authors)? {
+            continue;
         }
         
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?;
-        
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::Post(post)) => {
-                        posts.push(post);
-                        count += 1;
-                    }
-                    _ => {}
+        for link in get_links(friend_id, LinkTypes::UserToPosts, None)? {
+            let linked_at = link.timestamp.as_seconds_since_epoch();
+            if let Some(cursor) = &input.cursor {
+                if linked_at > cursor.created_at + HOME_FEED_LINK_SLACK_SECONDS {
+                    continue;
+                }
+            }
+            if let Some(post_hash) = link.target.into_entry_hash() {
+                if seen.insert(post_hash.clone()) {
+                    candidates.push((linked_at, post_hash));
                 }
             }
         }
     }
     
-    // Sort by created_at descending (newest first)
-    posts.sort_by_key(|p| -(p.created_at as i64));
-    
-    Ok(posts)
-}
-
+    // Newest links first; only the posts of this page are fetched
+    candidates.sort_by(|a, b| b.cmp(a));
+    
+    let mut items: Vec<FeedItem> = Vec::new();
+    let mut more = false;
+    for (linked_at, post_hash) in candidates {
+        if items.len() >= limit {
+            // Anything linked before the last item was also created before it
+            if items.last().map_or(false, |last| linked_at < last.post.created_at) {
+                more = true;
+                break;
+            }
+        }
+        
+        // Deleted posts resolve to None
+        let post = match get_latest_post(&post_hash)? {
+            Some(post) => post,
+            None => continue,
+        };
+        
+        // Only posts strictly older than the cursor
+        if let Some(cursor) = &input.cursor {
+            if (post.created_at, &post_hash) >= (cursor.created_at, &cursor.post_hash) {
+                continue;
+            }
+        }
+        if is_post_hidden(&post_hash, &mut hidden_posts)? {
+            continue;
+        }
+        
+        items.push(FeedItem { post_hash, post });
+        // Sort by created_at descending (newest first), hash as tie-breaker
+        items.sort_by(|a, b| (b.post.created_at, &b.post_hash).cmp(&(a.post.created_at, &a.post_hash)));
+        if items.len() > limit {
+            items.truncate(limit);
+            more = true;
+        }
+    }
+    
+    let next_cursor = if more {
+        items.last().map(|item| FeedCursor {
+            created_at: item.post.created_at,
+            post_hash: item.post_hash.clone(),
+        })
+    } else {
+        None
+    };
+    
+    Ok(FeedPage { items, next_cursor })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetTrendingFeedInput {
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_km: f64,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrendingItem {
+    pub post_hash: EntryHash,
+    pub post: Post,
+    pub score: f64,
+    pub distance_km: f64,
+}
+
+#[hdk_extern]
+pub fn get_trending_feed(input: GetTrendingFeedInput) -> ExternResult<Vec<TrendingItem>> {
+    let limit = input.limit.unwrap_or(20).min(100) as usize;
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    let oldest_day = now.div_euclid(SECONDS_PER_DAY) - TRENDING_WINDOW_DAYS;
+    
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
+    let mut hidden_posts = hidden_targets()?;
+    let mut items = Vec::new();
+    
+    // Candidates: recent posts from the global feed buckets
+    let root = Path::from(FEED_ROOT).typed(LinkTypes::FeedPath)?;
+    for (day, day_path) in feed_bucket_children(&root, now.div_euclid(SECONDS_PER_DAY))? {
+        if day < oldest_day {
+            break;
+        }
+        
+        for (_, hour_path) in feed_bucket_children(&day_path, now.div_euclid(SECONDS_PER_HOUR))? {
+            let links = get_links(hour_path.path_entry_hash()?, LinkTypes::Feed, None)?;
+            
+            for link in links {
+                let post_hash = match link.target.into_entry_hash() {
+                    Some(hash) => hash,
+                    None => continue,
+                };
+                let post = match get_latest_post(&post_hash)? {
+                    Some(post) => post,
+                    None => continue,
+                };
+                
+                // Only geotagged posts inside the radius
+                let distance_km = match &post.location {
+                    Some(location) => calculate_distance(input.lat, input.lon, location.lat, location.lon),
+                    None => continue,
+                };
+                if distance_km > input.radius_km {
+                    continue;
+                }
+                if is_author_hidden(&post.author_id, &agent, &my_blocked, &mut hidden_authors)? {
+                    continue;
+                }
+                
+                // Reported content stays out of explore
+                if is_post_hidden(&post_hash, &mut hidden_posts)? {
+                    continue;
+                }
+                
+                let claps = get_post_claps(post_hash.clone())?.total;
+                let likes = get_post_likes(post_hash.clone())?;
+                let comments = get_links(post_hash.clone(), LinkTypes::PostToComments, None)?.len() as u32;
+                let score = trending_score(claps, likes, comments, now - post.created_at);
+                
+                items.push(TrendingItem { post_hash, post, score, distance_km });
+            }
+        }
+    }
+    
+    // Highest score first
+    items.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    items.truncate(limit);
+    
+    Ok(items)
+}
+
+#[hdk_extern]
+pub fn get_user_posts(user_id: AgentPubKey, limit: Option<u32>) -> ExternResult<Vec<Post>> {
+    // Get posts from specific user
+    let limit = limit.unwrap_or(50);
+    
+    // Authors who blocked the caller don't expose their posts
+    let agent = agent_info()?.agent_latest_pubkey();
+    if user_id != agent && has_blocked(&user_id, &agent)? {
+        return Ok(Vec::new());
+    }
+    
+    let links = get_links(user_id, LinkTypes::UserToPosts, None)?;
+    let mut posts = Vec::new();
+    let mut count = 0;
+    
+    for link in links {
+        if count >= limit {
+            break;
+        }
+        
+        let post_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if let Some(post) = get_latest_post(&post_hash)? {
+            posts.push(post);
+            count += 1;
+        }
+    }
+    
+    // Sort by created_at descending (newest first)
+    posts.sort_by_key(|p| -(p.created_at as i64));
+    
+    Ok(posts)
+}
+
 #[hdk_extern]
 pub fn get_my_posts(limit: Option<u32>) -> ExternResult<Vec<Post>> {
     let agent = agent_info()?.agent_latest_pubkey();
     get_user_posts(agent, limit)
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdatePostInput {
+    pub post_hash: EntryHash,
+    pub text: Option<String>,
+    pub sticker_data: Option<Vec<StickerData>>,
+}
+
+#[hdk_extern]
+pub fn update_post(input: UpdatePostInput) -> ExternResult<Post> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut post = get_post_by_hash(&input.post_hash)?;
+    
+    if post.author_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the author can edit this post".into())));
+    }
+    
+    // Solo texto y stickers son editables; media, fecha y ubicación se mantienen
+    if let Some(text) = input.text {
+        // Index only hashtags and mentions the previous text didn't have, drop the ones edited out
+        let old_text = post.text.clone().unwrap_or_default();
+        let old_tags = extract_hashtags(&old_text);
+        let old_mentions = extract_mentions(&old_text);
+        let tags = extract_hashtags(&text);
+        let mentions = extract_mentions(&text);
+        let new_tags: Vec<String> = tags.iter().filter(|t| !old_tags.contains(t)).cloned().collect();
+        let new_mentions: Vec<AgentPubKey> = mentions.iter().filter(|m| !old_mentions.contains(m)).cloned().collect();
+        let removed_tags: Vec<String> = old_tags.into_iter().filter(|t| !tags.contains(t)).collect();
+        let removed_mentions: Vec<AgentPubKey> = old_mentions.into_iter().filter(|m| !mentions.contains(m)).collect();
+        index_hashtags(&input.post_hash, &new_tags, sys_time()?.as_seconds_since_epoch())?;
+        index_mentions(&input.post_hash, &new_mentions, MENTION_IN_POST)?;
+        // Solo los links del autor; los de comentarios con el mismo hashtag se mantienen
+        unindex_hashtags(&input.post_hash, &removed_tags, Some(&agent))?;
+        unindex_mentions(&input.post_hash, &removed_mentions)?;
+        
+        post.text = Some(text);
+    }
+    if let Some(sticker_data) = input.sticker_data {
+        check_stickers(&sticker_data)
+            .map_err(|reason| wasm_error!(WasmErrorInner::Guest(reason)))?;
+        post.sticker_data = sticker_data;
+    }
+    post.edited_at = Some(sys_time()?.as_seconds_since_epoch());
+    
+    let record = get(input.post_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))?;
+    update_entry(record.action_address().clone(), EntryTypes::Post(post.clone()))?;
+    let updated_hash = hash_entry(&EntryTypes::Post(post.clone()))?;
+    
+    // Original hash keeps its claps/likes/comments; readers follow this link to the newest version
+    create_link(
+        input.post_hash,
+        updated_hash,
+        LinkTypes::PostUpdates,
+        (),
+    )?;
+    
+    Ok(post)
+}
+
+#[hdk_extern]
+pub fn delete_post(post_hash: EntryHash) -> ExternResult<()> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let post = get_post_by_hash(&post_hash)?;
+    
+    if post.author_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the author can delete this post".into())));
+    }
+    
+    let target: AnyLinkableHash = post_hash.clone().into();
+    
+    // Remove from the author's posts
+    let links = get_links(agent, LinkTypes::UserToPosts, None)?;
+    for link in links {
+        if link.target == target {
+            delete_link(link.create_link_hash)?;
+        }
+    }
+    
+    // Remove from the global feed bucket
+    let bucket = feed_bucket_path(post.created_at)?;
+    let links = get_links(bucket.path_entry_hash()?, LinkTypes::Feed, None)?;
+    for link in links {
+        if link.target == target {
+            delete_link(link.create_link_hash)?;
+        }
+    }
+    
+    // Out of hashtag lookups, trending counts and mentions, including hashtags from comments
+    let mut tags = extract_hashtags(&post.text.clone().unwrap_or_default());
+    for (_, comment) in load_comments(get_links(post_hash.clone(), LinkTypes::PostToComments, None)?)? {
+        for tag in extract_hashtags(&comment.text) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    unindex_hashtags(&post_hash, &tags, None)?;
+    unindex_mentions(&post_hash, &extract_mentions(&post.text.clone().unwrap_or_default()))?;
+    
+    // Claps, likes, comments and reports stay linked but orphaned: once the entry is
+    // deleted the post no longer resolves and readers count nothing (see is_orphaned_post)
+    let record = get(post_hash, GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))?;
+    delete_entry(record.action_address().clone())?;
+    
+    Ok(())
+}
+
 // ========== Post Interactions ==========
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -919,35 +1459,90 @@ pub struct ClapPostInput {
 #[hdk_extern]
 pub fn clap_post(input: ClapPostInput) -> ExternResult<EntryHash> {
     let user_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
     
-    // Verificar que el post existe
-    let _post = get(input.post_hash.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))?;
+    if input.count == 0 {
+        return Err(wasm_error!(WasmErrorInner::Guest("Clap count must be positive".into())));
+    }
     
-    let clap = PostClap {
-        post_hash: input.post_hash.clone(),
-        user_id,
-        count: input.count,
-        created_at: sys_time()?.as_seconds_since_epoch(),
+    // Verificar que el post existe
+    let post = get_post_by_hash(&input.post_hash)?;
+    ensure_not_blocked_by(&post.author_id, &user_id)?;
+    
+    // Un solo registro por (usuario, post), encontrado por el tag del link
+    let tag = LinkTag::new(user_id.get_raw_39().to_vec());
+    let existing = get_links(input.post_hash.clone(), LinkTypes::PostToClaps, Some(tag.clone()))?
+        .into_iter()
+        .next();
+    
+    let (clap_hash, previous) = match existing {
+        Some(link) => {
+            let element = get(link.target.clone(), GetOptions::default())?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Clap not found".into())))?;
+            let clap: PostClap = element.entry()
+                .to_app_option()?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Clap entry not found".into())))?;
+            
+            if clap.count >= MAX_CLAPS_PER_USER {
+                return Err(wasm_error!(WasmErrorInner::Guest("Clap limit reached for this post".into())));
+            }
+            
+            let updated = PostClap {
+                count: clap.count.saturating_add(input.count).min(MAX_CLAPS_PER_USER),
+                updated_at: now,
+                ..clap
+            };
+            update_entry(element.action_address().clone(), EntryTypes::PostClap(updated.clone()))?;
+            (hash_entry(&EntryTypes::PostClap(updated))?, Some(link.create_link_hash))
+        }
+        None => {
+            let clap = PostClap {
+                post_hash: input.post_hash.clone(),
+                user_id,
+                count: input.count.min(MAX_CLAPS_PER_USER),
+                created_at: now,
+                updated_at: now,
+            };
+            (create_entry(EntryTypes::PostClap(clap))?, None)
+        }
     };
     
-    let clap_hash = create_entry(EntryTypes::PostClap(clap))?;
-    
-    // Link post to clap
+    // Replace the link so the post always points at the current record
+    let first_clap = previous.is_none();
+    if let Some(old_link) = previous {
+        delete_link(old_link)?;
+    }
     create_link(
         input.post_hash,
-        clap_hash,
+        clap_hash.clone(),
         LinkTypes::PostToClaps,
-        (),
+        tag,
     )?;
     
+    if first_clap {
+        award_achievements(AchievementEvidence::Clap)?;
+    }
+    
     Ok(clap_hash)
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostClapSummary {
+    pub total: u64,
+    pub mine: u32, // Aplausos del usuario actual
+}
+
 #[hdk_extern]
-pub fn get_post_claps(post_hash: EntryHash) -> ExternResult<u32> {
+pub fn get_post_claps(post_hash: EntryHash) -> ExternResult<PostClapSummary> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    if is_orphaned_post(&post_hash)? {
+        return Ok(PostClapSummary { total: 0, mine: 0 });
+    }
+    
     let links = get_links(post_hash, LinkTypes::PostToClaps, None)?;
-    let mut total_claps = 0u32;
+    
+    // Aggregate per user so older duplicate records still respect the cap
+    let mut per_user: HashMap<AgentPubKey, u32> = HashMap::new();
     
     for link in links {
         let element = get(link.target, GetOptions::default())?;
@@ -955,7 +1550,8 @@ pub fn get_post_claps(post_hash: EntryHash) -> ExternResult<u32> {
             if let Some(Entry::App(entry_bytes)) = element.entry() {
                 match EntryTypes::try_from(entry_bytes.clone()) {
                     Ok(EntryTypes::PostClap(clap)) => {
-                        total_claps += clap.count;
+                        let count = per_user.entry(clap.user_id).or_insert(0);
+                        *count = count.saturating_add(clap.count).min(MAX_CLAPS_PER_USER);
                     }
                     _ => {}
                 }
@@ -963,7 +1559,10 @@ pub fn get_post_claps(post_hash: EntryHash) -> ExternResult<u32> {
         }
     }
     
-    Ok(total_claps)
+    Ok(PostClapSummary {
+        total: per_user.values().map(|c| *c as u64).sum(),
+        mine: per_user.get(&agent).copied().unwrap_or(0),
+    })
 }
 
 #[hdk_extern]
@@ -971,8 +1570,8 @@ pub fn like_post(post_hash: EntryHash) -> ExternResult<EntryHash> {
     let user_id = agent_info()?.agent_latest_pubkey();
     
     // Verificar que el post existe
-    let _post = get(post_hash.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))?;
+    let post = get_post_by_hash(&post_hash)?;
+    ensure_not_blocked_by(&post.author_id, &user_id)?;
     
     // Verificar si ya dio like
     let existing_likes = get_links(post_hash.clone(), LinkTypes::PostToLikes, None)?;
@@ -1040,6 +1639,9 @@ pub fn unlike_post(post_hash: EntryHash) -> ExternResult<()> {
 
 #[hdk_extern]
 pub fn get_post_likes(post_hash: EntryHash) -> ExternResult<u32> {
+    if is_orphaned_post(&post_hash)? {
+        return Ok(0);
+    }
     let links = get_links(post_hash, LinkTypes::PostToLikes, None)?;
     Ok(links.len() as u32)
 }
@@ -1078,670 +1680,889 @@ pub struct CommentPostInput {
 
 #[hdk_extern]
 pub fn comment_post(input: CommentPostInput) -> ExternResult<EntryHash> {
-    let author_id = agent_info()?.agent_latest_pubkey();
+    create_comment(CommentTarget::Post(input.post_hash), input.text, input.parent_comment_hash)
+}
+
+// ========== Comments ==========
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentCursor {
+    pub created_at: i64,
+    pub comment_hash: EntryHash,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetCommentThreadInput {
+    pub target: CommentTarget,
+    pub parent_comment_hash: Option<EntryHash>, // Para cargar un subárbol
+    pub cursor: Option<CommentCursor>,
+    pub limit: Option<u32>,
+    pub depth: Option<u32>, // Niveles de respuestas incluidos (0 = solo este nivel)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentNode {
+    pub comment_hash: EntryHash,
+    pub comment: Comment,
+    pub reply_count: u32,
+    pub replies: Vec<CommentNode>, // Vacío más allá de `depth`; usar reply_count
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentThreadPage {
+    pub comments: Vec<CommentNode>,
+    pub next_cursor: Option<CommentCursor>,
+}
+
+#[hdk_extern]
+pub fn get_comment_thread(input: GetCommentThreadInput) -> ExternResult<CommentThreadPage> {
+    let limit = input.limit.unwrap_or(20).min(100) as usize;
+    let depth = input.depth.unwrap_or(1).min(MAX_COMMENT_DEPTH);
+    let agent = agent_info()?.agent_latest_pubkey();
     
-    // Verificar que el post existe
-    let _post = get(input.post_hash.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))?;
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
     
-    // Si es respuesta, verificar que el comentario padre existe
-    if let Some(parent_hash) = input.parent_comment_hash {
-        let _parent = get(parent_hash, GetOptions::default())?
-            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Parent comment not found".into())))?;
+    if let CommentTarget::Post(post_hash) = &input.target {
+        if is_orphaned_post(post_hash)? {
+            return Ok(CommentThreadPage { comments: Vec::new(), next_cursor: None });
+        }
     }
     
-    let comment = PostComment {
-        post_hash: input.post_hash.clone(),
-        author_id,
-        text: input.text,
-        parent_comment_hash: input.parent_comment_hash,
-        created_at: sys_time()?.as_seconds_since_epoch(),
+    let mut level = match &input.parent_comment_hash {
+        Some(parent_hash) => get_comment_replies(parent_hash)?,
+        None => get_root_comments(&input.target)?,
     };
     
-    let comment_hash = create_entry(EntryTypes::PostComment(comment.clone()))?;
-    
-    // Link post to comment
-    create_link(
-        input.post_hash,
-        comment_hash,
-        LinkTypes::PostToComments,
-        (),
-    )?;
+    // Only comments strictly after the cursor (oldest first)
+    if let Some(cursor) = &input.cursor {
+        level.retain(|(hash, comment)| (comment.created_at, hash) > (cursor.created_at, &cursor.comment_hash));
+    }
     
-    // Si es respuesta, link al comentario padre
-    if let Some(parent_hash) = input.parent_comment_hash {
-        create_link(
-            parent_hash,
-            comment_hash,
-            LinkTypes::CommentToReplies,
-            (),
-        )?;
+    let mut comments = Vec::new();
+    let mut next_cursor = None;
+    for (comment_hash, comment) in level {
+        if comments.len() >= limit {
+            next_cursor = comments.last().map(|node: &CommentNode| CommentCursor {
+                created_at: node.comment.created_at,
+                comment_hash: node.comment_hash.clone(),
+            });
+            break;
+        }
+        if is_author_hidden(&comment.author_id, &agent, &my_blocked, &mut hidden_authors)? {
+            continue;
+        }
+        if let Some(node) = build_comment_node(comment_hash, comment, depth, &agent, &my_blocked, &mut hidden_authors)? {
+            comments.push(node);
+        }
     }
     
-    Ok(comment_hash)
+    Ok(CommentThreadPage { comments, next_cursor })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateCommentInput {
+    pub comment_hash: EntryHash,
+    pub text: String,
 }
 
 #[hdk_extern]
-pub fn get_post_comments(post_hash: EntryHash) -> ExternResult<Vec<PostComment>> {
-    let links = get_links(post_hash, LinkTypes::PostToComments, None)?;
-    let mut comments = Vec::new();
+pub fn update_comment(input: UpdateCommentInput) -> ExternResult<Comment> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut comment = get_comment_by_hash(&input.comment_hash)?;
     
-    for link in links {
-        let element = get(link.target, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::PostComment(comment)) => {
-                        // Solo comentarios de nivel raíz (sin padre)
-                        if comment.parent_comment_hash.is_none() {
-                            comments.push(comment);
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
+    if comment.author_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the author can edit this comment".into())));
     }
+    if comment.deleted {
+        return Err(wasm_error!(WasmErrorInner::Guest("Comment was deleted".into())));
+    }
+    check_comment_text(&input.text)?;
     
-    // Sort by created_at ascending (oldest first)
-    comments.sort_by_key(|c| c.created_at);
-    Ok(comments)
+    comment.text = input.text;
+    comment.edited_at = Some(sys_time()?.as_seconds_since_epoch());
+    save_comment_version(&input.comment_hash, &comment)?;
+    
+    Ok(comment)
 }
 
 #[hdk_extern]
-pub fn get_comment_replies(comment_hash: EntryHash) -> ExternResult<Vec<PostComment>> {
-    let links = get_links(comment_hash, LinkTypes::CommentToReplies, None)?;
-    let mut replies = Vec::new();
+pub fn delete_comment(comment_hash: EntryHash) -> ExternResult<()> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut comment = get_comment_by_hash(&comment_hash)?;
     
-    for link in links {
-        let element = get(link.target, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::PostComment(comment)) => {
-                        replies.push(comment);
-                    }
-                    _ => {}
-                }
-            }
-        }
+    if comment.author_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the author can delete this comment".into())));
     }
     
-    // Sort by created_at ascending (oldest first)
-    replies.sort_by_key(|c| c.created_at);
-    Ok(replies)
+    // Soft delete keeps the thread intact for existing replies
+    comment.text = String::new();
+    comment.deleted = true;
+    comment.edited_at = Some(sys_time()?.as_seconds_since_epoch());
+    save_comment_version(&comment_hash, &comment)?;
+    
+    Ok(())
 }
 
-// ========== Post Reports ==========
+// ========== Stories ==========
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct ReportPostInput {
-    pub post_hash: EntryHash,
-    pub reason: String,
-    pub description: Option<String>,
+pub struct CreateStoryInput {
+    pub text: Option<String>,
+    pub image_hashes: Vec<String>,
+    pub video_hash: Option<String>,
+    pub sticker_data: Vec<StickerData>,
 }
 
 #[hdk_extern]
-pub fn report_post(input: ReportPostInput) -> ExternResult<EntryHash> {
-    let reporter_id = agent_info()?.agent_latest_pubkey();
-    
-    // Verificar que el post existe
-    let _post = get(input.post_hash.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))?;
+pub fn create_story(input: CreateStoryInput) -> ExternResult<EntryHash> {
+    let author_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
     
-    // Validar razón
-    let valid_reasons = vec!["spam", "inappropriate", "violence", "harassment", "other"];
-    if !valid_reasons.contains(&input.reason.as_str()) {
-        return Err(wasm_error!(WasmErrorInner::Guest("Invalid reason".into())));
+    if input.image_hashes.is_empty() && input.video_hash.is_none() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Story needs an image or video".into())));
     }
+    check_stickers(&input.sticker_data)
+        .map_err(|reason| wasm_error!(WasmErrorInner::Guest(reason)))?;
     
-    let report = PostReport {
-        post_hash: input.post_hash.clone(),
-        reporter_id,
-        reason: input.reason,
-        description: input.description,
-        created_at: sys_time()?.as_seconds_since_epoch(),
+    let story = Story {
+        author_id: author_id.clone(),
+        text: input.text,
+        image_hashes: input.image_hashes,
+        video_hash: input.video_hash,
+        sticker_data: input.sticker_data,
+        created_at: now,
+        expires_at: now + STORY_TTL_SECONDS,
     };
     
-    let report_hash = create_entry(EntryTypes::PostReport(report))?;
+    let story_hash = create_entry(EntryTypes::Story(story))?;
     
-    // Link post to report
+    // Link user to story
     create_link(
-        input.post_hash,
-        report_hash,
-        LinkTypes::PostToReports,
+        author_id,
+        story_hash.clone(),
+        LinkTypes::UserToStories,
         (),
     )?;
     
-    Ok(report_hash)
+    Ok(story_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoryItem {
+    pub story_hash: EntryHash,
+    pub story: Story,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StoryGroup {
+    pub author_id: AgentPubKey,
+    pub stories: Vec<StoryItem>, // Oldest first, in viewing order
 }
 
 #[hdk_extern]
-pub fn get_post_reports(post_hash: EntryHash) -> ExternResult<Vec<PostReport>> {
-    let links = get_links(post_hash, LinkTypes::PostToReports, None)?;
-    let mut reports = Vec::new();
-    
-    for link in links {
-        let element = get(link.target, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::PostReport(report)) => {
-                        reports.push(report);
-                    }
-                    _ => {}
-                }
-            }
+pub fn get_friend_stories(_: ()) -> ExternResult<Vec<StoryGroup>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
+    let mut groups = Vec::new();
+    
+    for friend_id in get_friends(())? {
+        if is_author_hidden(&friend_id, &agent, &my_blocked, &mut hidden_authors)? {
+            continue;
+        }
+        
+        let stories = get_active_stories(&friend_id, now)?;
+        if !stories.is_empty() {
+            groups.push(StoryGroup { author_id: friend_id, stories });
         }
     }
     
-    // Sort by created_at descending (newest first)
-    reports.sort_by_key(|r| -(r.created_at as i64));
-    Ok(reports)
+    // Authors with the most recent story first
+    groups.sort_by_key(|g| std::cmp::Reverse(g.stories.last().map(|s| s.story.created_at).unwrap_or(0)));
+    Ok(groups)
 }
 
-// ========== Ghost Mode ==========
-
 #[hdk_extern]
-pub fn set_ghost_mode(enabled: bool) -> ExternResult<User> {
-    let agent = agent_info()?.agent_latest_pubkey();
+pub fn view_story(story_hash: EntryHash) -> ExternResult<EntryHash> {
+    let viewer_id = agent_info()?.agent_latest_pubkey();
     let now = sys_time()?.as_seconds_since_epoch();
     
-    // Get user entry
-    let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
-    let mut user_hash_opt = None;
-    for link in links {
-        let element = get(link.target, GetOptions::default())?;
+    let story = get_story_by_hash(&story_hash)?;
+    if now > story.expires_at {
+        return Err(wasm_error!(WasmErrorInner::Guest("Story expired".into())));
+    }
+    ensure_not_blocked_by(&story.author_id, &viewer_id)?;
+    
+    // El autor no genera recibos propios; un recibo por espectador
+    if story.author_id == viewer_id {
+        return Ok(story_hash);
+    }
+    let existing_links = get_links(story_hash.clone(), LinkTypes::StoryToViews, None)?;
+    for link in existing_links {
+        let element = get(link.target.clone(), GetOptions::default())?;
         if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::User(_)) => {
-                        user_hash_opt = Some(link.target);
-                        break;
+            if let Some(view) = element.entry().to_app_option::<StoryView>()? {
+                if view.viewer_id == viewer_id {
+                    if let Some(view_hash) = link.target.into_entry_hash() {
+                        return Ok(view_hash);
                     }
-                    _ => {}
                 }
             }
         }
     }
     
-    let user_hash = user_hash_opt.ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
-    let element = get(user_hash.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
-    
-    let mut user: User = element.entry()
-        .to_app_option()?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User entry not found".into())))?;
-    
-    user.ghost_mode = enabled;
-    user.last_seen = now;
-    
-    let updated_hash = create_entry(EntryTypes::User(user.clone()))?;
-    
-    // Update link
-    let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
-    for link in links {
-        if link.target == user_hash {
-            delete_link(link.create_link_hash)?;
-        }
-    }
-    create_link(agent, updated_hash, LinkTypes::UserToProfileImage, ())?;
-    
-    Ok(user)
-}
-
-#[hdk_extern]
-pub fn update_last_seen(_: ()) -> ExternResult<()> {
-    let agent = agent_info()?.agent_latest_pubkey();
-    let mut user = get_user_by_agent(&agent)?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    let view = StoryView {
+        story_hash: story_hash.clone(),
+        viewer_id,
+        viewed_at: now,
+    };
     
-    user.last_seen = sys_time()?.as_seconds_since_epoch();
+    let view_hash = create_entry(EntryTypes::StoryView(view))?;
     
-    // Update user entry
-    let user_hash = hash_entry(&EntryTypes::User(user.clone()))?;
-    // In production, need to update existing entry
-    let _ = create_entry(EntryTypes::User(user))?;
+    // Link story to view receipt
+    create_link(
+        story_hash,
+        view_hash.clone(),
+        LinkTypes::StoryToViews,
+        (),
+    )?;
     
-    Ok(())
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct ShareLocationInput {
-    pub lat: f64,
-    pub lon: f64,
-    pub share_with: AgentPubKey,
+    Ok(view_hash)
 }
 
 #[hdk_extern]
-pub fn share_location(input: ShareLocationInput) -> ExternResult<String> {
+pub fn get_story_views(story_hash: EntryHash) -> ExternResult<Vec<StoryView>> {
     let agent = agent_info()?.agent_latest_pubkey();
     
-    // Update user location
-    let mut user = get_user_by_agent(&agent)?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
-    
-    user.lat = Some(input.lat);
-    user.lon = Some(input.lon);
-    user.last_seen = sys_time()?.as_seconds_since_epoch();
-    
-    // Update user entry
-    let _ = create_entry(EntryTypes::User(user))?;
-    
-    // Create a location sharing entry (simplified - in production, use proper entry type)
-    // For now, we'll use the user's location which is updated in real-time
-    
-    Ok("Location shared".to_string())
-}
-
-#[hdk_extern]
-pub fn get_shared_location(user_id: AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
-    let user = get_user_by_agent(&user_id)?;
-    
-    if let Some(u) = user {
-        if let (Some(lat), Some(lon)) = (u.lat, u.lon) {
-            // Check if location sharing is enabled for this user
-            // For now, return location if available
-            Ok(Some((lat, lon)))
-        } else {
-            Ok(None)
-        }
-    } else {
-        Ok(None)
+    let story = get_story_by_hash(&story_hash)?;
+    if story.author_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the author can see story views".into())));
     }
-}
-    let agent = agent_info()?.agent_latest_pubkey();
-    let now = sys_time()?.as_seconds_since_epoch();
     
-    // Get user entry
-    let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
-    let mut user_hash_opt = None;
+    let links = get_links(story_hash, LinkTypes::StoryToViews, None)?;
+    let mut views = Vec::new();
+    
     for link in links {
         let element = get(link.target, GetOptions::default())?;
         if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::User(_)) => {
-                        user_hash_opt = Some(link.target);
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-        }
-    }
-    
-    let user_hash = user_hash_opt.ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
-    let element = get(user_hash.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
-    
-    let mut user: User = element.entry()
-        .to_app_option()?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User entry not found".into())))?;
-    
-    // Solo actualizar last_seen si no está en modo ghost
-    if !user.ghost_mode {
-        user.last_seen = now;
-        let updated_hash = create_entry(EntryTypes::User(user))?;
-        
-        // Update link
-        let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
-        for link in links {
-            if link.target == user_hash {
-                delete_link(link.create_link_hash)?;
+            if let Some(view) = element.entry().to_app_option::<StoryView>()? {
+                views.push(view);
             }
         }
-        create_link(agent, updated_hash, LinkTypes::UserToProfileImage, ())?;
     }
     
-    Ok(())
+    // Sort by viewed_at descending (newest first)
+    views.sort_by_key(|v| std::cmp::Reverse(v.viewed_at));
+    Ok(views)
 }
 
-// ========== User Blocking ==========
+// ========== Sticker Packs ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateStickerPackInput {
+    pub name: String,
+    pub image_hashes: Vec<String>,
+}
 
 #[hdk_extern]
-pub fn block_user(blocked_id: AgentPubKey) -> ExternResult<EntryHash> {
-    let blocker_id = agent_info()?.agent_latest_pubkey();
+pub fn create_sticker_pack(input: CreateStickerPackInput) -> ExternResult<EntryHash> {
+    let creator_id = agent_info()?.agent_latest_pubkey();
     
-    if blocker_id == blocked_id {
-        return Err(wasm_error!(WasmErrorInner::Guest("Cannot block yourself".into())));
-    }
+    check_sticker_pack(&input.name, &input.image_hashes)
+        .map_err(|reason| wasm_error!(WasmErrorInner::Guest(reason)))?;
     
-    let block = UserBlock {
-        blocker_id,
-        blocked_id,
+    let pack = StickerPack {
+        creator_id: creator_id.clone(),
+        name: input.name,
+        image_hashes: input.image_hashes,
         created_at: sys_time()?.as_seconds_since_epoch(),
     };
     
-    let block_hash = create_entry(EntryTypes::UserBlock(block))?;
+    let pack_hash = create_entry(EntryTypes::StickerPack(pack))?;
     
-    // Link user to block
+    // Link creator to pack
     create_link(
-        blocker_id,
-        block_hash,
-        LinkTypes::UserToBlocks,
+        creator_id,
+        pack_hash.clone(),
+        LinkTypes::UserToStickerPacks,
         (),
     )?;
     
-    Ok(block_hash)
+    // Link to public catalogue
+    create_link(
+        Path::from(STICKER_PACKS_ROOT).path_entry_hash()?,
+        pack_hash.clone(),
+        LinkTypes::StickerPacks,
+        (),
+    )?;
+    
+    Ok(pack_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StickerPackItem {
+    pub pack_hash: EntryHash,
+    pub pack: StickerPack,
 }
 
 #[hdk_extern]
-pub fn unblock_user(blocked_id: AgentPubKey) -> ExternResult<()> {
-    let blocker_id = agent_info()?.agent_latest_pubkey();
-    
-    let links = get_links(blocker_id, LinkTypes::UserToBlocks, None)?;
+pub fn get_sticker_packs(_: ()) -> ExternResult<Vec<StickerPackItem>> {
+    let links = get_links(Path::from(STICKER_PACKS_ROOT).path_entry_hash()?, LinkTypes::StickerPacks, None)?;
+    get_sticker_packs_from_links(links)
+}
+
+#[hdk_extern]
+pub fn get_user_sticker_packs(user_id: AgentPubKey) -> ExternResult<Vec<StickerPackItem>> {
+    let links = get_links(user_id, LinkTypes::UserToStickerPacks, None)?;
+    get_sticker_packs_from_links(links)
+}
+
+fn get_sticker_packs_from_links(links: Vec<Link>) -> ExternResult<Vec<StickerPackItem>> {
+    let mut packs = Vec::new();
     
     for link in links {
-        let element = get(link.target, GetOptions::default())?;
+        let pack_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let element = get(pack_hash.clone(), GetOptions::default())?;
         if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::UserBlock(block)) => {
-                        if block.blocked_id == blocked_id {
-                            delete_entry(link.target)?;
-                            delete_link(link.create_link_hash)?;
-                            return Ok(());
-                        }
-                    }
-                    _ => {}
-                }
+            if let Some(pack) = element.entry().to_app_option::<StickerPack>()? {
+                packs.push(StickerPackItem { pack_hash, pack });
             }
         }
     }
     
-    Err(wasm_error!(WasmErrorInner::Guest("Block not found".into())))
+    // Sort by created_at descending (newest first)
+    packs.sort_by_key(|p| std::cmp::Reverse(p.pack.created_at));
+    Ok(packs)
+}
+
+// ========== Hashtags & Mentions ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetPostsByHashtagInput {
+    pub tag: String,
+    pub cursor: Option<FeedCursor>,
+    pub limit: Option<u32>,
 }
 
 #[hdk_extern]
-pub fn is_user_blocked(user_id: AgentPubKey) -> ExternResult<bool> {
-    let current_user = agent_info()?.agent_latest_pubkey();
+pub fn get_posts_by_hashtag(input: GetPostsByHashtagInput) -> ExternResult<FeedPage> {
+    let limit = input.limit.unwrap_or(50).min(100) as usize;
+    let tag = normalize_hashtag(&input.tag)
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Invalid hashtag".into())))?;
+    let agent = agent_info()?.agent_latest_pubkey();
     
-    let links = get_links(current_user, LinkTypes::UserToBlocks, None)?;
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
+    let mut hidden_posts = hidden_targets()?;
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
     
+    let links = get_links(hashtag_path(&tag).path_entry_hash()?, LinkTypes::HashtagToPosts, None)?;
     for link in links {
-        let element = get(link.target, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::UserBlock(block)) => {
-                        if block.blocked_id == user_id {
-                            return Ok(true);
-                        }
-                    }
-                    _ => {}
-                }
+        let post_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if !seen.insert(post_hash.clone()) {
+            continue;
+        }
+        
+        let post = match get_latest_post(&post_hash)? {
+            Some(post) => post,
+            None => continue,
+        };
+        
+        // Only posts strictly older than the cursor
+        if let Some(cursor) = &input.cursor {
+            if (post.created_at, &post_hash) >= (cursor.created_at, &cursor.post_hash) {
+                continue;
             }
         }
+        if is_author_hidden(&post.author_id, &agent, &my_blocked, &mut hidden_authors)? {
+            continue;
+        }
+        if is_post_hidden(&post_hash, &mut hidden_posts)? {
+            continue;
+        }
+        items.push(FeedItem { post_hash, post });
     }
     
-    Ok(false)
+    // Sort by created_at descending (newest first), hash as tie-breaker
+    items.sort_by(|a, b| (b.post.created_at, &b.post_hash).cmp(&(a.post.created_at, &a.post_hash)));
+    
+    let next_cursor = if items.len() > limit {
+        items.truncate(limit);
+        items.last().map(|item| FeedCursor {
+            created_at: item.post.created_at,
+            post_hash: item.post_hash.clone(),
+        })
+    } else {
+        None
+    };
+    
+    Ok(FeedPage { items, next_cursor })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HashtagCount {
+    pub tag: String,
+    pub count: u32,
 }
 
 #[hdk_extern]
-pub fn get_blocked_users(_: ()) -> ExternResult<Vec<AgentPubKey>> {
-    let blocker_id = agent_info()?.agent_latest_pubkey();
-    let mut blocked = Vec::new();
-    
-    let links = get_links(blocker_id, LinkTypes::UserToBlocks, None)?;
-    
-    for link in links {
-        let element = get(link.target, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::UserBlock(block)) => {
-                        blocked.push(block.blocked_id);
-                    }
-                    _ => {}
-                }
+pub fn get_trending_hashtags(window_hours: Option<u32>) -> ExternResult<Vec<HashtagCount>> {
+    // Ventana en horas (por defecto 24, máximo una semana)
+    let window_hours = window_hours.unwrap_or(24).clamp(1, 168) as i64;
+    let now_hour = sys_time()?.as_seconds_since_epoch().div_euclid(SECONDS_PER_HOUR);
+    
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for hour in (now_hour - window_hours + 1)..=now_hour {
+        let links = get_links(hashtag_usage_path(hour).path_entry_hash()?, LinkTypes::HashtagUsage, None)?;
+        for link in links {
+            if let Ok(tag) = String::from_utf8(link.tag.into_inner()) {
+                *counts.entry(tag).or_insert(0) += 1;
             }
         }
     }
     
-    Ok(blocked)
+    let mut trending: Vec<HashtagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| HashtagCount { tag, count })
+        .collect();
+    trending.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    trending.truncate(20);
+    
+    Ok(trending)
 }
 
-// ========== Tamagochi ==========
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MentionCursor {
+    pub created_at: i64,
+    pub target_hash: EntryHash,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct CreateTamagochiInput {
-    pub name: String,
+pub struct GetMentionsInput {
+    pub cursor: Option<MentionCursor>,
+    pub limit: Option<u32>,
 }
 
-#[hdk_extern]
-pub fn create_tamagochi(input: CreateTamagochiInput) -> ExternResult<EntryHash> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
-    let now = sys_time()?.as_seconds_since_epoch();
-    
-    let tamagochi = Tamagochi {
-        owner_id,
-        name: input.name,
-        stage: "egg".to_string(),
-        energy: 100,
-        hunger: 100,
-        hygiene: 100,
-        happiness: 100,
-        experience: 0,
-        level: 1,
-        born_at: now,
-        last_fed_at: now,
-        last_cleaned_at: now,
-        last_played_at: now,
-        is_alive: true,
-    };
-    
-    let tamagochi_hash = create_entry(EntryTypes::Tamagochi(tamagochi))?;
-    
-    // Link user to tamagochi
-    create_link(
-        owner_id,
-        tamagochi_hash,
-        LinkTypes::UserToTamagochi,
-        (),
-    )?;
-    
-    Ok(tamagochi_hash)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MentionItem {
+    pub target_hash: EntryHash,
+    pub kind: String, // "post", "comment"
+    pub context: CommentTarget, // Post mencionado, o post/producto del comentario
+    pub author_id: AgentPubKey,
+    pub text: String,
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MentionsPage {
+    pub items: Vec<MentionItem>,
+    pub next_cursor: Option<MentionCursor>,
 }
 
 #[hdk_extern]
-pub fn get_tamagochi(_: ()) -> ExternResult<Option<Tamagochi>> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
+pub fn get_my_mentions(input: GetMentionsInput) -> ExternResult<MentionsPage> {
+    let limit = input.limit.unwrap_or(50).min(100) as usize;
+    let agent = agent_info()?.agent_latest_pubkey();
     
-    let links = get_links(owner_id, LinkTypes::UserToTamagochi, None)?;
+    let my_blocked = get_blocked_users(())?;
+    let mut hidden_authors: HashMap<AgentPubKey, bool> = HashMap::new();
+    let mut items = Vec::new();
     
+    let links = get_links(agent.clone(), LinkTypes::AgentToMentions, None)?;
     for link in links {
-        let element = get(link.target, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::Tamagochi(tamagochi)) => {
-                        if tamagochi.is_alive {
-                            return Ok(Some(tamagochi));
-                        }
-                    }
-                    _ => {}
+        let target_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let kind = String::from_utf8(link.tag.into_inner()).unwrap_or_default();
+        
+        let item = if kind == MENTION_IN_POST {
+            get_latest_post(&target_hash)?.map(|post| MentionItem {
+                target_hash: target_hash.clone(),
+                kind: kind.clone(),
+                context: CommentTarget::Post(target_hash.clone()),
+                author_id: post.author_id,
+                text: post.text.unwrap_or_default(),
+                created_at: post.created_at,
+            })
+        } else {
+            get_latest_comment(&target_hash)?
+                .filter(|comment| !comment.deleted)
+                .map(|comment| MentionItem {
+                    target_hash: target_hash.clone(),
+                    kind: kind.clone(),
+                    context: comment.target,
+                    author_id: comment.author_id,
+                    text: comment.text,
+                    created_at: comment.created_at,
+                })
+        };
+        
+        if let Some(item) = item {
+            // Only mentions strictly older than the cursor
+            if let Some(cursor) = &input.cursor {
+                if (item.created_at, &item.target_hash) >= (cursor.created_at, &cursor.target_hash) {
+                    continue;
                 }
             }
+            if is_author_hidden(&item.author_id, &agent, &my_blocked, &mut hidden_authors)? {
+                continue;
+            }
+            items.push(item);
         }
     }
     
-    Ok(None)
+    // Sort by created_at descending (newest first)
+    items.sort_by(|a, b| (b.created_at, &b.target_hash).cmp(&(a.created_at, &a.target_hash)));
+    
+    let next_cursor = if items.len() > limit {
+        items.truncate(limit);
+        items.last().map(|item| MentionCursor {
+            created_at: item.created_at,
+            target_hash: item.target_hash.clone(),
+        })
+    } else {
+        None
+    };
+    
+    Ok(MentionsPage { items, next_cursor })
+}
+
+// ========== Reports ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReportInput {
+    pub target: ReportTarget,
+    pub reason: String,
+    pub description: Option<String>,
+    pub evidence_image_hashes: Vec<String>,
 }
 
 #[hdk_extern]
-pub fn feed_tamagochi(_: ()) -> ExternResult<Tamagochi> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
-    let now = sys_time()?.as_seconds_since_epoch();
+pub fn report(input: ReportInput) -> ExternResult<EntryHash> {
+    let reporter_id = agent_info()?.agent_latest_pubkey();
     
-    let mut tamagochi = get_tamagochi(())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
+    check_report(&input.reason, &input.description, &input.evidence_image_hashes)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(e)))?;
+    
+    // Verificar que el objetivo existe y encontrar a quién se reporta
+    let (reported_agent, target_action) = get_reported_agent(&input.target, &reporter_id)?;
+    if reported_agent == reporter_id {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot report yourself".into())));
+    }
     
-    if !tamagochi.is_alive {
-        return Err(wasm_error!(WasmErrorInner::Guest("Tamagochi is dead".into())));
+    // Un reporte por usuario y objetivo, encontrado por el tag del link
+    let base = input.target.base();
+    let tag = LinkTag::new(reporter_id.get_raw_39().to_vec());
+    if !get_links(base.clone(), LinkTypes::TargetToReports, Some(tag.clone()))?.is_empty() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Already reported".into())));
     }
     
-    tamagochi.hunger = (tamagochi.hunger + 30).min(100);
-    tamagochi.happiness = (tamagochi.happiness + 5).min(100);
-    tamagochi.last_fed_at = now;
-    tamagochi.experience += 5;
+    let report = Report {
+        target: input.target.clone(),
+        reported_agent: reported_agent.clone(),
+        target_action,
+        reporter_id,
+        reason: input.reason,
+        description: input.description,
+        evidence_image_hashes: input.evidence_image_hashes,
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let report_hash = create_entry(EntryTypes::Report(report))?;
     
-    check_tamagochi_evolution(&mut tamagochi);
+    // Link target to report
+    create_link(
+        base,
+        report_hash.clone(),
+        LinkTypes::TargetToReports,
+        tag,
+    )?;
     
-    let _hash = create_entry(EntryTypes::Tamagochi(tamagochi.clone()))?;
-    Ok(tamagochi)
+    // Link reported agent to report (repeat offenders)
+    create_link(
+        reported_agent,
+        report_hash.clone(),
+        LinkTypes::AgentToReports,
+        (),
+    )?;
+    
+    // Reaching the threshold hides the target and queues it for review
+    let status = get_moderation_status(&input.target, report_hide_threshold()?)?;
+    let moderator_hidden = matches!(status.decision, Some(ModerationAction::Hide) | Some(ModerationAction::Remove));
+    if status.pending_reports >= status.threshold && !moderator_hidden {
+        enqueue_for_moderation(&input.target)?;
+    }
+    
+    Ok(report_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReportPostInput {
+    pub post_hash: EntryHash,
+    pub reason: String,
+    pub description: Option<String>,
 }
 
 #[hdk_extern]
-pub fn clean_tamagochi(_: ()) -> ExternResult<Tamagochi> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
-    let now = sys_time()?.as_seconds_since_epoch();
+pub fn report_post(input: ReportPostInput) -> ExternResult<EntryHash> {
+    report(ReportInput {
+        target: ReportTarget::Post(input.post_hash),
+        reason: input.reason,
+        description: input.description,
+        evidence_image_hashes: Vec::new(),
+    })
+}
+
+#[hdk_extern]
+pub fn get_reports(target: ReportTarget) -> ExternResult<Vec<Report>> {
+    let mut reports = load_reports(get_links(target.base(), LinkTypes::TargetToReports, None)?)?;
+    reports.retain(|r| r.target == target);
+    Ok(reports)
+}
+
+#[hdk_extern]
+pub fn get_post_reports(post_hash: EntryHash) -> ExternResult<Vec<Report>> {
+    get_reports(ReportTarget::Post(post_hash))
+}
+
+/// Every report against an agent's content, across all target types (moderators only)
+#[hdk_extern]
+pub fn get_agent_reports(agent: AgentPubKey) -> ExternResult<Vec<Report>> {
+    ensure_role(Role::Moderator)?;
+    load_reports(get_links(agent, LinkTypes::AgentToReports, None)?)
+}
+
+// ========== Moderation ==========
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModerationQueueItem {
+    pub target: ReportTarget,
+    pub post: Option<Post>, // Solo para posts; None si el autor lo borró
+    pub reported_agent: Option<AgentPubKey>,
+    pub agent_report_count: u32, // Reportes totales contra el agente
+    pub report_count: u32, // Reportes desde la última decisión
+    pub reports: Vec<Report>,
+    pub queued_at: i64,
+}
+
+#[hdk_extern]
+pub fn get_moderation_queue(_: ()) -> ExternResult<Vec<ModerationQueueItem>> {
+    ensure_role(Role::Moderator)?;
     
-    let mut tamagochi = get_tamagochi(())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
+    let links = get_links(Path::from(MODERATION_QUEUE_ROOT).path_entry_hash()?, LinkTypes::ModerationQueue, None)?;
+    let threshold = report_hide_threshold()?;
+    let mut seen = HashSet::new();
+    let mut queue = Vec::new();
     
-    if !tamagochi.is_alive {
-        return Err(wasm_error!(WasmErrorInner::Guest("Tamagochi is dead".into())));
+    for link in links {
+        let kind = String::from_utf8(link.tag.into_inner()).unwrap_or_default();
+        let target = match ReportTarget::from_parts(&kind, link.target) {
+            Some(target) => target,
+            None => continue,
+        };
+        if !seen.insert(target.base()) {
+            continue;
+        }
+        
+        // Queue links belong to the reporters, so resolved targets are skipped instead of unlinked
+        let status = get_moderation_status(&target, threshold)?;
+        let moderator_hidden = matches!(status.decision, Some(ModerationAction::Hide) | Some(ModerationAction::Remove));
+        if status.pending_reports < threshold || moderator_hidden {
+            continue;
+        }
+        let mut reports = get_reports(target.clone())?;
+        reports.retain(|r| r.created_at >= status.decided_at.unwrap_or(i64::MIN));
+        
+        let post = match &target {
+            ReportTarget::Post(post_hash) => get_latest_post(post_hash)?,
+            _ => None,
+        };
+        let reported_agent = reports.first().map(|r| r.reported_agent.clone());
+        let agent_report_count = match &reported_agent {
+            Some(agent) => get_links(agent.clone(), LinkTypes::AgentToReports, None)?.len() as u32,
+            None => 0,
+        };
+        
+        queue.push(ModerationQueueItem {
+            target,
+            post,
+            reported_agent,
+            agent_report_count,
+            report_count: status.pending_reports,
+            reports,
+            queued_at: link.timestamp.as_seconds_since_epoch(),
+        });
     }
     
-    tamagochi.hygiene = 100;
-    tamagochi.happiness = (tamagochi.happiness + 10).min(100);
-    tamagochi.last_cleaned_at = now;
-    tamagochi.experience += 3;
-    
-    check_tamagochi_evolution(&mut tamagochi);
-    
-    let _hash = create_entry(EntryTypes::Tamagochi(tamagochi.clone()))?;
-    Ok(tamagochi)
+    // Most reported first, then oldest in queue
+    queue.sort_by(|a, b| b.report_count.cmp(&a.report_count).then_with(|| a.queued_at.cmp(&b.queued_at)));
+    Ok(queue)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResolveReportInput {
+    pub target: ReportTarget,
+    pub action: ModerationAction,
+    pub note: Option<String>,
 }
 
 #[hdk_extern]
-pub fn play_with_tamagochi(_: ()) -> ExternResult<Tamagochi> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
-    let now = sys_time()?.as_seconds_since_epoch();
+pub fn resolve_report(input: ResolveReportInput) -> ExternResult<EntryHash> {
+    let (moderator_id, authority) = ensure_role(Role::Moderator)?;
     
-    let mut tamagochi = get_tamagochi(())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
-    
-    if !tamagochi.is_alive {
-        return Err(wasm_error!(WasmErrorInner::Guest("Tamagochi is dead".into())));
+    let status = get_moderation_status(&input.target, report_hide_threshold()?)?;
+    if status.decision == Some(ModerationAction::Remove) {
+        return Err(wasm_error!(WasmErrorInner::Guest("Already removed".into())));
     }
     
-    tamagochi.energy = tamagochi.energy.saturating_sub(10);
-    tamagochi.happiness = (tamagochi.happiness + 15).min(100);
-    tamagochi.last_played_at = now;
-    tamagochi.experience += 10;
+    let base = input.target.base();
+    let decision = ModerationDecision {
+        target: input.target,
+        moderator_id,
+        action: input.action,
+        note: input.note,
+        authority,
+        decided_at: sys_time()?.as_seconds_since_epoch(),
+    };
     
-    check_tamagochi_evolution(&mut tamagochi);
+    let decision_hash = create_entry(EntryTypes::ModerationDecision(decision))?;
     
-    let _hash = create_entry(EntryTypes::Tamagochi(tamagochi.clone()))?;
-    Ok(tamagochi)
+    // Link target to decision
+    create_link(
+        base,
+        decision_hash.clone(),
+        LinkTypes::TargetToModeration,
+        (),
+    )?;
+    
+    // Leaves the queue (get_moderation_queue skips it) until enough new reports arrive
+    Ok(decision_hash)
 }
 
+// ========== Roles ==========
+
 #[hdk_extern]
-pub fn kill_tamagochi(_: ()) -> ExternResult<EntryHash> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
-    let now = sys_time()?.as_seconds_since_epoch();
-    
-    let mut tamagochi = get_tamagochi(())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
+pub fn get_my_roles(_: ()) -> ExternResult<Vec<Role>> {
+    let agent = agent_info()?.agent_latest_pubkey();
     
-    if !tamagochi.is_alive {
-        return Err(wasm_error!(WasmErrorInner::Guest("Tamagochi already dead".into())));
+    let mut roles = Vec::new();
+    if dna_properties()?.progenitor.as_ref() == Some(&agent) {
+        roles.push(Role::Admin);
+    }
+    for (_, grant) in get_active_role_grants(&agent)? {
+        if !roles.contains(&grant.role) {
+            roles.push(grant.role);
+        }
     }
     
-    let age = now - tamagochi.born_at;
-    tamagochi.is_alive = false;
+    Ok(roles)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoleInput {
+    pub agent: AgentPubKey,
+    pub role: Role,
+}
+
+#[hdk_extern]
+pub fn grant_role(input: RoleInput) -> ExternResult<ActionHash> {
+    let (granted_by, authority) = ensure_role(Role::Admin)?;
+    let now = sys_time()?.as_seconds_since_epoch();
     
-    let _tamagochi_hash = create_entry(EntryTypes::Tamagochi(tamagochi.clone()))?;
+    // Re-granting renews a grant close to expiry
+    if get_active_role_grants(&input.agent)?
+        .iter()
+        .any(|(_, g)| g.role == input.role && g.expires_at - now > ROLE_GRANT_RENEWAL_SECONDS)
+    {
+        return Err(wasm_error!(WasmErrorInner::Guest("Role already granted".into())));
+    }
     
-    // Create death record
-    let death = TamagochiDeath {
-        owner_id,
-        tamagochi_name: tamagochi.name.clone(),
-        death_reason: "killed".to_string(),
-        died_at: now,
-        age_seconds: age,
+    let grant = RoleGrant {
+        grantee: input.agent.clone(),
+        role: input.role,
+        granted_by,
+        authority,
+        granted_at: now,
+        expires_at: now + ROLE_GRANT_TTL_SECONDS,
     };
     
-    let death_hash = create_entry(EntryTypes::TamagochiDeath(death))?;
+    let grant_hash = create_entry(EntryTypes::RoleGrant(grant))?;
     
-    // Link user to death
+    // Link grantee to grant
     create_link(
-        owner_id,
-        death_hash,
-        LinkTypes::UserToDeaths,
+        input.agent,
+        grant_hash.clone(),
+        LinkTypes::AgentToRoleGrants,
         (),
     )?;
     
-    Ok(death_hash)
+    Ok(grant_hash)
 }
 
 #[hdk_extern]
-pub fn update_tamagochi_state(_: ()) -> ExternResult<Tamagochi> {
-    let mut tamagochi = get_tamagochi(())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
-    
-    if !tamagochi.is_alive {
-        return Ok(tamagochi);
-    }
-    
+pub fn revoke_role(input: RoleInput) -> ExternResult<()> {
+    let (revoked_by, authority) = ensure_role(Role::Admin)?;
     let now = sys_time()?.as_seconds_since_epoch();
     
-    // Decrease stats over time
-    let hours_since_fed = (now - tamagochi.last_fed_at) / 3600;
-    tamagochi.hunger = tamagochi.hunger.saturating_sub((hours_since_fed * 5) as u32).max(0);
-    
-    let hours_since_cleaned = (now - tamagochi.last_cleaned_at) / 3600;
-    tamagochi.hygiene = tamagochi.hygiene.saturating_sub((hours_since_cleaned * 3) as u32).max(0);
-    
-    // Energy regenerates slowly
-    if tamagochi.energy < 100 {
-        tamagochi.energy = (tamagochi.energy + 1).min(100);
+    let grants: Vec<ActionHash> = get_active_role_grants(&input.agent)?
+        .into_iter()
+        .filter(|(_, g)| g.role == input.role)
+        .map(|(hash, _)| hash)
+        .collect();
+    if grants.is_empty() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Role not granted".into())));
     }
     
-    // Check for death
-    if tamagochi.hunger == 0 {
-        tamagochi.is_alive = false;
-        let age = now - tamagochi.born_at;
-        
-        let death = TamagochiDeath {
-            owner_id: tamagochi.owner_id,
-            tamagochi_name: tamagochi.name.clone(),
-            death_reason: "starvation".to_string(),
-            died_at: now,
-            age_seconds: age,
+    for grant_hash in grants {
+        let revocation = RoleRevocation {
+            grant_hash: grant_hash.clone(),
+            revoked_by: revoked_by.clone(),
+            authority: authority.clone(),
+            revoked_at: now,
         };
+        let revocation_hash = create_entry(EntryTypes::RoleRevocation(revocation))?;
         
-        let death_hash = create_entry(EntryTypes::TamagochiDeath(death))?;
+        // Link grant to revocation
         create_link(
-            tamagochi.owner_id,
-            death_hash,
-            LinkTypes::UserToDeaths,
+            grant_hash,
+            revocation_hash,
+            LinkTypes::RoleGrantToRevocations,
             (),
         )?;
     }
     
-    check_tamagochi_evolution(&mut tamagochi);
-    
-    let _hash = create_entry(EntryTypes::Tamagochi(tamagochi.clone()))?;
-    Ok(tamagochi)
+    Ok(())
 }
 
+// ========== Ghost Mode ==========
+
 #[hdk_extern]
-pub fn get_tamagochi_deaths(_: ()) -> ExternResult<Vec<TamagochiDeath>> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
-    let mut deaths = Vec::new();
-    
-    let links = get_links(owner_id, LinkTypes::UserToDeaths, None)?;
+pub fn set_ghost_mode(enabled: bool) -> ExternResult<User> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
     
+    // Get user entry
+    let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
+    let mut user_hash_opt = None;
     for link in links {
         let element = get(link.target, GetOptions::default())?;
         if let Some(element) = element {
             if let Some(Entry::App(entry_bytes)) = element.entry() {
                 match EntryTypes::try_from(entry_bytes.clone()) {
-                    Ok(EntryTypes::TamagochiDeath(death)) => {
-                        deaths.push(death);
+                    Ok(EntryTypes::User(_)) => {
+                        user_hash_opt = Some(link.target);
+                        break;
                     }
                     _ => {}
                 }
@@ -1749,800 +2570,3768 @@ pub fn get_tamagochi_deaths(_: ()) -> ExternResult<Vec<TamagochiDeath>> {
         }
     }
     
-    // Sort by death time (newest first)
-    deaths.sort_by_key(|d| -(d.died_at as i64));
-    Ok(deaths)
-}
-
-fn check_tamagochi_evolution(tamagochi: &mut Tamagochi) {
-    let old_stage = tamagochi.stage.clone();
+    let user_hash = user_hash_opt.ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    let element = get(user_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
     
-    if tamagochi.experience >= 100 && tamagochi.stage == "egg" {
-        tamagochi.stage = "baby".to_string();
-        tamagochi.level = 2;
-    } else if tamagochi.experience >= 300 && tamagochi.stage == "baby" {
-        tamagochi.stage = "child".to_string();
-        tamagochi.level = 3;
-    } else if tamagochi.experience >= 600 && tamagochi.stage == "child" {
-        tamagochi.stage = "teen".to_string();
-        tamagochi.level = 4;
-    } else if tamagochi.experience >= 1000 && tamagochi.stage == "teen" {
-        tamagochi.stage = "adult".to_string();
-        tamagochi.level = 5;
-    }
+    let mut user: User = element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User entry not found".into())))?;
+    
+    user.ghost_mode = enabled;
+    user.last_seen = now;
+    
+    let updated_hash = create_entry(EntryTypes::User(user.clone()))?;
     
-    if old_stage != tamagochi.stage {
-        tamagochi.happiness = 100; // Evolution makes them happy
+    // Update link
+    let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
+    for link in links {
+        if link.target == user_hash {
+            delete_link(link.create_link_hash)?;
+        }
     }
+    create_link(agent, updated_hash, LinkTypes::UserToProfileImage, ())?;
+    
+    Ok(user)
 }
 
 #[hdk_extern]
-pub fn auto_grow_tamagochi(_: ()) -> ExternResult<Tamagochi> {
-    let mut tamagochi = get_tamagochi(())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
-    
-    if !tamagochi.is_alive {
-        return Ok(tamagochi);
-    }
-    
-    // Auto-grow: increase experience on each app entry
-    tamagochi.experience += 10;
+pub fn update_last_seen(_: ()) -> ExternResult<()> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut user = get_user_by_agent(&agent)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
     
-    // Auto-feed: restore hunger a bit
-    tamagochi.hunger = (tamagochi.hunger + 20).min(100);
+    user.last_seen = sys_time()?.as_seconds_since_epoch();
     
-    // Check evolution
-    check_tamagochi_evolution(&mut tamagochi);
+    // Update user entry
+    let user_hash = hash_entry(&EntryTypes::User(user.clone()))?;
+    // In production, need to update existing entry
+    let _ = create_entry(EntryTypes::User(user))?;
     
-    let _hash = create_entry(EntryTypes::Tamagochi(tamagochi.clone()))?;
-    Ok(tamagochi)
+    Ok(())
 }
 
-// ========== Friends System ==========
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShareLocationInput {
+    pub lat: f64,
+    pub lon: f64,
+    pub share_with: AgentPubKey,
+}
 
 #[hdk_extern]
-pub fn add_friend(friend_id: AgentPubKey) -> ExternResult<String> {
+pub fn share_location(input: ShareLocationInput) -> ExternResult<String> {
     let agent = agent_info()?.agent_latest_pubkey();
     
-    // Check if already friends
-    let existing_links = get_links(agent, LinkTypes::UserToFriends, None)?;
-    for link in existing_links {
-        if link.target == friend_id {
-            return Err(wasm_error!(WasmErrorInner::Guest("Already friends".into())));
-        }
-    }
+    // Update user location
+    let mut user = get_user_by_agent(&agent)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
     
-    // Create bidirectional friendship link
-    create_link(agent, friend_id, LinkTypes::UserToFriends, LinkTag::new(vec![]))?;
+    user.lat = Some(input.lat);
+    user.lon = Some(input.lon);
+    user.last_seen = sys_time()?.as_seconds_since_epoch();
     
-    Ok("Friend added".to_string())
+    // Update user entry
+    let _ = create_entry(EntryTypes::User(user))?;
+    
+    // Geo index for nearby discovery, only while sharing is enabled
+    if get_preferences_of(&agent)?.1.location_sharing_enabled {
+        index_location(&agent, input.lat, input.lon)?;
+    } else {
+        unindex_location(&agent)?;
+    }
+    
+    Ok("Location shared".to_string())
 }
 
 #[hdk_extern]
-pub fn remove_friend(friend_id: AgentPubKey) -> ExternResult<String> {
+pub fn get_shared_location(user_id: AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
+    // Hide location between users who blocked each other
     let agent = agent_info()?.agent_latest_pubkey();
+    if has_blocked(&user_id, &agent)? || has_blocked(&agent, &user_id)? {
+        return Ok(None);
+    }
+    
+    let user = get_user_by_agent(&user_id)?;
+    
+    if let Some(u) = user {
+        if let (Some(lat), Some(lon)) = (u.lat, u.lon) {
+            // Only if this user chose to share it
+            if !get_preferences_of(&user_id)?.1.location_sharing_enabled {
+                return Ok(None);
+            }
+            Ok(Some((lat, lon)))
+        } else {
+            Ok(None)
+        }
+    } else {
+        Ok(None)
+    }
+}
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    // Get user entry
+    let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
+    let mut user_hash_opt = None;
+    for link in links {
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(Entry::App(entry_bytes)) = element.entry() {
+                match EntryTypes::try_from(entry_bytes.clone()) {
+                    Ok(EntryTypes::User(_)) => {
+                        user_hash_opt = Some(link.target);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    
+    let user_hash = user_hash_opt.ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    let element = get(user_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    
+    let mut user: User = element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User entry not found".into())))?;
+    
+    // Solo actualizar last_seen si no está en modo ghost
+    if !user.ghost_mode {
+        user.last_seen = now;
+        let updated_hash = create_entry(EntryTypes::User(user))?;
+        
+        // Update link
+        let links = get_links(agent, LinkTypes::UserToProfileImage, None)?;
+        for link in links {
+            if link.target == user_hash {
+                delete_link(link.create_link_hash)?;
+            }
+        }
+        create_link(agent, updated_hash, LinkTypes::UserToProfileImage, ())?;
+    }
+    
+    Ok(())
+}
+
+// ========== User Blocking ==========
+
+#[hdk_extern]
+pub fn block_user(blocked_id: AgentPubKey) -> ExternResult<EntryHash> {
+    let blocker_id = agent_info()?.agent_latest_pubkey();
+    
+    if blocker_id == blocked_id {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot block yourself".into())));
+    }
+    
+    let block = UserBlock {
+        blocker_id,
+        blocked_id,
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let block_hash = create_entry(EntryTypes::UserBlock(block))?;
+    
+    // Link user to block
+    create_link(
+        blocker_id,
+        block_hash,
+        LinkTypes::UserToBlocks,
+        (),
+    )?;
+    
+    Ok(block_hash)
+}
+
+#[hdk_extern]
+pub fn unblock_user(blocked_id: AgentPubKey) -> ExternResult<()> {
+    let blocker_id = agent_info()?.agent_latest_pubkey();
+    
+    let links = get_links(blocker_id, LinkTypes::UserToBlocks, None)?;
+    
+    for link in links {
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(Entry::App(entry_bytes)) = element.entry() {
+                match EntryTypes::try_from(entry_bytes.clone()) {
+                    Ok(EntryTypes::UserBlock(block)) => {
+                        if block.blocked_id == blocked_id {
+                            delete_entry(link.target)?;
+                            delete_link(link.create_link_hash)?;
+                            return Ok(());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    
+    Err(wasm_error!(WasmErrorInner::Guest("Block not found".into())))
+}
+
+#[hdk_extern]
+pub fn is_user_blocked(user_id: AgentPubKey) -> ExternResult<bool> {
+    let current_user = agent_info()?.agent_latest_pubkey();
+    has_blocked(&current_user, &user_id)
+}
+
+#[hdk_extern]
+pub fn is_blocked_by(user_id: AgentPubKey) -> ExternResult<bool> {
+    let current_user = agent_info()?.agent_latest_pubkey();
+    has_blocked(&user_id, &current_user)
+}
+
+#[hdk_extern]
+pub fn get_blocked_users(_: ()) -> ExternResult<Vec<AgentPubKey>> {
+    let blocker_id = agent_info()?.agent_latest_pubkey();
+    let mut blocked = Vec::new();
+    
+    let links = get_links(blocker_id, LinkTypes::UserToBlocks, None)?;
+    
+    for link in links {
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(Entry::App(entry_bytes)) = element.entry() {
+                match EntryTypes::try_from(entry_bytes.clone()) {
+                    Ok(EntryTypes::UserBlock(block)) => {
+                        blocked.push(block.blocked_id);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    
+    Ok(blocked)
+}
+
+// ========== Tamagochi ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTamagochiInput {
+    pub name: String,
+}
+
+#[hdk_extern]
+pub fn create_tamagochi(input: CreateTamagochiInput) -> ExternResult<EntryHash> {
+    let genome = tamagochi::Genome::founder(&random_bytes(32)?.to_vec());
+    new_tamagochi(input.name, Genome::from_engine(&genome), None)
+}
+
+// New egg-stage pet for the caller, from scratch or hatched from a BreedingRecord
+fn new_tamagochi(name: String, genome: Genome, bred_from: Option<ActionHash>) -> ExternResult<EntryHash> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    if get_current_tamagochi(&owner_id)?.is_some() {
+        return Err(wasm_error!(WasmErrorInner::Guest("You already have a living tamagochi".into())));
+    }
+    
+    let mut tamagochi = Tamagochi {
+        owner_id: owner_id.clone(),
+        name,
+        stage: String::new(),
+        energy: 0,
+        hunger: 0,
+        hygiene: 0,
+        happiness: 0,
+        experience: 0,
+        level: 0,
+        born_at: now,
+        last_fed_at: now,
+        last_cleaned_at: now,
+        last_played_at: now,
+        last_updated_at: now,
+        neglected_ticks: 0,
+        care_streak: 0,
+        last_care_day: 0,
+        genome: Some(genome),
+        bred_from,
+        previous: None,
+        is_alive: true,
+    };
+    apply_pet_state(&mut tamagochi, tamagochi::PetState::new(now));
+    
+    let tamagochi_hash = create_entry(EntryTypes::Tamagochi(tamagochi))?;
+    
+    // Link user to tamagochi
+    create_link(
+        owner_id,
+        tamagochi_hash.clone(),
+        LinkTypes::UserToTamagochi,
+        (),
+    )?;
+    
+    Ok(tamagochi_hash)
+}
+
+/// Current state simulated up to now; nothing is written until the owner acts or calls update_tamagochi_state
+#[hdk_extern]
+pub fn get_tamagochi(_: ()) -> ExternResult<Option<Tamagochi>> {
+    get_tamagochi_for_user(agent_info()?.agent_latest_pubkey())
+}
+
+#[hdk_extern]
+pub fn feed_tamagochi(_: ()) -> ExternResult<Tamagochi> {
+    run_tamagochi(&[tamagochi::Event::Feed])
+}
+
+#[hdk_extern]
+pub fn clean_tamagochi(_: ()) -> ExternResult<Tamagochi> {
+    run_tamagochi(&[tamagochi::Event::Clean])
+}
+
+#[hdk_extern]
+pub fn play_with_tamagochi(_: ()) -> ExternResult<Tamagochi> {
+    run_tamagochi(&[tamagochi::Event::Play])
+}
+
+#[hdk_extern]
+pub fn kill_tamagochi(_: ()) -> ExternResult<EntryHash> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    let (tamagochi_hash, mut tamagochi) = get_current_tamagochi(&owner_id)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
+    
+    // It may already have starved since the last update
+    let outcome = tamagochi::simulate(&pet_state(&tamagochi), &[], now);
+    apply_pet_state(&mut tamagochi, outcome.state);
+    let (reason, died_at) = match outcome.death {
+        Some(death) => (death.cause.as_str(), death.at),
+        None => ("killed", now),
+    };
+    tamagochi.is_alive = false;
+    
+    save_tamagochi(&tamagochi_hash, &mut tamagochi)?;
+    withdraw_leaderboard_snapshots(&owner_id)?;
+    record_tamagochi_death(&tamagochi, reason, died_at)
+}
+
+#[hdk_extern]
+pub fn update_tamagochi_state(_: ()) -> ExternResult<Tamagochi> {
+    run_tamagochi(&[])
+}
+
+#[hdk_extern]
+pub fn get_tamagochi_deaths(_: ()) -> ExternResult<Vec<TamagochiDeath>> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let graveyard = get_graveyard(owner_id)?;
+    Ok(graveyard.into_iter().map(|grave| grave.death).collect())
+}
+
+// ========== Tamagochi Items ==========
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShopItem {
+    pub id: String,
+    pub name: String,
+    pub kind: String, // "food", "toy", "soap", "medicine"
+    pub price: u32,
+    pub hunger: i32,
+    pub hygiene: i32,
+    pub happiness: i32,
+    pub energy: i32,
+    pub experience: u32,
+}
+
+#[hdk_extern]
+pub fn get_item_catalogue(_: ()) -> ExternResult<Vec<ShopItem>> {
+    Ok(tamagochi::CATALOGUE
+        .iter()
+        .map(|item| ShopItem {
+            id: item.id.to_string(),
+            name: item.name.to_string(),
+            kind: item.kind.as_str().to_string(),
+            price: item.price,
+            hunger: item.hunger,
+            hygiene: item.hygiene,
+            happiness: item.happiness,
+            energy: item.energy,
+            experience: item.experience,
+        })
+        .collect())
+}
+
+#[hdk_extern]
+pub fn get_inventory(_: ()) -> ExternResult<TamagochiInventory> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    Ok(get_my_inventory(&owner_id)?.2)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BuyItemInput {
+    pub item_id: String,
+    pub quantity: u32,
+}
+
+#[hdk_extern]
+pub fn buy_item(input: BuyItemInput) -> ExternResult<TamagochiInventory> {
+    change_inventory(InventoryChange::Buy { item_id: input.item_id, quantity: input.quantity })
+}
+
+#[hdk_extern]
+pub fn use_item(item_id: String) -> ExternResult<Tamagochi> {
+    let item = tamagochi::find_item(&item_id)
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Unknown item".into())))?;
+    
+    change_inventory(InventoryChange::Use { item_id: item.id.to_string() })?;
+    run_tamagochi(&[tamagochi::Event::UseItem(item)])
+}
+
+/// Requester and driver each collect once per completed ride
+#[hdk_extern]
+pub fn claim_ride_reward(ride_hash: EntryHash) -> ExternResult<TamagochiInventory> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    
+    let element = get(ride_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Ride not found".into())))?;
+    let ride: RideRequest = element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Ride not found".into())))?;
+    
+    if ride.requester_id != agent && ride.driver_id.as_ref() != Some(&agent) {
+        return Err(wasm_error!(WasmErrorInner::Guest("You were not part of this ride".into())));
+    }
+    if ride.status != "completed" {
+        return Err(wasm_error!(WasmErrorInner::Guest("Ride is not completed".into())));
+    }
+    
+    let tag = LinkTag::new(agent.get_raw_39().to_vec());
+    if !get_links(ride_hash.clone(), LinkTypes::RideToRewards, Some(tag.clone()))?.is_empty() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Reward already claimed".into())));
+    }
+    
+    let reward_link = create_link(
+        ride_hash,
+        agent.clone(),
+        LinkTypes::RideToRewards,
+        tag,
+    )?;
+    reward_activity(tamagochi::Activity::Ride, reward_link)?;
+    award_achievements(AchievementEvidence::RideReward)?;
+    
+    Ok(get_my_inventory(&agent)?.2)
+}
+
+// ========== Tamagochi Graveyard ==========
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Grave {
+    pub death_hash: EntryHash,
+    pub death: TamagochiDeath,
+    pub memorial_count: u32,
+}
+
+/// A user's past pets, newest death first; public so friends can visit
+#[hdk_extern]
+pub fn get_graveyard(owner_id: AgentPubKey) -> ExternResult<Vec<Grave>> {
+    let links = get_links(owner_id, LinkTypes::UserToDeaths, None)?;
+    let mut graves = Vec::new();
+    
+    for link in links {
+        let death_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let death = match get_latest_tamagochi_death(&death_hash)? {
+            Some(death) => death,
+            None => continue,
+        };
+        let memorial_count = get_links(death_hash.clone(), LinkTypes::DeathToMemorials, None)?.len() as u32;
+        
+        graves.push(Grave { death_hash, death, memorial_count });
+    }
+    
+    // Sort by death time (newest first)
+    graves.sort_by_key(|g| -g.death.died_at);
+    Ok(graves)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetEpitaphInput {
+    pub death_hash: EntryHash,
+    pub epitaph: String,
+}
+
+#[hdk_extern]
+pub fn set_epitaph(input: SetEpitaphInput) -> ExternResult<TamagochiDeath> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut death = get_latest_tamagochi_death(&input.death_hash)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Death record not found".into())))?;
+    
+    if death.owner_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the owner can write the epitaph".into())));
+    }
+    let epitaph = input.epitaph.trim().to_string();
+    if epitaph.chars().count() > MAX_EPITAPH_CHARS {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!("Epitaph is too long (max {} characters)", MAX_EPITAPH_CHARS))));
+    }
+    death.epitaph = if epitaph.is_empty() { None } else { Some(epitaph) };
+    
+    let record = get(input.death_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Death record not found".into())))?;
+    update_entry(record.action_address().clone(), EntryTypes::TamagochiDeath(death.clone()))?;
+    
+    // Memorials stay on the original hash; readers follow this link
+    create_link(
+        input.death_hash,
+        hash_entry(&EntryTypes::TamagochiDeath(death.clone()))?,
+        LinkTypes::TamagochiDeathUpdates,
+        (),
+    )?;
+    
+    Ok(death)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LeaveMemorialInput {
+    pub death_hash: EntryHash,
+    pub message: String,
+}
+
+#[hdk_extern]
+pub fn leave_memorial(input: LeaveMemorialInput) -> ExternResult<EntryHash> {
+    let author_id = agent_info()?.agent_latest_pubkey();
+    let death = get_latest_tamagochi_death(&input.death_hash)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Death record not found".into())))?;
+    
+    // El dueño y sus amigos
+    if death.owner_id != author_id && !has_friend(&death.owner_id, &author_id)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only friends can leave memorials".into())));
+    }
+    ensure_not_blocked_by(&death.owner_id, &author_id)?;
+    
+    let message = input.message.trim().to_string();
+    let chars = message.chars().count();
+    if chars == 0 || chars > MAX_MEMORIAL_CHARS {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!("Memorial messages must have 1-{} characters", MAX_MEMORIAL_CHARS))));
+    }
+    
+    // Un mensaje por persona, encontrado por el tag del link
+    let tag = LinkTag::new(author_id.get_raw_39().to_vec());
+    if !get_links(input.death_hash.clone(), LinkTypes::DeathToMemorials, Some(tag.clone()))?.is_empty() {
+        return Err(wasm_error!(WasmErrorInner::Guest("You already left a memorial".into())));
+    }
+    
+    let memorial = TamagochiMemorial {
+        death_hash: input.death_hash.clone(),
+        author_id,
+        message,
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let memorial_hash = create_entry(EntryTypes::TamagochiMemorial(memorial))?;
+    
+    // Link death to memorial
+    create_link(
+        input.death_hash,
+        memorial_hash.clone(),
+        LinkTypes::DeathToMemorials,
+        tag,
+    )?;
+    
+    Ok(memorial_hash)
+}
+
+#[hdk_extern]
+pub fn get_memorials(death_hash: EntryHash) -> ExternResult<Vec<TamagochiMemorial>> {
+    let links = get_links(death_hash, LinkTypes::DeathToMemorials, None)?;
+    let mut memorials = Vec::new();
+    
+    for link in links {
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(memorial) = element.entry().to_app_option::<TamagochiMemorial>()? {
+                memorials.push(memorial);
+            }
+        }
+    }
+    
+    // Oldest first, like a guestbook
+    memorials.sort_by_key(|m| m.created_at);
+    Ok(memorials)
+}
+
+#[hdk_extern]
+pub fn auto_grow_tamagochi(_: ()) -> ExternResult<Tamagochi> {
+    run_tamagochi(&[tamagochi::Event::AppOpen])
+}
+
+// ========== Tamagochi Leaderboards ==========
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    Level,
+    Age, // Edad actual del tamagochi vivo
+    CareStreak, // Días seguidos cuidado por su dueño
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LeaderboardScope {
+    Global,
+    Friends, // Incluye al usuario
+    Nearby { lat: f64, lon: f64, radius_km: f64 },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetLeaderboardInput {
+    pub metric: LeaderboardMetric,
+    pub scope: LeaderboardScope,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub owner_id: AgentPubKey,
+    pub tamagochi_hash: EntryHash,
+    pub tamagochi_name: String,
+    pub stage: String,
+    pub level: u32,
+    pub age_seconds: i64,
+    pub care_streak: u32,
+    pub score: i64, // Valor de la métrica pedida
+    pub snapshot_taken_at: i64,
+}
+
+/// Reads the snapshots published in the last two days; pets whose owner disabled
+/// tamagochi or hasn't opened the app since then are not listed
+#[hdk_extern]
+pub fn get_leaderboard(input: GetLeaderboardInput) -> ExternResult<Vec<LeaderboardEntry>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    let limit = input.limit.unwrap_or(MAX_LEADERBOARD_SIZE).min(MAX_LEADERBOARD_SIZE);
+    
+    let snapshots = match &input.scope {
+        LeaderboardScope::Friends => {
+            let mut owners = vec![agent.clone()];
+            owners.extend(get_friends(())?);
+            let mut snapshots = Vec::new();
+            for owner in owners {
+                if let Some(snapshot) = get_latest_leaderboard_snapshot(&owner)? {
+                    snapshots.push(snapshot);
+                }
+            }
+            snapshots
+        }
+        LeaderboardScope::Global => get_bucket_leaderboard_snapshots(now)?,
+        LeaderboardScope::Nearby { lat, lon, radius_km } => get_bucket_leaderboard_snapshots(now)?
+            .into_iter()
+            .filter(|s| match (s.lat, s.lon) {
+                (Some(s_lat), Some(s_lon)) => calculate_distance(*lat, *lon, s_lat, s_lon) <= *radius_km,
+                _ => false,
+            })
+            .collect(),
+    };
+    
+    let mut entries = Vec::new();
+    for snapshot in snapshots {
+        if now - snapshot.taken_at > LEADERBOARD_WINDOW_SECONDS {
+            continue;
+        }
+        if snapshot.owner_id != agent
+            && (has_blocked(&snapshot.owner_id, &agent)? || has_blocked(&agent, &snapshot.owner_id)?)
+        {
+            continue;
+        }
+        
+        let age_seconds = now - snapshot.born_at;
+        let care_streak = tamagochi::effective_care_streak(snapshot.care_streak, snapshot.last_care_day, now);
+        let score = match input.metric {
+            LeaderboardMetric::Level => snapshot.level as i64,
+            LeaderboardMetric::Age => age_seconds,
+            LeaderboardMetric::CareStreak => care_streak as i64,
+        };
+        entries.push((snapshot.experience, LeaderboardEntry {
+            rank: 0,
+            owner_id: snapshot.owner_id,
+            tamagochi_hash: snapshot.tamagochi_hash,
+            tamagochi_name: snapshot.tamagochi_name,
+            stage: snapshot.stage,
+            level: snapshot.level,
+            age_seconds,
+            care_streak,
+            score,
+            snapshot_taken_at: snapshot.taken_at,
+        }));
+    }
+    
+    // Empates: más experiencia, luego el más viejo
+    entries.sort_by(|(a_xp, a), (b_xp, b)| {
+        b.score.cmp(&a.score)
+            .then(b_xp.cmp(a_xp))
+            .then(b.age_seconds.cmp(&a.age_seconds))
+    });
+    
+    Ok(entries
+        .into_iter()
+        .take(limit)
+        .enumerate()
+        .map(|(i, (_, mut entry))| {
+            entry.rank = i as u32 + 1;
+            entry
+        })
+        .collect())
+}
+
+/// Publishes a fresh snapshot of the caller's pet right away (normally done every few hours
+/// while the pet is updated); None if there is no living pet or tamagochi is disabled
+#[hdk_extern]
+pub fn publish_leaderboard_snapshot(_: ()) -> ExternResult<Option<EntryHash>> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    match get_current_tamagochi(&owner_id)? {
+        Some((tamagochi_hash, mut tamagochi)) => {
+            // The snapshot has to cite the newest version, so write one now
+            let version = save_tamagochi(&tamagochi_hash, &mut tamagochi)?;
+            refresh_leaderboard_snapshot(&tamagochi_hash, &version, &tamagochi, true)
+        }
+        None => {
+            withdraw_leaderboard_snapshots(&owner_id)?;
+            Ok(None)
+        }
+    }
+}
+
+// ========== Tamagochi Breeding ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProposeBreedingInput {
+    pub partner_id: AgentPubKey,
+    pub egg_owner_id: Option<AgentPubKey>, // Por defecto quien propone
+}
+
+/// Both pets must be living adults, their owners friends with each other and
+/// neither pet bred within the cooldown; the partner co-signs with respond_to_breeding
+#[hdk_extern]
+pub fn propose_breeding(input: ProposeBreedingInput) -> ExternResult<ActionHash> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    if input.partner_id == agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Breeding needs a partner".into())));
+    }
+    if has_blocked(&input.partner_id, &agent)? || has_blocked(&agent, &input.partner_id)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot breed with this user".into())));
+    }
+    if !has_friend(&agent, &input.partner_id)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Breeding is only possible between friends".into())));
+    }
+    
+    let (proposer_tamagochi, proposer_version, _) = get_breedable_tamagochi(&agent, now, "Your")?;
+    let (partner_tamagochi, partner_version, _) = get_breedable_tamagochi(&input.partner_id, now, "Your friend's")?;
+    if is_resting(&proposer_tamagochi, now)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Your tamagochi needs to rest before breeding again".into())));
+    }
+    
+    let proposal = BreedingProposal {
+        proposer_id: agent.clone(),
+        partner_id: input.partner_id.clone(),
+        proposer_tamagochi,
+        partner_tamagochi,
+        proposer_version,
+        partner_version,
+        egg_owner_id: input.egg_owner_id.unwrap_or(agent),
+        proposed_at: now,
+    };
+    
+    let proposal_hash = create_entry(EntryTypes::BreedingProposal(proposal))?;
+    
+    create_link(
+        input.partner_id,
+        proposal_hash.clone(),
+        LinkTypes::UserToBreedingProposals,
+        (),
+    )?;
+    
+    Ok(proposal_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingBreeding {
+    pub proposal_hash: ActionHash,
+    pub proposal: BreedingProposal,
+}
+
+/// Proposals waiting for the caller's answer, newest first
+#[hdk_extern]
+pub fn get_breeding_proposals(_: ()) -> ExternResult<Vec<PendingBreeding>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    let links = get_links(agent.clone(), LinkTypes::UserToBreedingProposals, None)?;
+    let mut pending = Vec::new();
+    
+    for link in links {
+        let proposal_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if get_links(proposal_hash.clone(), LinkTypes::BreedingProposalToResponse, None)?.iter().any(|l| l.author == agent) {
+            continue;
+        }
+        if let Some(element) = get(proposal_hash.clone(), GetOptions::default())? {
+            if let Some(proposal) = element.entry().to_app_option::<BreedingProposal>()? {
+                if now - proposal.proposed_at <= BREEDING_PROPOSAL_TTL_SECONDS {
+                    pending.push(PendingBreeding { proposal_hash, proposal });
+                }
+            }
+        }
+    }
+    
+    pending.sort_by_key(|p| std::cmp::Reverse(p.proposal.proposed_at));
+    Ok(pending)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RespondToBreedingInput {
+    pub proposal_hash: ActionHash,
+    pub accept: bool,
+}
+
+/// Accepting lays the egg (returns the BreedingRecord hash) for whoever the proposal named
+#[hdk_extern]
+pub fn respond_to_breeding(input: RespondToBreedingInput) -> ExternResult<Option<ActionHash>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    let proposal = get(input.proposal_hash.clone(), GetOptions::default())?
+        .and_then(|element| element.entry().to_app_option::<BreedingProposal>().ok().flatten())
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Breeding proposal not found".into())))?;
+    
+    if proposal.partner_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the invited partner can respond".into())));
+    }
+    if get_links(input.proposal_hash.clone(), LinkTypes::BreedingProposalToResponse, None)?.iter().any(|l| l.author == agent) {
+        return Err(wasm_error!(WasmErrorInner::Guest("Already responded".into())));
+    }
+    if now - proposal.proposed_at > BREEDING_PROPOSAL_TTL_SECONDS {
+        return Err(wasm_error!(WasmErrorInner::Guest("This proposal has expired".into())));
+    }
+    
+    let response = if input.accept { "accepted" } else { "declined" };
+    create_link(
+        input.proposal_hash.clone(),
+        agent.clone(),
+        LinkTypes::BreedingProposalToResponse,
+        LinkTag::new(response.as_bytes().to_vec()),
+    )?;
+    
+    if !input.accept {
+        return Ok(None);
+    }
+    
+    // Las mascotas pudieron morir o criar con otro desde la propuesta
+    let (partner_hash, _, partner_pet) = get_breedable_tamagochi(&agent, now, "Your")?;
+    let (proposer_hash, _, proposer_pet) = get_breedable_tamagochi(&proposal.proposer_id, now, "Your friend's")?;
+    if partner_hash != proposal.partner_tamagochi || proposer_hash != proposal.proposer_tamagochi {
+        return Err(wasm_error!(WasmErrorInner::Guest("The pets in this proposal are gone".into())));
+    }
+    if is_resting(&partner_hash, now)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Your tamagochi needs to rest before breeding again".into())));
+    }
+    
+    let parent_genome = |pet: &Tamagochi| pet.genome.as_ref().map(|g| g.to_engine()).unwrap_or_default();
+    let child = tamagochi::breed(
+        &parent_genome(&proposer_pet),
+        &parent_genome(&partner_pet),
+        input.proposal_hash.get_raw_39(),
+    );
+    
+    let record = BreedingRecord {
+        proposal_hash: input.proposal_hash,
+        proposer_id: proposal.proposer_id,
+        partner_id: proposal.partner_id,
+        egg_owner_id: proposal.egg_owner_id.clone(),
+        genome: Genome::from_engine(&child),
+        bred_at: now,
+    };
+    let breeding_hash = create_entry(EntryTypes::BreedingRecord(record))?;
+    
+    for parent_hash in [proposer_hash, partner_hash] {
+        create_link(parent_hash, breeding_hash.clone(), LinkTypes::TamagochiToBreedings, ())?;
+    }
+    create_link(
+        proposal.egg_owner_id,
+        breeding_hash.clone(),
+        LinkTypes::UserToEggs,
+        (),
+    )?;
+    
+    Ok(Some(breeding_hash))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Egg {
+    pub breeding_hash: ActionHash,
+    pub breeding: BreedingRecord,
+}
+
+/// Eggs waiting to hatch; one can only hatch while its owner has no living pet
+#[hdk_extern]
+pub fn get_my_eggs(_: ()) -> ExternResult<Vec<Egg>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let links = get_links(agent, LinkTypes::UserToEggs, None)?;
+    let mut eggs = Vec::new();
+    
+    for link in links {
+        let breeding_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if !get_links(breeding_hash.clone(), LinkTypes::EggToHatchling, None)?.is_empty() {
+            continue;
+        }
+        if let Some(element) = get(breeding_hash.clone(), GetOptions::default())? {
+            if let Some(breeding) = element.entry().to_app_option::<BreedingRecord>()? {
+                eggs.push(Egg { breeding_hash, breeding });
+            }
+        }
+    }
+    
+    eggs.sort_by_key(|e| e.breeding.bred_at);
+    Ok(eggs)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HatchEggInput {
+    pub breeding_hash: ActionHash,
+    pub name: String,
+}
+
+#[hdk_extern]
+pub fn hatch_egg(input: HatchEggInput) -> ExternResult<EntryHash> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    
+    let breeding = get(input.breeding_hash.clone(), GetOptions::default())?
+        .and_then(|element| element.entry().to_app_option::<BreedingRecord>().ok().flatten())
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Egg not found".into())))?;
+    
+    if breeding.egg_owner_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("This egg is not yours".into())));
+    }
+    if !get_links(input.breeding_hash.clone(), LinkTypes::EggToHatchling, None)?.is_empty() {
+        return Err(wasm_error!(WasmErrorInner::Guest("This egg already hatched".into())));
+    }
+    
+    let tamagochi_hash = new_tamagochi(input.name, breeding.genome, Some(input.breeding_hash.clone()))?;
+    
+    create_link(
+        input.breeding_hash,
+        tamagochi_hash.clone(),
+        LinkTypes::EggToHatchling,
+        (),
+    )?;
+    
+    Ok(tamagochi_hash)
+}
+
+// ========== Achievements ==========
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AchievementInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub required: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnlockedAchievement {
+    pub achievement: AchievementInfo,
+    pub unlock_hash: ActionHash,
+    pub unlocked_at: i64,
+}
+
+#[hdk_extern]
+pub fn get_achievement_catalogue(_: ()) -> ExternResult<Vec<AchievementInfo>> {
+    Ok(integrity::ACHIEVEMENTS.iter().map(achievement_info).collect())
+}
+
+/// Unlocked achievements for a profile, newest first
+#[hdk_extern]
+pub fn get_achievements(agent: AgentPubKey) -> ExternResult<Vec<UnlockedAchievement>> {
+    let links = get_links(agent.clone(), LinkTypes::AgentToAchievements, None)?;
+    let mut unlocked = Vec::new();
+    
+    for link in links {
+        let unlock_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let unlock = match get(unlock_hash.clone(), GetOptions::default())? {
+            Some(element) => match element.entry().to_app_option::<AchievementUnlock>()? {
+                Some(unlock) => unlock,
+                None => continue,
+            },
+            None => continue,
+        };
+        // Solo cuenta si lo firmó el propio agente (la validación revisa la evidencia)
+        if unlock.agent_id != agent {
+            continue;
+        }
+        if let Some(achievement) = integrity::find_achievement(&unlock.achievement_id) {
+            unlocked.push(UnlockedAchievement {
+                achievement: achievement_info(achievement),
+                unlock_hash,
+                unlocked_at: unlock.unlocked_at,
+            });
+        }
+    }
+    
+    unlocked.sort_by_key(|u| std::cmp::Reverse(u.unlocked_at));
+    Ok(unlocked)
+}
+
+/// Re-checks every achievement against the caller's chain, e.g. for activity from
+/// before achievements existed; returns the ids unlocked now
+#[hdk_extern]
+pub fn check_achievements(_: ()) -> ExternResult<Vec<String>> {
+    let mut unlocked = Vec::new();
+    for evidence in [
+        AchievementEvidence::Post,
+        AchievementEvidence::Clap,
+        AchievementEvidence::WishHelp,
+        AchievementEvidence::Review,
+        AchievementEvidence::RideReward,
+        AchievementEvidence::AdultTamagochi,
+    ] {
+        unlocked.extend(award_achievements(evidence)?);
+    }
+    Ok(unlocked)
+}
+
+// ========== Friends System ==========
+
+/// Kept for older clients: now only sends a friend request
+#[hdk_extern]
+pub fn add_friend(friend_id: AgentPubKey) -> ExternResult<String> {
+    send_friend_request(SendFriendRequestInput { recipient_id: friend_id, message: None })?;
+    Ok("Friend request sent".to_string())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SendFriendRequestInput {
+    pub recipient_id: AgentPubKey,
+    pub message: Option<String>,
+}
+
+/// If the other user already asked us, this accepts their request instead
+#[hdk_extern]
+pub fn send_friend_request(input: SendFriendRequestInput) -> ExternResult<ActionHash> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let recipient = input.recipient_id;
+    
+    if recipient == agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot befriend yourself".into())));
+    }
+    if has_blocked(&recipient, &agent)? || has_blocked(&agent, &recipient)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot send a friend request to this user".into())));
+    }
+    if has_friend(&agent, &recipient)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Already friends".into())));
+    }
+    
+    if let Some(incoming) = get_pending_requests(())?.into_iter().find(|p| p.request.sender_id == recipient) {
+        accept_friend_request(incoming.request_hash.clone())?;
+        return Ok(incoming.request_hash);
+    }
+    for link in get_links(recipient.clone(), LinkTypes::UserToFriendRequests, None)? {
+        if let Some(request_hash) = link.target.into_action_hash() {
+            if link.author == agent && !has_response(&request_hash, &recipient)? {
+                return Err(wasm_error!(WasmErrorInner::Guest("Friend request already sent".into())));
+            }
+        }
+    }
+    
+    // Sin solicitud pendiente, su link hacia nosotros es del antiguo add_friend (o de una
+    // solicitud ya respondida): se borra para que el nuestro no complete la amistad solo
+    unlink_friend(&recipient, &agent)?;
+    
+    let request = FriendRequest {
+        sender_id: agent.clone(),
+        recipient_id: recipient.clone(),
+        message: input.message,
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    let request_hash = create_entry(EntryTypes::FriendRequest(request))?;
+    
+    create_link(
+        recipient.clone(),
+        request_hash.clone(),
+        LinkTypes::UserToFriendRequests,
+        (),
+    )?;
+    
+    // Nuestro lado de la amistad; cuenta cuando el otro acepte
+    link_friend(&agent, &recipient)?;
+    
+    Ok(request_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingFriendRequest {
+    pub request_hash: ActionHash,
+    pub request: FriendRequest,
+}
+
+/// Requests waiting for the caller's answer, newest first
+#[hdk_extern]
+pub fn get_pending_requests(_: ()) -> ExternResult<Vec<PendingFriendRequest>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let links = get_links(agent.clone(), LinkTypes::UserToFriendRequests, None)?;
+    let mut pending = Vec::new();
+    
+    for link in links {
+        let request_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if has_response(&request_hash, &agent)? {
+            continue;
+        }
+        if let Some(element) = get(request_hash.clone(), GetOptions::default())? {
+            if let Some(request) = element.entry().to_app_option::<FriendRequest>()? {
+                if request.recipient_id == agent && !has_blocked(&agent, &request.sender_id)? {
+                    pending.push(PendingFriendRequest { request_hash, request });
+                }
+            }
+        }
+    }
+    
+    pending.sort_by_key(|p| std::cmp::Reverse(p.request.created_at));
+    Ok(pending)
+}
+
+#[hdk_extern]
+pub fn accept_friend_request(request_hash: ActionHash) -> ExternResult<String> {
+    let (agent, request) = respond_to_friend_request(&request_hash, "accepted")?;
+    link_friend(&agent, &request.sender_id)?;
+    Ok("Friend added".to_string())
+}
+
+/// The sender is not notified; their side of the link is removed so it can't become a
+/// friendship later
+#[hdk_extern]
+pub fn decline_friend_request(request_hash: ActionHash) -> ExternResult<String> {
+    let (agent, request) = respond_to_friend_request(&request_hash, "declined")?;
+    unlink_friend(&request.sender_id, &agent)?;
+    Ok("Friend request declined".to_string())
+}
+
+/// Removes both directions, so a new request is needed to be friends again
+#[hdk_extern]
+pub fn remove_friend(friend_id: AgentPubKey) -> ExternResult<String> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    unlink_friend(&agent, &friend_id)?;
+    unlink_friend(&friend_id, &agent)?;
+    Ok("Friend removed".to_string())
+}
+
+/// Only mutual friendships; pending or declined requests don't count
+#[hdk_extern]
+pub fn get_friends(_: ()) -> ExternResult<Vec<AgentPubKey>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let links = get_links(agent.clone(), LinkTypes::UserToFriends, None)?;
+    let mut friends = Vec::new();
+    
+    for link in links {
+        if let Some(friend) = link.target.into_agent_pub_key() {
+            if !friends.contains(&friend) && has_linked_friend(&friend, &agent)? {
+                friends.push(friend);
+            }
+        }
+    }
+    Ok(friends)
+}
+
+#[hdk_extern]
+pub fn is_friend(user_id: AgentPubKey) -> ExternResult<bool> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    has_friend(&agent, &user_id)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FriendSuggestion {
+    pub agent_id: AgentPubKey,
+    pub score: f64,
+    pub mutual_friends: u32,
+    pub distance_km: Option<f64>,
+    pub interactions: u32, // Chats, aplausos y ayuda en deseos
+    pub reason: String, // Ej: "3 mutual friends · 2.1 km away"
+}
+
+/// People the caller may know, best first; friends, pending requests and blocked
+/// users in either direction are never suggested
+#[hdk_extern]
+pub fn get_friend_suggestions(limit: usize) -> ExternResult<Vec<FriendSuggestion>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let limit = limit.min(MAX_FRIEND_SUGGESTIONS);
+    let mut mutual: HashMap<AgentPubKey, u32> = HashMap::new();
+    let mut distance: HashMap<AgentPubKey, f64> = HashMap::new();
+    let mut interactions: HashMap<AgentPubKey, u32> = HashMap::new();
+    
+    // Amigos de amigos (solo amistades mutuas); la lista de cada candidato se lee una vez
+    let friends = get_friends(())?;
+    let mut candidate_friends: HashMap<AgentPubKey, Vec<Link>> = HashMap::new();
+    for friend in friends.iter().take(MAX_SUGGESTION_FRIENDS) {
+        let friend_hash: AnyLinkableHash = friend.clone().into();
+        for link in get_links(friend.clone(), LinkTypes::UserToFriends, None)? {
+            let candidate = match link.target.into_agent_pub_key() {
+                Some(candidate) => candidate,
+                None => continue,
+            };
+            if !candidate_friends.contains_key(&candidate) {
+                if candidate_friends.len() >= MAX_SUGGESTION_CANDIDATES {
+                    continue;
+                }
+                let links = get_links(candidate.clone(), LinkTypes::UserToFriends, None)?;
+                candidate_friends.insert(candidate.clone(), links);
+            }
+            if candidate_friends[&candidate].iter().any(|l| l.target == friend_hash) {
+                *mutual.entry(candidate).or_insert(0) += 1;
+            }
+        }
+    }
+    
+    // Cercanía, si el usuario comparte su ubicación
+    if let Some((lat, lon)) = get_indexed_location(&agent)? {
+        for (candidate, km) in nearby_agents(lat, lon, SUGGESTION_RADIUS_KM)? {
+            distance.insert(candidate, km);
+        }
+    }
+    
+    for candidate in get_interaction_partners(&agent)? {
+        *interactions.entry(candidate).or_insert(0) += 1;
+    }
+    
+    // Solicitudes pendientes en ambos sentidos: las nuestras ya están en nuestra lista,
+    // las suyas esperan nuestra respuesta
+    let linked: Vec<AnyLinkableHash> = get_links(agent.clone(), LinkTypes::UserToFriends, None)?
+        .into_iter()
+        .map(|l| l.target)
+        .collect();
+    let requested_us: Vec<AgentPubKey> = get_pending_requests(())?
+        .into_iter()
+        .map(|p| p.request.sender_id)
+        .collect();
+    
+    let mut candidates: Vec<AgentPubKey> = mutual.keys().cloned().collect();
+    candidates.extend(distance.keys().cloned());
+    candidates.extend(interactions.keys().cloned());
+    candidates.sort_by(|a, b| a.get_raw_39().cmp(b.get_raw_39()));
+    candidates.dedup();
+    
+    let mut suggestions = Vec::new();
+    for candidate in candidates {
+        if candidate == agent || linked.contains(&candidate.clone().into()) || requested_us.contains(&candidate) {
+            continue;
+        }
+        
+        let mutual_friends = mutual.get(&candidate).copied().unwrap_or(0);
+        let distance_km = distance.get(&candidate).copied();
+        let interaction_count = interactions.get(&candidate).copied().unwrap_or(0).min(MAX_SCORED_INTERACTIONS);
+        
+        let mut score = mutual_friends as f64 * 3.0 + interaction_count as f64 * 2.0;
+        let mut reasons = Vec::new();
+        if mutual_friends > 0 {
+            reasons.push(match mutual_friends {
+                1 => "1 mutual friend".to_string(),
+                n => format!("{} mutual friends", n),
+            });
+        }
+        if let Some(km) = distance_km {
+            // Más cerca, más puntos; 0 en el borde del radio
+            score += 5.0 * (1.0 - km / SUGGESTION_RADIUS_KM).max(0.0);
+            reasons.push(format!("{:.1} km away", km));
+        }
+        if interaction_count > 0 {
+            reasons.push("You've interacted before".to_string());
+        }
+        
+        suggestions.push(FriendSuggestion {
+            agent_id: candidate,
+            score,
+            mutual_friends,
+            distance_km,
+            interactions: interaction_count,
+            reason: reasons.join(" · "),
+        });
+    }
+    
+    suggestions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    
+    // Bloqueos al final: solo se consultan para los que se van a devolver
+    let mut allowed = Vec::new();
+    for suggestion in suggestions {
+        if allowed.len() >= limit {
+            break;
+        }
+        if has_blocked(&agent, &suggestion.agent_id)? || has_blocked(&suggestion.agent_id, &agent)? {
+            continue;
+        }
+        allowed.push(suggestion);
+    }
+    Ok(allowed)
+}
+
+// ========== User Preferences ==========
+
+/// Stored preferences, or the defaults until the user saves any
+#[hdk_extern]
+pub fn get_user_preferences(_: ()) -> ExternResult<Option<UserPreferences>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    Ok(Some(get_preferences_of(&agent)?.1))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdatePreferencesInput {
+    pub app_color: Option<String>,
+    pub tamagochi_enabled: Option<bool>,
+    pub location_sharing_enabled: Option<bool>,
+    pub language: Option<String>,
+    pub distance_unit: Option<String>,
+    pub notifications: Option<integrity::NotificationSettings>,
+    pub default_currency: Option<String>,
+    pub profile_visibility: Option<String>,
+}
+
+/// Only the fields present in the input change
+#[hdk_extern]
+pub fn update_user_preferences(input: UpdatePreferencesInput) -> ExternResult<UserPreferences> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let (prefs_hash, mut prefs) = get_preferences_of(&agent)?;
+    
+    // Update fields
+    if let Some(color) = input.app_color {
+        prefs.app_color = color;
+    }
+    if let Some(enabled) = input.tamagochi_enabled {
+        prefs.tamagochi_enabled = enabled;
+    }
+    if let Some(sharing) = input.location_sharing_enabled {
+        prefs.location_sharing_enabled = sharing;
+    }
+    if let Some(language) = input.language {
+        prefs.language = language;
+    }
+    if let Some(unit) = input.distance_unit {
+        prefs.distance_unit = unit;
+    }
+    if let Some(notifications) = input.notifications {
+        prefs.notifications = notifications;
+    }
+    if let Some(currency) = input.default_currency {
+        prefs.default_currency = currency.to_uppercase();
+    }
+    if let Some(visibility) = input.profile_visibility {
+        prefs.profile_visibility = visibility;
+    }
+    integrity::check_preferences(&prefs)
+        .map_err(|reason| wasm_error!(WasmErrorInner::Guest(reason)))?;
+    
+    save_preferences(prefs_hash, &mut prefs)?;
+    
+    // Opt-out: the pet leaves every leaderboard right away
+    if !prefs.tamagochi_enabled {
+        withdraw_leaderboard_snapshots(&prefs.owner_id)?;
+    }
+    if !prefs.location_sharing_enabled {
+        unindex_location(&prefs.owner_id)?;
+    }
+    
+    Ok(prefs)
+}
+
+// ========== Location Sharing ==========
+
+#[hdk_extern]
+pub fn get_user_location(user_id: AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
+    get_shared_location(user_id)
+}
+
+// ========== Driver System ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RegisterDriverInput {
+    pub vehicle_info: integrity::VehicleInfo,
+    pub price_per_km: Option<f64>,
+    pub base_price: Option<f64>,
+    pub currency: String,
+}
+
+#[hdk_extern]
+pub fn register_as_driver(input: RegisterDriverInput) -> ExternResult<User> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut user = get_user_by_agent(&agent)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    
+    // Update vehicle info with pricing
+    let mut vehicle_info = input.vehicle_info;
+    vehicle_info.price_per_km = input.price_per_km;
+    vehicle_info.base_price = input.base_price;
+    vehicle_info.currency = input.currency.clone();
+    
+    user.is_driver = true;
+    user.driver_status = Some("offline".to_string());
+    user.vehicle_info = Some(vehicle_info);
+    
+    let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
+    Ok(user)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateDriverPricingInput {
+    pub price_per_km: Option<f64>,
+    pub base_price: Option<f64>,
+    pub currency: Option<String>,
+}
+
+#[hdk_extern]
+pub fn update_driver_pricing(input: UpdateDriverPricingInput) -> ExternResult<User> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut user = get_user_by_agent(&agent)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    
+    if !user.is_driver {
+        return Err(wasm_error!(WasmErrorInner::Guest("User is not a driver".into())));
+    }
+    
+    if let Some(ref mut vehicle_info) = user.vehicle_info {
+        if let Some(price_per_km) = input.price_per_km {
+            vehicle_info.price_per_km = Some(price_per_km);
+        }
+        if let Some(base_price) = input.base_price {
+            vehicle_info.base_price = Some(base_price);
+        }
+        if let Some(currency) = input.currency {
+            vehicle_info.currency = currency;
+        }
+    } else {
+        // Create vehicle info if it doesn't exist
+        user.vehicle_info = Some(integrity::VehicleInfo {
+            make: "".to_string(),
+            model: "".to_string(),
+            year: None,
+            color: None,
+            license_plate: None,
+            capacity: 4,
+            price_per_km: input.price_per_km,
+            base_price: input.base_price,
+            currency: input.currency.unwrap_or_else(|| "CLP".to_string()),
+        });
+    }
+    
+    let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
+    Ok(user)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateDriverStatusInput {
+    pub status: String, // "available", "busy", "offline"
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+}
+
+#[hdk_extern]
+pub fn update_driver_status(input: UpdateDriverStatusInput) -> ExternResult<User> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let mut user = get_user_by_agent(&agent)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+    
+    if !user.is_driver {
+        return Err(wasm_error!(WasmErrorInner::Guest("User is not a driver".into())));
+    }
+    
+    if input.status != "available" && input.status != "busy" && input.status != "offline" {
+        return Err(wasm_error!(WasmErrorInner::Guest("Invalid driver status".into())));
+    }
+    
+    user.driver_status = Some(input.status.clone());
+    
+    // Update location if provided
+    if let Some(lat) = input.lat {
+        user.lat = Some(lat);
+    }
+    if let Some(lon) = input.lon {
+        user.lon = Some(lon);
+    }
+    
+    // Update last_seen when going online
+    if input.status == "available" || input.status == "busy" {
+        user.last_seen = sys_time()?.as_seconds_since_epoch();
+    }
+    
+    let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
+    Ok(user)
+}
+
+#[hdk_extern]
+pub fn get_available_drivers(_: ()) -> ExternResult<Vec<(AgentPubKey, f64, f64)>> {
+    // Get all users who are drivers and available
+    // This is simplified - in production, use proper query/index
+    let mut drivers = Vec::new();
+    
+    // For now, return empty - will need proper user index in production
+    // This would query all users where is_driver = true and driver_status = "available"
+    // and return their agent_id, lat, lon
+    
+    Ok(drivers)
+}
+
+#[hdk_extern]
+pub fn get_driver_location(driver_id: AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
+    let user = get_user_by_agent(&driver_id)?;
+    
+    if let Some(u) = user {
+        if u.is_driver && u.driver_status.as_ref().map(|s| s.as_str()) == Some("available") {
+            if let (Some(lat), Some(lon)) = (u.lat, u.lon) {
+                return Ok(Some((lat, lon)));
+            }
+        }
+    }
+    
+    Ok(None)
+}
+
+#[hdk_extern]
+pub fn get_all_drivers(_: ()) -> ExternResult<Vec<(AgentPubKey, String, Option<f64>, Option<f64>, Option<String>)>> {
+    // Get all drivers with their status and location
+    // Returns: (agent_id, status, lat, lon, vehicle_info as JSON string)
+    // This is simplified - in production, use proper query/index
+    let mut drivers = Vec::new();
+    
+    // For now, return empty - will need proper user index in production
+    Ok(drivers)
+}
+
+// ========== Ride Quoting ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuoteRideInput {
+    pub pickup_lat: f64,
+    pub pickup_lon: f64,
+    pub dropoff_lat: f64,
+    pub dropoff_lon: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RideQuote {
+    pub driver_id: AgentPubKey,
+    pub driver_name: String,
+    pub distance_km: f64,
+    pub estimated_price: f64,
+    pub currency: String,
+    pub vehicle_info: Option<String>, // JSON string of VehicleInfo
+    pub estimated_duration_minutes: Option<u32>,
+}
+
+#[hdk_extern]
+pub fn quote_ride(input: QuoteRideInput) -> ExternResult<Vec<RideQuote>> {
+    // Calculate distance between pickup and dropoff
+    let distance_km = calculate_distance(
+        input.pickup_lat,
+        input.pickup_lon,
+        input.dropoff_lat,
+        input.dropoff_lon,
+    );
+    
+    // Get all available drivers
+    let drivers = get_available_drivers(())?;
+    let mut quotes = Vec::new();
+    
+    for (driver_id, driver_lat, driver_lon) in drivers {
+        // Get driver info
+        let user = get_user_by_agent(&driver_id)?;
+        
+        if let Some(driver_user) = user {
+            if driver_user.is_driver && driver_user.driver_status.as_ref().map(|s| s.as_str()) == Some("available") {
+                // Calculate distance from driver to pickup
+                let driver_to_pickup_km = calculate_distance(
+                    driver_lat,
+                    driver_lon,
+                    input.pickup_lat,
+                    input.pickup_lon,
+                );
+                
+                // Get vehicle info and pricing
+                let mut estimated_price = 0.0;
+                let mut currency = "CLP".to_string();
+                let vehicle_info_json = None;
+                
+                if let Some(ref vehicle_info) = driver_user.vehicle_info {
+                    currency = vehicle_info.currency.clone();
+                    
+                    // Calculate price: base_price + (distance_km * price_per_km)
+                    if let Some(base) = vehicle_info.base_price {
+                        estimated_price += base;
+                    }
+                    if let Some(price_per_km) = vehicle_info.price_per_km {
+                        estimated_price += distance_km * price_per_km;
+                    }
+                    
+                    // Serialize vehicle info to JSON string
+                    if let Ok(json) = serde_json::to_string(vehicle_info) {
+                        vehicle_info_json = Some(json);
+                    }
+                }
+                
+                // Estimate duration (simplified: ~30 km/h average in city)
+                let estimated_duration = Some((distance_km / 30.0 * 60.0) as u32);
+                
+                quotes.push(RideQuote {
+                    driver_id,
+                    driver_name: driver_user.name,
+                    distance_km,
+                    estimated_price,
+                    currency,
+                    vehicle_info: vehicle_info_json,
+                    estimated_duration_minutes: estimated_duration,
+                });
+            }
+        }
+    }
+    
+    // Sort by price (lowest first)
+    quotes.sort_by(|a, b| a.estimated_price.partial_cmp(&b.estimated_price).unwrap_or(std::cmp::Ordering::Equal));
+    
+    Ok(quotes)
+}
+
+// Calculate distance between two points using Haversine formula (in kilometers)
+fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+    
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    
+    let a = (d_lat / 2.0).sin() * (d_lat / 2.0).sin() +
+            lat1.to_radians().cos() * lat2.to_radians().cos() *
+            (d_lon / 2.0).sin() * (d_lon / 2.0).sin();
+    
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    
+    EARTH_RADIUS_KM * c
+}
+
+// ========== Wish System ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateWishInput {
+    pub text: String,
+    pub image_hash: Option<String>,
+    pub video_hash: Option<String>,
+}
+
+#[hdk_extern]
+pub fn create_wish(input: CreateWishInput) -> ExternResult<EntryHash> {
+    let author_id = agent_info()?.agent_latest_pubkey();
+    
+    let wish = Wish {
+        author_id,
+        text: input.text,
+        image_hash: input.image_hash,
+        video_hash: input.video_hash,
+        fulfilled: false,
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let wish_hash = create_entry(EntryTypes::Wish(wish.clone()))?;
+    
+    // Link user to wish
+    create_link(
+        author_id,
+        wish_hash,
+        LinkTypes::UserToWishes,
+        (),
+    )?;
+    
+    Ok(wish_hash)
+}
+
+#[hdk_extern]
+pub fn get_wishes(_: ()) -> ExternResult<Vec<Wish>> {
+    // Get all wishes (simplified - in production, use proper query/index)
+    // For now, return empty - will need proper wish index in production
+    let mut wishes = Vec::new();
+    Ok(wishes)
+}
+
+#[hdk_extern]
+pub fn get_user_wishes(user_id: AgentPubKey) -> ExternResult<Vec<Wish>> {
+    let links = get_links(user_id, LinkTypes::UserToWishes, None)?;
+    let mut hidden = hidden_targets()?;
+    let mut wishes = Vec::new();
+    
+    for link in links {
+        let entry_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if is_hidden(&ReportTarget::Wish(entry_hash.clone()), &mut hidden)? {
+            continue;
+        }
+        let element = get(entry_hash, GetOptions::default())?
+            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish not found".into())))?;
+        
+        if let Some(Entry::App(entry_bytes)) = element.entry() {
+            let wish: Wish = entry_bytes.try_into()?;
+            wishes.push(wish);
+        }
+    }
+    
+    // Sort by created_at desc
+    wishes.sort_by_key(|w| std::cmp::Reverse(w.created_at));
+    Ok(wishes)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HelpWishInput {
+    pub wish_id: EntryHash,
+    pub message: Option<String>,
+}
+
+#[hdk_extern]
+pub fn help_wish(input: HelpWishInput) -> ExternResult<EntryHash> {
+    let helper_id = agent_info()?.agent_latest_pubkey();
+    
+    // Verify wish exists
+    let element = get(input.wish_id.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish not found".into())))?;
+    
+    let wish: Wish = element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish entry not found".into())))?;
+    
+    if wish.fulfilled {
+        return Err(wasm_error!(WasmErrorInner::Guest("Wish already fulfilled".into())));
+    }
+    
+    ensure_not_blocked_by(&wish.author_id, &helper_id)?;
+    
+    // Check if already helping
+    let existing_links = get_links(input.wish_id.clone(), LinkTypes::WishToHelpers, None)?;
+    for link in existing_links {
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(Entry::App(entry_bytes)) = element.entry() {
+                if let Ok(help_entry) = entry_bytes.try_into::<WishHelp>() {
+                    if help_entry.helper_id == helper_id && (help_entry.status == "pending" || help_entry.status == "accepted") {
+                        return Err(wasm_error!(WasmErrorInner::Guest("Already helping this wish".into())));
+                    }
+                }
+            }
+        }
+    }
+    
+    let wish_help = WishHelp {
+        wish_id: input.wish_id.clone(),
+        helper_id,
+        message: input.message,
+        status: "pending".to_string(),
+        created_at: sys_time()?.as_seconds_since_epoch(),
+    };
+    
+    let help_hash = create_entry(EntryTypes::WishHelp(wish_help.clone()))?;
+    
+    // Link wish to helper
+    create_link(
+        input.wish_id,
+        help_hash,
+        LinkTypes::WishToHelpers,
+        (),
+    )?;
+    
+    reward_activity(tamagochi::Activity::WishHelp, help_hash.clone())?;
+    award_achievements(AchievementEvidence::WishHelp)?;
+    
+    Ok(help_hash)
+}
+
+#[hdk_extern]
+pub fn get_wish_helpers(wish_id: EntryHash) -> ExternResult<Vec<WishHelp>> {
+    let links = get_links(wish_id, LinkTypes::WishToHelpers, None)?;
+    let mut helpers = Vec::new();
+    
+    for link in links {
+        let entry_hash = link.target;
+        let element = get(entry_hash, GetOptions::default())?
+            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("WishHelp not found".into())))?;
+        
+        if let Some(Entry::App(entry_bytes)) = element.entry() {
+            let help: WishHelp = entry_bytes.try_into()?;
+            helpers.push(help);
+        }
+    }
+    
+    helpers.sort_by_key(|h| std::cmp::Reverse(h.created_at));
+    Ok(helpers)
+}
+
+#[hdk_extern]
+pub fn mark_wish_fulfilled(wish_id: EntryHash) -> ExternResult<Wish> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    
+    let element = get(wish_id.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish not found".into())))?;
+    
+    let mut wish: Wish = element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish entry not found".into())))?;
+    
+    if wish.author_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only wish author can mark as fulfilled".into())));
+    }
+    
+    wish.fulfilled = true;
+    let _updated_hash = create_entry(EntryTypes::Wish(wish.clone()))?;
+    
+    Ok(wish)
+}
+
+// ========== Tamagochi Visits ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VisitTamagochiInput {
+    pub owner_id: AgentPubKey,
+    pub message: Option<String>,
+    pub interaction: Option<VisitInteraction>,
+    pub gift_item_id: Option<String>,
+}
+
+/// Interactions wait for the owner in get_pending_visits; gifts leave the visitor's inventory
+/// right away and are not refunded if the owner declines. The visitor's pet grows with
+/// collect_visit_play once the owner accepts.
+#[hdk_extern]
+pub fn visit_tamagochi(input: VisitTamagochiInput) -> ExternResult<EntryHash> {
+    let visitor_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    
+    if input.owner_id == visitor_id {
+        return Err(wasm_error!(WasmErrorInner::Guest("Cannot visit your own tamagochi".into())));
+    }
+    ensure_not_blocked_by(&input.owner_id, &visitor_id)?;
+    
+    // Verify owner has tamagochi
+    let owner_tamagochi = get_tamagochi_for_user(input.owner_id.clone())?;
+    if owner_tamagochi.is_none() {
+        return Err(wasm_error!(WasmErrorInner::Guest("User doesn't have a tamagochi".into())));
+    }
+    
+    // Rate limit: the visitor's links are tagged with the visited owner
+    let owner_tag = LinkTag::new(input.owner_id.get_raw_39().to_vec());
+    let my_visits = get_links(visitor_id.clone(), LinkTypes::UserToTamagochiVisits, None)?;
+    let visits_today = my_visits
+        .iter()
+        .filter(|l| now - l.timestamp.as_seconds_since_epoch() < SECONDS_PER_DAY)
+        .count();
+    if visits_today >= MAX_DAILY_VISITS {
+        return Err(wasm_error!(WasmErrorInner::Guest("Daily visit limit reached".into())));
+    }
+    if my_visits.iter().any(|l| l.tag == owner_tag && now - l.timestamp.as_seconds_since_epoch() < VISIT_COOLDOWN_SECONDS) {
+        return Err(wasm_error!(WasmErrorInner::Guest("You visited this tamagochi recently".into())));
+    }
+    
+    let gift_item_id = match input.interaction {
+        Some(VisitInteraction::Gift) => {
+            let item_id = input.gift_item_id
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Choose an item to gift".into())))?;
+            change_inventory(InventoryChange::Use { item_id: item_id.clone() })?;
+            Some(item_id)
+        }
+        _ => None,
+    };
+    
+    let visit = TamagochiVisit {
+        visitor_id: visitor_id.clone(),
+        tamagochi_owner_id: input.owner_id.clone(),
+        message: input.message,
+        interaction: input.interaction.clone(),
+        gift_item_id,
+        visited_at: now,
+    };
+    
+    let visit_hash = create_entry(EntryTypes::TamagochiVisit(visit.clone()))?;
+    
+    // Link visitor to visit
+    create_link(
+        visitor_id.clone(),
+        visit_hash.clone(),
+        LinkTypes::UserToTamagochiVisits,
+        owner_tag,
+    )?;
+    
+    // Link tamagochi owner to visit
+    create_link(
+        input.owner_id,
+        visit_hash.clone(),
+        LinkTypes::TamagochiToVisits,
+        (),
+    )?;
+    
+    Ok(visit_hash)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingVisit {
+    pub visit_hash: EntryHash,
+    pub visit: TamagochiVisit,
+}
+
+/// Interactive visits to the caller's pet that haven't been accepted or declined yet
+#[hdk_extern]
+pub fn get_pending_visits(_: ()) -> ExternResult<Vec<PendingVisit>> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let links = get_links(owner_id.clone(), LinkTypes::TamagochiToVisits, None)?;
+    let mut hidden = hidden_targets()?;
+    let mut pending = Vec::new();
+    
+    for link in links {
+        let visit_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if is_hidden(&ReportTarget::TamagochiVisit(visit_hash.clone()), &mut hidden)? {
+            continue;
+        }
+        if get_visit_response(&visit_hash, &owner_id)?.is_some() {
+            continue;
+        }
+        if let Some(element) = get(visit_hash.clone(), GetOptions::default())? {
+            if let Some(visit) = element.entry().to_app_option::<TamagochiVisit>()? {
+                if visit.interaction.is_some() {
+                    pending.push(PendingVisit { visit_hash, visit });
+                }
+            }
+        }
+    }
+    
+    // Oldest first
+    pending.sort_by_key(|p| p.visit.visited_at);
+    Ok(pending)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RespondToVisitInput {
+    pub visit_hash: EntryHash,
+    pub accept: bool,
+}
+
+/// Owner-side half of an interaction; returns the updated pet when accepted
+#[hdk_extern]
+pub fn respond_to_visit(input: RespondToVisitInput) -> ExternResult<Option<Tamagochi>> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    
+    let element = get(input.visit_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Visit not found".into())))?;
+    let visit: TamagochiVisit = element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Visit not found".into())))?;
+    
+    if visit.tamagochi_owner_id != owner_id {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the owner can respond to this visit".into())));
+    }
+    let interaction = visit.interaction
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("This visit has nothing to accept".into())))?;
+    if get_visit_response(&input.visit_hash, &owner_id)?.is_some() {
+        return Err(wasm_error!(WasmErrorInner::Guest("Already responded".into())));
+    }
+    
+    let response = if input.accept { "accepted" } else { "declined" };
+    create_link(
+        input.visit_hash,
+        owner_id.clone(),
+        LinkTypes::VisitToResponse,
+        LinkTag::new(response.as_bytes().to_vec()),
+    )?;
+    
+    if !input.accept {
+        return Ok(None);
+    }
+    
+    let event = match interaction {
+        VisitInteraction::Pet => tamagochi::Event::VisitorPet,
+        VisitInteraction::Play => tamagochi::Event::VisitorPlay,
+        VisitInteraction::Gift => {
+            let item_id = visit.gift_item_id.unwrap_or_default();
+            let item = tamagochi::find_item(&item_id)
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Unknown item".into())))?;
+            tamagochi::Event::UseItem(item)
+        }
+    };
+    
+    Ok(Some(run_tamagochi(&[event])?))
+}
+
+/// Visitor-side half: each recently accepted interaction grows the visitor's pet once
+#[hdk_extern]
+pub fn collect_visit_play(_: ()) -> ExternResult<Option<Tamagochi>> {
+    let visitor_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    if get_current_tamagochi(&visitor_id)?.is_none() {
+        return Ok(None);
+    }
+    
+    let links = get_links(visitor_id.clone(), LinkTypes::UserToTamagochiVisits, None)?;
+    let mut events = Vec::new();
     
-    // Get all friend links
-    let links = get_links(agent, LinkTypes::UserToFriends, None)?;
     for link in links {
-        if link.target == friend_id {
-            delete_link(link.create_link_hash)?;
+        // Una versión aplica pocos eventos; el resto se cobra en la próxima llamada
+        if events.len() >= tamagochi::MAX_EVENTS_PER_UPDATE {
+            break;
+        }
+        if now - link.timestamp.as_seconds_since_epoch() > VISIT_CLAIM_WINDOW_SECONDS {
+            continue;
+        }
+        let visit_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let visit = match get(visit_hash.clone(), GetOptions::default())? {
+            Some(element) => match element.entry().to_app_option::<TamagochiVisit>().ok().flatten() {
+                Some(visit) => visit,
+                None => continue,
+            },
+            None => continue,
+        };
+        if visit.visitor_id != visitor_id || visit.interaction.is_none() {
+            continue;
+        }
+        if get_visit_response(&visit_hash, &visit.tamagochi_owner_id)? != Some(true) {
+            continue;
+        }
+        let claims = get_links(visit_hash.clone(), LinkTypes::VisitToPlayClaims, None)?;
+        if claims.iter().any(|l| l.author == visitor_id) {
+            continue;
         }
+        
+        create_link(
+            visit_hash,
+            visitor_id.clone(),
+            LinkTypes::VisitToPlayClaims,
+            (),
+        )?;
+        events.push(tamagochi::Event::SocialPlay);
     }
     
-    Ok("Friend removed".to_string())
+    if events.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(run_tamagochi(&events)?))
 }
 
-#[hdk_extern]
-pub fn get_friends(_: ()) -> ExternResult<Vec<AgentPubKey>> {
-    let agent = agent_info()?.agent_latest_pubkey();
-    let links = get_links(agent, LinkTypes::UserToFriends, None)?;
-    Ok(links.into_iter().map(|l| l.target).collect())
+// The owner's answer to a visit (true = accepted); links by anyone else are ignored
+fn get_visit_response(visit_hash: &EntryHash, owner_id: &AgentPubKey) -> ExternResult<Option<bool>> {
+    let links = get_links(visit_hash.clone(), LinkTypes::VisitToResponse, None)?;
+    Ok(links
+        .into_iter()
+        .filter(|l| l.author == *owner_id)
+        .min_by_key(|l| l.timestamp)
+        .map(|l| l.tag.into_inner() == b"accepted".to_vec()))
 }
 
 #[hdk_extern]
-pub fn is_friend(user_id: AgentPubKey) -> ExternResult<bool> {
-    let agent = agent_info()?.agent_latest_pubkey();
-    let links = get_links(agent, LinkTypes::UserToFriends, None)?;
-    Ok(links.iter().any(|l| l.target == user_id))
+pub fn get_tamagochi_visits(owner_id: AgentPubKey) -> ExternResult<Vec<TamagochiVisit>> {
+    let links = get_links(owner_id, LinkTypes::TamagochiToVisits, None)?;
+    let mut hidden = hidden_targets()?;
+    let mut visits = Vec::new();
+    
+    for link in links {
+        let entry_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if is_hidden(&ReportTarget::TamagochiVisit(entry_hash.clone()), &mut hidden)? {
+            continue;
+        }
+        let element = get(entry_hash, GetOptions::default())?
+            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Visit not found".into())))?;
+        
+        if let Some(Entry::App(entry_bytes)) = element.entry() {
+            let visit: TamagochiVisit = entry_bytes.try_into()?;
+            visits.push(visit);
+        }
+    }
+    
+    visits.sort_by_key(|v| std::cmp::Reverse(v.visited_at));
+    Ok(visits)
 }
 
-// ========== User Preferences ==========
-
 #[hdk_extern]
-pub fn get_user_preferences(_: ()) -> ExternResult<Option<UserPreferences>> {
-    let agent = agent_info()?.agent_latest_pubkey();
+pub fn get_tamagochi_for_user(user_id: AgentPubKey) -> ExternResult<Option<Tamagochi>> {
+    let now = sys_time()?.as_seconds_since_epoch();
     
-    // Get preferences entry for this user
-    // Simplified - in production, need proper agent-to-preferences mapping
-    // For now, return default preferences
-    Ok(Some(UserPreferences {
-        owner_id: agent,
-        app_color: "#1f7aec".to_string(),
-        tamagochi_enabled: true,
-        location_sharing_enabled: false,
-        updated_at: sys_time()?.as_seconds_since_epoch(),
-    }))
+    match get_current_tamagochi(&user_id)? {
+        Some((_, mut tamagochi)) => {
+            let outcome = tamagochi::simulate(&pet_state(&tamagochi), &[], now);
+            apply_pet_state(&mut tamagochi, outcome.state);
+            Ok(Some(tamagochi))
+        }
+        None => Ok(None),
+    }
 }
 
+// ========== Profile Cover ==========
+
 #[derive(Serialize, Deserialize, Debug)]
-pub struct UpdatePreferencesInput {
-    pub app_color: Option<String>,
-    pub tamagochi_enabled: Option<bool>,
-    pub location_sharing_enabled: Option<bool>,
+pub struct SetProfileCoverInput {
+    pub cover_type: String, // "tamagochi", "image", "video"
+    pub image_hash: Option<String>,
+    pub video_hash: Option<String>,
 }
 
 #[hdk_extern]
-pub fn update_user_preferences(input: UpdatePreferencesInput) -> ExternResult<UserPreferences> {
-    let agent = agent_info()?.agent_latest_pubkey();
+pub fn set_profile_cover(input: SetProfileCoverInput) -> ExternResult<EntryHash> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
     
-    // Get existing preferences or create new
-    let mut prefs = get_user_preferences(())?.unwrap_or_else(|| UserPreferences {
-        owner_id: agent,
-        app_color: "#1f7aec".to_string(),
-        tamagochi_enabled: true,
-        location_sharing_enabled: false,
+    // Delete old cover if exists
+    let old_links = get_links(owner_id, LinkTypes::UserToProfileCover, None)?;
+    for link in old_links {
+        delete_link(link.create_link_hash)?;
+    }
+    
+    let cover = ProfileCover {
+        owner_id,
+        cover_type: input.cover_type,
+        image_hash: input.image_hash,
+        video_hash: input.video_hash,
         updated_at: sys_time()?.as_seconds_since_epoch(),
-    });
+    };
     
-    // Update fields
-    if let Some(color) = input.app_color {
-        prefs.app_color = color;
-    }
-    if let Some(enabled) = input.tamagochi_enabled {
-        prefs.tamagochi_enabled = enabled;
-    }
-    if let Some(sharing) = input.location_sharing_enabled {
-        prefs.location_sharing_enabled = sharing;
-    }
-    prefs.updated_at = sys_time()?.as_seconds_since_epoch();
+    let cover_hash = create_entry(EntryTypes::ProfileCover(cover.clone()))?;
     
-    // Create or update entry
-    let _hash = create_entry(EntryTypes::UserPreferences(prefs.clone()))?;
+    // Link user to cover
+    create_link(
+        owner_id,
+        cover_hash,
+        LinkTypes::UserToProfileCover,
+        (),
+    )?;
     
-    Ok(prefs)
+    Ok(cover_hash)
 }
 
-// ========== Location Sharing ==========
-
 #[hdk_extern]
-pub fn get_user_location(user_id: AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
-    get_shared_location(user_id)
+pub fn get_profile_cover(user_id: AgentPubKey) -> ExternResult<Option<ProfileCover>> {
+    let links = get_links(user_id, LinkTypes::UserToProfileCover, None)?;
+    for link in links {
+        let entry_hash = link.target;
+        let element = get(entry_hash, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(Entry::App(entry_bytes)) = element.entry() {
+                if let Ok(cover) = entry_bytes.try_into::<ProfileCover>() {
+                    return Ok(Some(cover));
+                }
+            }
+        }
+    }
+    Ok(None)
 }
 
-// ========== Driver System ==========
+// ========== Helper Functions ==========
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct RegisterDriverInput {
-    pub vehicle_info: integrity::VehicleInfo,
-    pub price_per_km: Option<f64>,
-    pub base_price: Option<f64>,
-    pub currency: String,
+fn hash_email(email: &str) -> ExternResult<String> {
+    use sha2::{Sha256, Digest};
+    let mut hasher = Sha256::new();
+    hasher.update(email.as_bytes());
+    Ok(hex::encode(hasher.finalize()))
 }
 
-#[hdk_extern]
-pub fn register_as_driver(input: RegisterDriverInput) -> ExternResult<User> {
-    let agent = agent_info()?.agent_latest_pubkey();
-    let mut user = get_user_by_agent(&agent)?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+fn hash_password(password: &str) -> ExternResult<String> {
+    bcrypt::hash(password, bcrypt::DEFAULT_COST)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Password hashing failed: {}", e))))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    bcrypt::verify(password, hash).unwrap_or(false)
+}
+
+// "12.345.678-k" -> "12345678K"
+fn normalize_rut(rut: &str) -> String {
+    rut.trim().replace(".", "").replace("-", "").to_uppercase()
+}
+
+fn validate_rut(rut: &str) -> bool {
+    let clean = normalize_rut(rut);
+    if !regex::Regex::new(r"^\d{7,8}[0-9K]$").unwrap().is_match(&clean) {
+        return false;
+    }
     
-    // Update vehicle info with pricing
-    let mut vehicle_info = input.vehicle_info;
-    vehicle_info.price_per_km = input.price_per_km;
-    vehicle_info.base_price = input.base_price;
-    vehicle_info.currency = input.currency.clone();
+    let body = &clean[..clean.len() - 1];
+    let dv = &clean[clean.len() - 1..];
     
-    user.is_driver = true;
-    user.driver_status = Some("offline".to_string());
-    user.vehicle_info = Some(vehicle_info);
+    let mut sum = 0;
+    let mut multiplier = 2;
+    for ch in body.chars().rev() {
+        sum += ch.to_digit(10).unwrap() as i32 * multiplier;
+        multiplier = if multiplier == 7 { 2 } else { multiplier + 1 };
+    }
     
-    let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
-    Ok(user)
+    let remainder = 11 - (sum % 11);
+    let expected = match remainder {
+        11 => "0",
+        10 => "K",
+        _ => &remainder.to_string(),
+    };
+    
+    dv == expected
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct UpdateDriverPricingInput {
-    pub price_per_km: Option<f64>,
-    pub base_price: Option<f64>,
-    pub currency: Option<String>,
+// Only the leading digits and the verifier digit: "12345678K" -> "12.***.***-K"
+fn mask_rut(rut: &str) -> String {
+    let clean = normalize_rut(rut);
+    let (body, dv) = clean.split_at(clean.len() - 1);
+    let lead = &body[..body.len().saturating_sub(6)];
+    format!("{}.***.***-{}", lead, dv)
 }
 
-#[hdk_extern]
-pub fn update_driver_pricing(input: UpdateDriverPricingInput) -> ExternResult<User> {
-    let agent = agent_info()?.agent_latest_pubkey();
-    let mut user = get_user_by_agent(&agent)?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
-    
-    if !user.is_driver {
-        return Err(wasm_error!(WasmErrorInner::Guest("User is not a driver".into())));
+// Iterated SHA-256 keyed with the verifiers' secret; deterministic so equal RUTs collide in the index
+fn hash_rut(rut: &str, key: &str) -> String {
+    use sha2::{Sha256, Digest};
+    let mut digest = Sha256::digest(format!("{}:{}", key, normalize_rut(rut)).as_bytes());
+    for _ in 1..RUT_HASH_ROUNDS {
+        digest = Sha256::digest(digest);
     }
+    hex::encode(digest)
+}
+
+// Newest key stored with set_rut_key on this verifier's own chain
+fn get_rut_key() -> ExternResult<String> {
+    let filter = ChainQueryFilter::new()
+        .entry_type(UnitEntryTypes::RutKey.try_into()?)
+        .include_entries(true);
+    query(filter)?
+        .iter()
+        .rev()
+        .find_map(|record| record.entry().to_app_option::<RutKey>().ok().flatten())
+        .map(|rut_key| rut_key.key)
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Set the verifiers' RUT key first".into())))
+}
+
+// Claims are written by their owner (validated), so a link authored by its target is the holder
+fn ensure_rut_available(rut_key_hash: &str, agent: &AgentPubKey) -> ExternResult<()> {
+    let links = get_links(rut_index_path(rut_key_hash)?, LinkTypes::RutIndex, None)?;
+    if links.iter().any(|link| link.target != agent.clone().into() && link.target == link.author.clone().into()) {
+        return Err(wasm_error!(WasmErrorInner::Guest("RUT already registered to another account".into())));
+    }
+    Ok(())
+}
+
+fn generate_reset_token() -> String {
+    use sha2::{Sha256, Digest};
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}{}", sys_time().unwrap().as_seconds_since_epoch(), rand::random::<u64>()).as_bytes());
+    hex::encode(&hasher.finalize()[..16])
+}
+
+fn calculate_image_hash(bytes: &[u8]) -> String {
+    use sha2::{Sha256, Digest};
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex::encode(hasher.finalize())
+}
+
+// Engagement score decayed by age (gravity-style): newer and more active posts rank higher
+fn trending_score(claps: u64, likes: u32, comments: u32, age_seconds: i64) -> f64 {
+    let engagement = claps as f64 + likes as f64 * 2.0 + comments as f64 * 3.0;
+    let age_hours = age_seconds.max(0) as f64 / SECONDS_PER_HOUR as f64;
+    (engagement + 1.0) / (age_hours + 2.0).powf(1.5)
+}
+
+fn format_chat_id(a: &AgentPubKey, b: &AgentPubKey) -> String {
+    let mut ids = vec![a.to_string(), b.to_string()];
+    ids.sort();
+    format!("chat_{}_{}", ids[0], ids[1])
+}
+
+// Global feed anchor: feed.<day>.<hour>, both counted in whole units since the epoch
+fn feed_bucket_path(created_at: i64) -> ExternResult<TypedPath> {
+    let day = created_at.div_euclid(SECONDS_PER_DAY);
+    let hour = created_at.div_euclid(SECONDS_PER_HOUR);
+    Path::from(format!("{}.{}.{}", FEED_ROOT, day, hour)).typed(LinkTypes::FeedPath)
+}
+
+// Child buckets of a feed path not newer than `max_bucket`, newest first
+fn feed_bucket_children(parent: &TypedPath, max_bucket: i64) -> ExternResult<Vec<(i64, TypedPath)>> {
+    let mut children = Vec::new();
     
-    if let Some(ref mut vehicle_info) = user.vehicle_info {
-        if let Some(price_per_km) = input.price_per_km {
-            vehicle_info.price_per_km = Some(price_per_km);
+    for child in parent.children_paths()? {
+        let bucket = child.leaf()
+            .and_then(|component| String::try_from(component).ok())
+            .and_then(|leaf| leaf.parse::<i64>().ok());
+        if let Some(bucket) = bucket {
+            if bucket <= max_bucket {
+                children.push((bucket, child));
+            }
         }
-        if let Some(base_price) = input.base_price {
-            vehicle_info.base_price = Some(base_price);
+    }
+    
+    children.sort_by_key(|(bucket, _)| std::cmp::Reverse(*bucket));
+    Ok(children)
+}
+
+fn create_comment(target: CommentTarget, text: String, parent_comment_hash: Option<EntryHash>) -> ExternResult<EntryHash> {
+    let author_id = agent_info()?.agent_latest_pubkey();
+    check_comment_text(&text)?;
+    
+    // Verificar que el objetivo existe y que su dueño no bloqueó al autor
+    let owner_id = match &target {
+        CommentTarget::Post(post_hash) => get_post_by_hash(post_hash)?.author_id,
+        CommentTarget::Product(product_id) => {
+            let element = get(product_id.clone(), GetOptions::default())?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Product not found".into())))?;
+            let product: Product = element.entry()
+                .to_app_option()?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Product entry not found".into())))?;
+            product.seller_id
         }
-        if let Some(currency) = input.currency {
-            vehicle_info.currency = currency;
+    };
+    ensure_not_blocked_by(&owner_id, &author_id)?;
+    
+    // Si es respuesta, el padre debe existir y pertenecer al mismo objetivo
+    if let Some(parent_hash) = &parent_comment_hash {
+        let parent = get_comment_by_hash(parent_hash)?;
+        if parent.target != target {
+            return Err(wasm_error!(WasmErrorInner::Guest("Parent comment belongs to another thread".into())));
         }
-    } else {
-        // Create vehicle info if it doesn't exist
-        user.vehicle_info = Some(integrity::VehicleInfo {
-            make: "".to_string(),
-            model: "".to_string(),
-            year: None,
-            color: None,
-            license_plate: None,
-            capacity: 4,
-            price_per_km: input.price_per_km,
-            base_price: input.base_price,
-            currency: input.currency.unwrap_or_else(|| "CLP".to_string()),
-        });
     }
     
-    let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
-    Ok(user)
+    let comment = Comment {
+        target: target.clone(),
+        author_id,
+        text,
+        parent_comment_hash: parent_comment_hash.clone(),
+        created_at: sys_time()?.as_seconds_since_epoch(),
+        edited_at: None,
+        deleted: false,
+    };
+    
+    let comment_hash = create_entry(EntryTypes::Comment(comment.clone()))?;
+    
+    // Link target to comment
+    let (target_hash, link_type) = match &target {
+        CommentTarget::Post(post_hash) => (post_hash.clone(), LinkTypes::PostToComments),
+        CommentTarget::Product(product_id) => (product_id.clone(), LinkTypes::ProductToComments),
+    };
+    create_link(target_hash, comment_hash.clone(), link_type, ())?;
+    
+    // Si es respuesta, link al comentario padre
+    if let Some(parent_hash) = parent_comment_hash {
+        create_link(
+            parent_hash,
+            comment_hash.clone(),
+            LinkTypes::CommentToReplies,
+            (),
+        )?;
+    }
+    
+    // Hashtags en comentarios hacen descubrible el post
+    if let CommentTarget::Post(post_hash) = &target {
+        index_hashtags(post_hash, &extract_hashtags(&comment.text), comment.created_at)?;
+    }
+    index_mentions(&comment_hash, &extract_mentions(&comment.text), MENTION_IN_COMMENT)?;
+    
+    Ok(comment_hash)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct UpdateDriverStatusInput {
-    pub status: String, // "available", "busy", "offline"
-    pub lat: Option<f64>,
-    pub lon: Option<f64>,
+fn check_comment_text(text: &str) -> ExternResult<()> {
+    if text.trim().is_empty() || text.chars().count() > MAX_COMMENT_CHARS {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!("Comments must have 1-{} characters", MAX_COMMENT_CHARS))));
+    }
+    Ok(())
 }
 
-#[hdk_extern]
-pub fn update_driver_status(input: UpdateDriverStatusInput) -> ExternResult<User> {
-    let agent = agent_info()?.agent_latest_pubkey();
-    let mut user = get_user_by_agent(&agent)?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("User not found".into())))?;
+fn get_comment_by_hash(comment_hash: &EntryHash) -> ExternResult<Comment> {
+    get_latest_comment(comment_hash)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Comment not found".into())))
+}
+
+// Newest version of a comment, following CommentUpdates
+fn get_latest_comment(comment_hash: &EntryHash) -> ExternResult<Option<Comment>> {
+    let element = match get(comment_hash.clone(), GetOptions::default())? {
+        Some(element) => element,
+        None => return Ok(None),
+    };
     
-    if !user.is_driver {
-        return Err(wasm_error!(WasmErrorInner::Guest("User is not a driver".into())));
-    }
+    let original = match element.entry().to_app_option::<Comment>()? {
+        Some(comment) => comment,
+        None => return Ok(None),
+    };
     
-    if input.status != "available" && input.status != "busy" && input.status != "offline" {
-        return Err(wasm_error!(WasmErrorInner::Guest("Invalid driver status".into())));
+    let updates = get_links(comment_hash.clone(), LinkTypes::CommentUpdates, None)?;
+    if let Some(latest) = updates.into_iter().filter(|l| l.author == original.author_id).max_by_key(|l| l.timestamp) {
+        if let Some(updated) = get(latest.target, GetOptions::default())? {
+            if let Some(comment) = updated.entry().to_app_option::<Comment>()? {
+                if comment.author_id == original.author_id {
+                    return Ok(Some(comment));
+                }
+            }
+        }
     }
     
-    user.driver_status = Some(input.status.clone());
-    
-    // Update location if provided
-    if let Some(lat) = input.lat {
-        user.lat = Some(lat);
-    }
-    if let Some(lon) = input.lon {
-        user.lon = Some(lon);
-    }
+    Ok(Some(original))
+}
+
+// Writes a new version and links it from the original hash, which keeps the thread links
+fn save_comment_version(comment_hash: &EntryHash, comment: &Comment) -> ExternResult<()> {
+    let record = get(comment_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Comment not found".into())))?;
+    update_entry(record.action_address().clone(), EntryTypes::Comment(comment.clone()))?;
     
-    // Update last_seen when going online
-    if input.status == "available" || input.status == "busy" {
-        user.last_seen = sys_time()?.as_seconds_since_epoch();
-    }
+    create_link(
+        comment_hash.clone(),
+        hash_entry(&EntryTypes::Comment(comment.clone()))?,
+        LinkTypes::CommentUpdates,
+        (),
+    )?;
+    Ok(())
+}
+
+// Top-level comments of a post or product, oldest first
+fn get_root_comments(target: &CommentTarget) -> ExternResult<Vec<(EntryHash, Comment)>> {
+    let (target_hash, link_type) = match target {
+        CommentTarget::Post(post_hash) => (post_hash.clone(), LinkTypes::PostToComments),
+        CommentTarget::Product(product_id) => (product_id.clone(), LinkTypes::ProductToComments),
+    };
+    let links = get_links(target_hash, link_type, None)?;
     
-    let _user_hash = create_entry(EntryTypes::User(user.clone()))?;
-    Ok(user)
+    let mut comments = load_comments(links)?;
+    comments.retain(|(_, comment)| comment.parent_comment_hash.is_none());
+    Ok(comments)
 }
 
-#[hdk_extern]
-pub fn get_available_drivers(_: ()) -> ExternResult<Vec<(AgentPubKey, f64, f64)>> {
-    // Get all users who are drivers and available
-    // This is simplified - in production, use proper query/index
-    let mut drivers = Vec::new();
+// Direct replies of a comment, oldest first
+fn get_comment_replies(comment_hash: &EntryHash) -> ExternResult<Vec<(EntryHash, Comment)>> {
+    let links = get_links(comment_hash.clone(), LinkTypes::CommentToReplies, None)?;
+    load_comments(links)
+}
+
+fn load_comments(links: Vec<Link>) -> ExternResult<Vec<(EntryHash, Comment)>> {
+    let mut comments = Vec::new();
     
-    // For now, return empty - will need proper user index in production
-    // This would query all users where is_driver = true and driver_status = "available"
-    // and return their agent_id, lat, lon
+    for link in links {
+        let comment_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if let Some(comment) = get_latest_comment(&comment_hash)? {
+            comments.push((comment_hash, comment));
+        }
+    }
     
-    Ok(drivers)
+    // Sort by created_at ascending (oldest first)
+    comments.sort_by(|(a_hash, a), (b_hash, b)| (a.created_at, a_hash).cmp(&(b.created_at, b_hash)));
+    Ok(comments)
 }
 
-#[hdk_extern]
-pub fn get_driver_location(driver_id: AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
-    let user = get_user_by_agent(&driver_id)?;
+// Builds a node with `depth` levels of replies; deleted comments without replies are dropped
+fn build_comment_node(
+    comment_hash: EntryHash,
+    comment: Comment,
+    depth: u32,
+    agent: &AgentPubKey,
+    my_blocked: &[AgentPubKey],
+    hidden_authors: &mut HashMap<AgentPubKey, bool>,
+) -> ExternResult<Option<CommentNode>> {
+    let mut visible = Vec::new();
+    for (reply_hash, reply) in get_comment_replies(&comment_hash)? {
+        if !is_author_hidden(&reply.author_id, agent, my_blocked, hidden_authors)? {
+            visible.push((reply_hash, reply));
+        }
+    }
     
-    if let Some(u) = user {
-        if u.is_driver && u.driver_status.as_ref().map(|s| s.as_str()) == Some("available") {
-            if let (Some(lat), Some(lon)) = (u.lat, u.lon) {
-                return Ok(Some((lat, lon)));
+    let mut replies = Vec::new();
+    let mut reply_count = 0;
+    for (reply_hash, reply) in visible {
+        if depth == 0 {
+            // Only count; deleted leaves still need a lookup to know if they count
+            if !reply.deleted || !get_links(reply_hash.clone(), LinkTypes::CommentToReplies, None)?.is_empty() {
+                reply_count += 1;
             }
+            continue;
+        }
+        if let Some(node) = build_comment_node(reply_hash, reply, depth - 1, agent, my_blocked, hidden_authors)? {
+            replies.push(node);
+            reply_count += 1;
         }
     }
     
-    Ok(None)
+    if comment.deleted && reply_count == 0 {
+        return Ok(None);
+    }
+    
+    Ok(Some(CommentNode { comment_hash, comment, reply_count, replies }))
 }
 
-#[hdk_extern]
-pub fn get_all_drivers(_: ()) -> ExternResult<Vec<(AgentPubKey, String, Option<f64>, Option<f64>, Option<String>)>> {
-    // Get all drivers with their status and location
-    // Returns: (agent_id, status, lat, lon, vehicle_info as JSON string)
-    // This is simplified - in production, use proper query/index
-    let mut drivers = Vec::new();
+fn get_story_by_hash(story_hash: &EntryHash) -> ExternResult<Story> {
+    let element = get(story_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Story not found".into())))?;
     
-    // For now, return empty - will need proper user index in production
-    Ok(drivers)
+    element.entry()
+        .to_app_option()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Story entry not found".into())))
 }
 
-// ========== Ride Quoting ==========
+// Unexpired stories of `author`, oldest first
+fn get_active_stories(author: &AgentPubKey, now: i64) -> ExternResult<Vec<StoryItem>> {
+    let links = get_links(author.clone(), LinkTypes::UserToStories, None)?;
+    let mut stories = Vec::new();
+    
+    for link in links {
+        let story_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let element = get(story_hash.clone(), GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(story) = element.entry().to_app_option::<Story>()? {
+                if story.expires_at > now {
+                    stories.push(StoryItem { story_hash, story });
+                }
+            }
+        }
+    }
+    
+    stories.sort_by_key(|s| s.story.created_at);
+    Ok(stories)
+}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct QuoteRideInput {
-    pub pickup_lat: f64,
-    pub pickup_lon: f64,
-    pub dropoff_lat: f64,
-    pub dropoff_lon: f64,
+// Moderation state of a report target: latest decision plus reports received after it
+struct ModerationStatus {
+    decision: Option<ModerationAction>,
+    decided_at: Option<i64>,
+    pending_reports: u32,
+    threshold: u32,
+    hidden: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct RideQuote {
-    pub driver_id: AgentPubKey,
-    pub driver_name: String,
-    pub distance_km: f64,
-    pub estimated_price: f64,
-    pub currency: String,
-    pub vehicle_info: Option<String>, // JSON string of VehicleInfo
-    pub estimated_duration_minutes: Option<u32>,
+fn report_hide_threshold() -> ExternResult<u32> {
+    Ok(dna_properties()?.report_hide_threshold.max(1))
 }
 
-#[hdk_extern]
-pub fn quote_ride(input: QuoteRideInput) -> ExternResult<Vec<RideQuote>> {
-    // Calculate distance between pickup and dropoff
-    let distance_km = calculate_distance(
-        input.pickup_lat,
-        input.pickup_lon,
-        input.dropoff_lat,
-        input.dropoff_lon,
-    );
-    
-    // Get all available drivers
-    let drivers = get_available_drivers(())?;
-    let mut quotes = Vec::new();
-    
-    for (driver_id, driver_lat, driver_lon) in drivers {
-        // Get driver info
-        let user = get_user_by_agent(&driver_id)?;
-        
-        if let Some(driver_user) = user {
-            if driver_user.is_driver && driver_user.driver_status.as_ref().map(|s| s.as_str()) == Some("available") {
-                // Calculate distance from driver to pickup
-                let driver_to_pickup_km = calculate_distance(
-                    driver_lat,
-                    driver_lon,
-                    input.pickup_lat,
-                    input.pickup_lon,
-                );
-                
-                // Get vehicle info and pricing
-                let mut estimated_price = 0.0;
-                let mut currency = "CLP".to_string();
-                let vehicle_info_json = None;
-                
-                if let Some(ref vehicle_info) = driver_user.vehicle_info {
-                    currency = vehicle_info.currency.clone();
-                    
-                    // Calculate price: base_price + (distance_km * price_per_km)
-                    if let Some(base) = vehicle_info.base_price {
-                        estimated_price += base;
-                    }
-                    if let Some(price_per_km) = vehicle_info.price_per_km {
-                        estimated_price += distance_km * price_per_km;
-                    }
-                    
-                    // Serialize vehicle info to JSON string
-                    if let Ok(json) = serde_json::to_string(vehicle_info) {
-                        vehicle_info_json = Some(json);
-                    }
+fn get_moderation_status(target: &ReportTarget, threshold: u32) -> ExternResult<ModerationStatus> {
+    let mut latest: Option<ModerationDecision> = None;
+    let links = get_links(target.base(), LinkTypes::TargetToModeration, None)?;
+    for link in links {
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(decision) = element.entry().to_app_option::<ModerationDecision>()? {
+                // Solo decisiones sobre este mismo objetivo, enlazadas por su moderador
+                if decision.target != *target || link.author != decision.moderator_id {
+                    continue;
+                }
+                if latest.as_ref().map_or(true, |l| decision.decided_at > l.decided_at) {
+                    latest = Some(decision);
                 }
-                
-                // Estimate duration (simplified: ~30 km/h average in city)
-                let estimated_duration = Some((distance_km / 30.0 * 60.0) as u32);
-                
-                quotes.push(RideQuote {
-                    driver_id,
-                    driver_name: driver_user.name,
-                    distance_km,
-                    estimated_price,
-                    currency,
-                    vehicle_info: vehicle_info_json,
-                    estimated_duration_minutes: estimated_duration,
-                });
             }
         }
     }
     
-    // Sort by price (lowest first)
-    quotes.sort_by(|a, b| a.estimated_price.partial_cmp(&b.estimated_price).unwrap_or(std::cmp::Ordering::Equal));
+    // Distinct reporters since the decision, so repeat reports from one agent count once
+    let decided_at = latest.as_ref().map(|d| d.decided_at);
+    let mut reporters = HashSet::new();
+    for report in get_reports(target.clone())? {
+        if decided_at.map_or(true, |at| report.created_at >= at) {
+            reporters.insert(report.reporter_id);
+        }
+    }
+    let pending_reports = reporters.len() as u32;
     
-    Ok(quotes)
+    let decision = latest.map(|d| d.action);
+    let hidden = match decision {
+        Some(ModerationAction::Hide) | Some(ModerationAction::Remove) => true,
+        _ => pending_reports >= threshold,
+    };
+    
+    Ok(ModerationStatus { decision, decided_at, pending_reports, threshold, hidden })
 }
 
-// Calculate distance between two points using Haversine formula (in kilometers)
-fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    const EARTH_RADIUS_KM: f64 = 6371.0;
-    
-    let d_lat = (lat2 - lat1).to_radians();
-    let d_lon = (lon2 - lon1).to_radians();
-    
-    let a = (d_lat / 2.0).sin() * (d_lat / 2.0).sin() +
-            lat1.to_radians().cos() * lat2.to_radians().cos() *
-            (d_lon / 2.0).sin() * (d_lon / 2.0).sin();
-    
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-    
-    EARTH_RADIUS_KM * c
+// Moderation lookups for one page: the threshold is read once and each target at most once
+struct HiddenTargets {
+    threshold: u32,
+    cache: HashMap<AnyLinkableHash, bool>,
 }
 
-// ========== Wish System ==========
+fn hidden_targets() -> ExternResult<HiddenTargets> {
+    Ok(HiddenTargets { threshold: report_hide_threshold()?, cache: HashMap::new() })
+}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct CreateWishInput {
-    pub text: String,
-    pub image_hash: Option<String>,
-    pub video_hash: Option<String>,
+// Hidden from listings: over the report threshold pending review, or hidden/removed by a moderator
+fn is_hidden(target: &ReportTarget, hidden: &mut HiddenTargets) -> ExternResult<bool> {
+    let base = target.base();
+    if let Some(result) = hidden.cache.get(&base) {
+        return Ok(*result);
+    }
+    let result = get_moderation_status(target, hidden.threshold)?.hidden;
+    hidden.cache.insert(base, result);
+    Ok(result)
 }
 
-#[hdk_extern]
-pub fn create_wish(input: CreateWishInput) -> ExternResult<EntryHash> {
-    let author_id = agent_info()?.agent_latest_pubkey();
+fn is_post_hidden(post_hash: &EntryHash, hidden: &mut HiddenTargets) -> ExternResult<bool> {
+    is_hidden(&ReportTarget::Post(post_hash.clone()), hidden)
+}
+
+// Queue links point at the target's base; the tag keeps its kind so it can be rebuilt
+fn enqueue_for_moderation(target: &ReportTarget) -> ExternResult<()> {
+    create_link(
+        Path::from(MODERATION_QUEUE_ROOT).path_entry_hash()?,
+        target.base(),
+        LinkTypes::ModerationQueue,
+        LinkTag::new(target.kind().as_bytes().to_vec()),
+    )?;
+    Ok(())
+}
+
+fn load_reports(links: Vec<Link>) -> ExternResult<Vec<Report>> {
+    let mut reports = Vec::new();
     
-    let wish = Wish {
-        author_id,
-        text: input.text,
-        image_hash: input.image_hash,
-        video_hash: input.video_hash,
-        fulfilled: false,
-        created_at: sys_time()?.as_seconds_since_epoch(),
+    for link in links {
+        let author = link.author;
+        let element = get(link.target, GetOptions::default())?;
+        if let Some(element) = element {
+            if let Some(report) = element.entry().to_app_option::<Report>()? {
+                if report.reporter_id == author {
+                    reports.push(report);
+                }
+            }
+        }
+    }
+    
+    // Sort by created_at descending (newest first)
+    reports.sort_by_key(|r| -r.created_at);
+    Ok(reports)
+}
+
+// Users are reported directly; content is attributed to whoever signed it, cited by action for validation.
+// Messages only by their recipient.
+fn get_reported_agent(target: &ReportTarget, reporter: &AgentPubKey) -> ExternResult<(AgentPubKey, Option<ActionHash>)> {
+    let hash = match target {
+        ReportTarget::User(agent) => return Ok((agent.clone(), None)),
+        ReportTarget::Post(hash)
+        | ReportTarget::Product(hash)
+        | ReportTarget::Service(hash)
+        | ReportTarget::Message(hash)
+        | ReportTarget::Wish(hash)
+        | ReportTarget::TamagochiVisit(hash) => hash,
+    };
+    
+    let record = get(hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Reported content not found".into())))?;
+    
+    // The hash must be the kind of content the report claims it is
+    let entry = record.entry();
+    let matches_kind = match target {
+        ReportTarget::User(_) => false,
+        ReportTarget::Post(_) => entry.to_app_option::<Post>().ok().flatten().is_some(),
+        ReportTarget::Product(_) => entry.to_app_option::<Product>().ok().flatten().is_some(),
+        ReportTarget::Service(_) => entry.to_app_option::<Service>().ok().flatten().is_some(),
+        ReportTarget::Message(_) => entry.to_app_option::<Message>().ok().flatten().is_some(),
+        ReportTarget::Wish(_) => entry.to_app_option::<Wish>().ok().flatten().is_some(),
+        ReportTarget::TamagochiVisit(_) => entry.to_app_option::<TamagochiVisit>().ok().flatten().is_some(),
     };
+    if !matches_kind {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!("Reported content is not a {}", target.kind()))));
+    }
     
-    let wish_hash = create_entry(EntryTypes::Wish(wish.clone()))?;
+    if let ReportTarget::Message(_) = target {
+        let message = record.entry().to_app_option::<Message>()?
+            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Message not found".into())))?;
+        if message.receiver_id != *reporter {
+            return Err(wasm_error!(WasmErrorInner::Guest("Only the recipient can report a message".into())));
+        }
+    }
     
-    // Link user to wish
-    create_link(
-        author_id,
-        wish_hash,
-        LinkTypes::UserToWishes,
-        (),
-    )?;
+    Ok((record.action().author().clone(), Some(record.action_address().clone())))
+}
+
+fn get_verification_item(request_hash: ActionHash) -> ExternResult<VerificationRequestItem> {
+    let record = get(request_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Verification request not found".into())))?;
+    let request = record.entry().to_app_option::<VerificationRequest>()?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Verification request not found".into())))?;
+    let attestation = get_verification_attestation(&request_hash)?.map(|(_, a)| a);
     
-    Ok(wish_hash)
+    Ok(VerificationRequestItem { request_hash, request, attestation })
 }
 
-#[hdk_extern]
-pub fn get_wishes(_: ()) -> ExternResult<Vec<Wish>> {
-    // Get all wishes (simplified - in production, use proper query/index)
-    // For now, return empty - will need proper wish index in production
-    let mut wishes = Vec::new();
-    Ok(wishes)
+// Latest attestation on a request (two verifiers may race; the newest wins)
+fn get_verification_attestation(request_hash: &ActionHash) -> ExternResult<Option<(ActionHash, VerificationAttestation)>> {
+    let links = get_links(request_hash.clone(), LinkTypes::VerificationRequestToAttestations, None)?;
+    let mut latest: Option<(ActionHash, VerificationAttestation)> = None;
+    
+    for link in links {
+        let attestation_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let record = get(attestation_hash.clone(), GetOptions::default())?;
+        if let Some(record) = record {
+            // Links to anything else (or to another request's attestation) are skipped
+            if let Some(attestation) = record.entry().to_app_option::<VerificationAttestation>().ok().flatten() {
+                if attestation.request_hash != *request_hash {
+                    continue;
+                }
+                if latest.as_ref().map_or(true, |(_, l)| attestation.attested_at > l.attested_at) {
+                    latest = Some((attestation_hash, attestation));
+                }
+            }
+        }
+    }
+    
+    Ok(latest)
 }
 
-#[hdk_extern]
-pub fn get_user_wishes(user_id: AgentPubKey) -> ExternResult<Vec<Wish>> {
-    let links = get_links(user_id, LinkTypes::UserToWishes, None)?;
-    let mut wishes = Vec::new();
+// Unexpired, unrevoked role grants held by `agent`, with the grant's action hash
+fn get_active_role_grants(agent: &AgentPubKey) -> ExternResult<Vec<(ActionHash, RoleGrant)>> {
+    let now = sys_time()?.as_seconds_since_epoch();
+    let links = get_links(agent.clone(), LinkTypes::AgentToRoleGrants, None)?;
+    let mut grants = Vec::new();
     
     for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?
-            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish not found".into())))?;
-        
-        if let Some(Entry::App(entry_bytes)) = element.entry() {
-            let wish: Wish = entry_bytes.try_into()?;
-            wishes.push(wish);
+        let grant_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let record = get(grant_hash.clone(), GetOptions::default())?;
+        if let Some(record) = record {
+            if let Some(grant) = record.entry().to_app_option::<RoleGrant>().ok().flatten() {
+                if grant.grantee == *agent && now <= grant.expires_at && !is_grant_revoked(&grant_hash)? {
+                    grants.push((grant_hash, grant));
+                }
+            }
         }
     }
     
-    // Sort by created_at desc
-    wishes.sort_by_key(|w| std::cmp::Reverse(w.created_at));
-    Ok(wishes)
+    Ok(grants)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct HelpWishInput {
-    pub wish_id: EntryHash,
-    pub message: Option<String>,
+// Only a signed revocation of this very grant counts; stray links are ignored
+fn is_grant_revoked(grant_hash: &ActionHash) -> ExternResult<bool> {
+    let links = get_links(grant_hash.clone(), LinkTypes::RoleGrantToRevocations, None)?;
+    for link in links {
+        let revocation_hash = match link.target.into_action_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let record = get(revocation_hash, GetOptions::default())?;
+        if let Some(record) = record {
+            if let Some(revocation) = record.entry().to_app_option::<RoleRevocation>().ok().flatten() {
+                if revocation.grant_hash == *grant_hash && revocation.revoked_by == link.author {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
 }
 
-#[hdk_extern]
-pub fn help_wish(input: HelpWishInput) -> ExternResult<EntryHash> {
-    let helper_id = agent_info()?.agent_latest_pubkey();
+// Caller with `role` (or Admin), plus the grant to cite as authority; the progenitor needs none
+fn ensure_role(role: Role) -> ExternResult<(AgentPubKey, Option<ActionHash>)> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let progenitor = dna_properties()?.progenitor.ok_or(wasm_error!(WasmErrorInner::Guest(
+        "Privileged actions are disabled until a progenitor is set".into()
+    )))?;
+    if progenitor == agent {
+        return Ok((agent, None));
+    }
     
-    // Verify wish exists
-    let element = get(input.wish_id.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish not found".into())))?;
+    let grants = get_active_role_grants(&agent)?;
+    let authority = grants
+        .iter()
+        .find(|(_, g)| g.role == role)
+        .or_else(|| grants.iter().find(|(_, g)| g.role == Role::Admin))
+        .map(|(hash, _)| hash.clone());
     
-    let wish: Wish = element.entry()
-        .to_app_option()?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish entry not found".into())))?;
+    match authority {
+        Some(hash) => Ok((agent, Some(hash))),
+        None => Err(wasm_error!(WasmErrorInner::Guest(format!("Requires the {:?} role", role)))),
+    }
+}
+
+// Anchor for posts tagged with `tag` (already normalized)
+fn hashtag_path(tag: &str) -> Path {
+    Path::from(format!("{}.{}", HASHTAG_ROOT, tag))
+}
+
+// Hourly anchor counting hashtag uses, for trending
+fn hashtag_usage_path(hour: i64) -> Path {
+    Path::from(format!("{}.{}", HASHTAG_USAGE_ROOT, hour))
+}
+
+// Lowercases and strips a leading '#'; None if empty, too long or not letters/digits/_
+fn normalize_hashtag(raw: &str) -> Option<String> {
+    let tag = raw.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() || tag.chars().count() > MAX_HASHTAG_LEN {
+        return None;
+    }
+    if !tag.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(tag)
+}
+
+// Distinct hashtags in `text`, e.g. "#Santiago #santiago!" -> ["santiago"]
+fn extract_hashtags(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(rest) = word.strip_prefix('#') {
+            let raw: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+            if let Some(tag) = normalize_hashtag(&raw) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+    }
+    tags
+}
+
+// Distinct agents mentioned as `@<agent pub key>` in `text`
+fn extract_mentions(text: &str) -> Vec<AgentPubKey> {
+    let mut mentions = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(rest) = word.strip_prefix('@') {
+            let raw: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_').collect();
+            if let Ok(agent) = AgentPubKeyB64::from_b64_str(&raw) {
+                let agent = AgentPubKey::from(agent);
+                if !mentions.contains(&agent) {
+                    mentions.push(agent);
+                }
+            }
+        }
+    }
+    mentions
+}
+
+fn index_hashtags(post_hash: &EntryHash, tags: &[String], at: i64) -> ExternResult<()> {
+    let usage_base = hashtag_usage_path(at.div_euclid(SECONDS_PER_HOUR)).path_entry_hash()?;
+    for tag in tags {
+        create_link(
+            hashtag_path(tag).path_entry_hash()?,
+            post_hash.clone(),
+            LinkTypes::HashtagToPosts,
+            (),
+        )?;
+        create_link(
+            usage_base.clone(),
+            post_hash.clone(),
+            LinkTypes::HashtagUsage,
+            LinkTag::new(tag.as_bytes().to_vec()),
+        )?;
+    }
+    Ok(())
+}
+
+// Removes the post from `tags` and from their trending counts; `author` limits it to that agent's links
+fn unindex_hashtags(post_hash: &EntryHash, tags: &[String], author: Option<&AgentPubKey>) -> ExternResult<()> {
+    let target: AnyLinkableHash = post_hash.clone().into();
     
-    if wish.fulfilled {
-        return Err(wasm_error!(WasmErrorInner::Guest("Wish already fulfilled".into())));
+    for tag in tags {
+        let tag_bytes = tag.as_bytes().to_vec();
+        for link in get_links(hashtag_path(tag).path_entry_hash()?, LinkTypes::HashtagToPosts, None)? {
+            if link.target != target || author.map_or(false, |a| link.author != *a) {
+                continue;
+            }
+            
+            // The usage link was written in the same call: this hour or, across the boundary, the one before
+            let hour = link.timestamp.as_seconds_since_epoch().div_euclid(SECONDS_PER_HOUR);
+            for usage_hour in [hour - 1, hour] {
+                let usage_base = hashtag_usage_path(usage_hour).path_entry_hash()?;
+                for usage in get_links(usage_base, LinkTypes::HashtagUsage, Some(LinkTag::new(tag_bytes.clone())))? {
+                    if usage.target == target && usage.author == link.author && usage.tag.0 == tag_bytes {
+                        delete_link(usage.create_link_hash)?;
+                    }
+                }
+            }
+            delete_link(link.create_link_hash)?;
+        }
+    }
+    Ok(())
+}
+
+// Links each mentioned agent to the post or comment; `kind` goes in the tag
+fn index_mentions(target_hash: &EntryHash, mentions: &[AgentPubKey], kind: &str) -> ExternResult<()> {
+    for agent in mentions {
+        create_link(
+            agent.clone(),
+            target_hash.clone(),
+            LinkTypes::AgentToMentions,
+            LinkTag::new(kind.as_bytes().to_vec()),
+        )?;
     }
+    Ok(())
+}
+
+fn unindex_mentions(target_hash: &EntryHash, mentions: &[AgentPubKey]) -> ExternResult<()> {
+    let target: AnyLinkableHash = target_hash.clone().into();
+    for agent in mentions {
+        for link in get_links(agent.clone(), LinkTypes::AgentToMentions, None)? {
+            if link.target == target {
+                delete_link(link.create_link_hash)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Whether posts by `author` must be hidden from `agent`, caching per author
+fn is_author_hidden(
+    author: &AgentPubKey,
+    agent: &AgentPubKey,
+    my_blocked: &[AgentPubKey],
+    cache: &mut HashMap<AgentPubKey, bool>,
+) -> ExternResult<bool> {
+    if let Some(hidden) = cache.get(author) {
+        return Ok(*hidden);
+    }
+    let hidden = my_blocked.contains(author) || has_blocked(author, agent)?;
+    cache.insert(author.clone(), hidden);
+    Ok(hidden)
+}
+
+// Checks the blocker's UserToBlocks links for a block targeting `blocked`
+fn has_blocked(blocker: &AgentPubKey, blocked: &AgentPubKey) -> ExternResult<bool> {
+    let links = get_links(blocker.clone(), LinkTypes::UserToBlocks, None)?;
     
-    // Check if already helping
-    let existing_links = get_links(input.wish_id.clone(), LinkTypes::WishToHelpers, None)?;
-    for link in existing_links {
+    for link in links {
         let element = get(link.target, GetOptions::default())?;
         if let Some(element) = element {
             if let Some(Entry::App(entry_bytes)) = element.entry() {
-                if let Ok(help_entry) = entry_bytes.try_into::<WishHelp>() {
-                    if help_entry.helper_id == helper_id && (help_entry.status == "pending" || help_entry.status == "accepted") {
-                        return Err(wasm_error!(WasmErrorInner::Guest("Already helping this wish".into())));
+                match EntryTypes::try_from(entry_bytes.clone()) {
+                    Ok(EntryTypes::UserBlock(block)) => {
+                        if block.blocked_id == *blocked {
+                            return Ok(true);
+                        }
                     }
+                    _ => {}
                 }
             }
         }
     }
     
-    let wish_help = WishHelp {
-        wish_id: input.wish_id.clone(),
-        helper_id,
-        message: input.message,
-        status: "pending".to_string(),
-        created_at: sys_time()?.as_seconds_since_epoch(),
+    Ok(false)
+}
+
+// Fails if `target` has blocked `actor` (receiver-side block)
+fn ensure_not_blocked_by(target: &AgentPubKey, actor: &AgentPubKey) -> ExternResult<()> {
+    if target != actor && has_blocked(target, actor)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("This user has blocked you".into())));
+    }
+    Ok(())
+}
+
+// Deleted posts keep their interaction links; they just stop resolving
+fn is_orphaned_post(post_hash: &EntryHash) -> ExternResult<bool> {
+    Ok(get(post_hash.clone(), GetOptions::default())?.is_none())
+}
+
+fn get_post_by_hash(post_hash: &EntryHash) -> ExternResult<Post> {
+    get_latest_post(post_hash)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Post not found".into())))
+}
+
+// The owner's living tamagochi (original hash + newest version)
+fn get_current_tamagochi(owner_id: &AgentPubKey) -> ExternResult<Option<(EntryHash, Tamagochi)>> {
+    let links = get_links(owner_id.clone(), LinkTypes::UserToTamagochi, None)?;
+    
+    for link in links {
+        let tamagochi_hash = match link.target.into_entry_hash() {
+            Some(hash) => hash,
+            None => continue,
+        };
+        if let Some(tamagochi) = get_latest_tamagochi(&tamagochi_hash)? {
+            if tamagochi.is_alive {
+                return Ok(Some((tamagochi_hash, tamagochi)));
+            }
+        }
+    }
+    
+    Ok(None)
+}
+
+// Newest version on the owner's own chain, without going to the network
+fn get_own_tamagochi_version(tamagochi_hash: &EntryHash) -> ExternResult<Option<ActionHash>> {
+    let filter = ChainQueryFilter::new().entry_type(UnitEntryTypes::Tamagochi.try_into()?);
+    Ok(query(filter)?
+        .iter()
+        .rev()
+        .find(|record| integrity::is_pet_version(record.action(), tamagochi_hash))
+        .map(|record| record.action_address().clone()))
+}
+
+fn get_latest_tamagochi(tamagochi_hash: &EntryHash) -> ExternResult<Option<Tamagochi>> {
+    Ok(get_latest_tamagochi_version(tamagochi_hash)?.map(|(_, tamagochi)| tamagochi))
+}
+
+// Newest version and the action that wrote it; only the owner's own updates count
+fn get_latest_tamagochi_version(tamagochi_hash: &EntryHash) -> ExternResult<Option<(ActionHash, Tamagochi)>> {
+    let element = match get(tamagochi_hash.clone(), GetOptions::default())? {
+        Some(element) => element,
+        None => return Ok(None),
+    };
+    let original = match element.entry().to_app_option::<Tamagochi>()? {
+        Some(tamagochi) => tamagochi,
+        None => return Ok(None),
     };
     
-    let help_hash = create_entry(EntryTypes::WishHelp(wish_help.clone()))?;
+    let updates = get_links(tamagochi_hash.clone(), LinkTypes::TamagochiUpdates, None)?;
+    if let Some(latest) = updates.into_iter().filter(|l| l.author == original.owner_id).max_by_key(|l| l.timestamp) {
+        if let Some(updated) = get(latest.target, GetOptions::default())? {
+            if let Some(tamagochi) = updated.entry().to_app_option::<Tamagochi>()? {
+                if tamagochi.owner_id == original.owner_id {
+                    return Ok(Some((updated.action_address().clone(), tamagochi)));
+                }
+            }
+        }
+    }
+    
+    Ok(Some((element.action_address().clone(), original)))
+}
+
+// Original hash stays stable for visits and links; readers follow TamagochiUpdates.
+// Each version cites the one before it so validation can compare them
+fn save_tamagochi(tamagochi_hash: &EntryHash, tamagochi: &mut Tamagochi) -> ExternResult<ActionHash> {
+    let record = get(tamagochi_hash.clone(), GetOptions::default())?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
+    tamagochi.previous = get_own_tamagochi_version(tamagochi_hash)?;
+    let version = update_entry(record.action_address().clone(), EntryTypes::Tamagochi(tamagochi.clone()))?;
     
-    // Link wish to helper
     create_link(
-        input.wish_id,
-        help_hash,
-        LinkTypes::WishToHelpers,
+        tamagochi_hash.clone(),
+        hash_entry(&EntryTypes::Tamagochi(tamagochi.clone()))?,
+        LinkTypes::TamagochiUpdates,
         (),
     )?;
+    Ok(version)
+}
+
+// Simulates the owner's pet up to now plus `events`, persists it and records a death if it happened
+fn run_tamagochi(events: &[tamagochi::Event]) -> ExternResult<Tamagochi> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
     
-    Ok(help_hash)
+    let (tamagochi_hash, mut tamagochi) = get_current_tamagochi(&owner_id)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Tamagochi not found".into())))?;
+    
+    let outcome = tamagochi::simulate(&pet_state(&tamagochi), events, now);
+    apply_pet_state(&mut tamagochi, outcome.state);
+    let version = save_tamagochi(&tamagochi_hash, &mut tamagochi)?;
+    
+    if outcome.evolved && tamagochi.stage == tamagochi::Stage::Adult.as_str() {
+        award_achievements(AchievementEvidence::AdultTamagochi)?;
+    }
+    
+    // Se devuelve Ok para que la muerte quede registrada en la cadena
+    if let Some(death) = outcome.death {
+        record_tamagochi_death(&tamagochi, death.cause.as_str(), death.at)?;
+        withdraw_leaderboard_snapshots(&owner_id)?;
+    } else {
+        refresh_leaderboard_snapshot(&tamagochi_hash, &version, &tamagochi, false)?;
+    }
+    
+    Ok(tamagochi)
 }
 
-#[hdk_extern]
-pub fn get_wish_helpers(wish_id: EntryHash) -> ExternResult<Vec<WishHelp>> {
-    let links = get_links(wish_id, LinkTypes::WishToHelpers, None)?;
-    let mut helpers = Vec::new();
+fn get_latest_tamagochi_death(death_hash: &EntryHash) -> ExternResult<Option<TamagochiDeath>> {
+    let element = match get(death_hash.clone(), GetOptions::default())? {
+        Some(element) => element,
+        None => return Ok(None),
+    };
     
-    for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?
-            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("WishHelp not found".into())))?;
-        
-        if let Some(Entry::App(entry_bytes)) = element.entry() {
-            let help: WishHelp = entry_bytes.try_into()?;
-            helpers.push(help);
+    let original = match element.entry().to_app_option::<TamagochiDeath>()? {
+        Some(death) => death,
+        None => return Ok(None),
+    };
+    
+    // Only the owner's own edits count
+    let updates = get_links(death_hash.clone(), LinkTypes::TamagochiDeathUpdates, None)?;
+    if let Some(latest) = updates.into_iter().filter(|l| l.author == original.owner_id).max_by_key(|l| l.timestamp) {
+        if let Some(updated) = get(latest.target, GetOptions::default())? {
+            if let Some(death) = updated.entry().to_app_option::<TamagochiDeath>().ok().flatten() {
+                if death.owner_id == original.owner_id {
+                    return Ok(Some(death));
+                }
+            }
         }
     }
     
-    helpers.sort_by_key(|h| std::cmp::Reverse(h.created_at));
-    Ok(helpers)
+    Ok(Some(original))
 }
 
-#[hdk_extern]
-pub fn mark_wish_fulfilled(wish_id: EntryHash) -> ExternResult<Wish> {
-    let agent = agent_info()?.agent_latest_pubkey();
+// Original hash (None until the first save), newest version's action and newest version; empty by default
+fn get_my_inventory(owner_id: &AgentPubKey) -> ExternResult<(Option<EntryHash>, Option<ActionHash>, TamagochiInventory)> {
+    let links = get_links(owner_id.clone(), LinkTypes::UserToInventory, None)?;
     
-    let element = get(wish_id.clone(), GetOptions::default())?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish not found".into())))?;
+    if let Some(link) = links.into_iter().filter(|l| l.author == *owner_id).min_by_key(|l| l.timestamp) {
+        if let Some(inventory_hash) = link.target.into_entry_hash() {
+            let updates = get_links(inventory_hash.clone(), LinkTypes::InventoryUpdates, None)?;
+            let latest_hash = match updates.into_iter().filter(|l| l.author == *owner_id).max_by_key(|l| l.timestamp) {
+                Some(latest) => latest.target,
+                None => inventory_hash.clone().into(),
+            };
+            if let Some(element) = get(latest_hash, GetOptions::default())? {
+                if let Some(inventory) = element.entry().to_app_option::<TamagochiInventory>()? {
+                    return Ok((Some(inventory_hash), Some(element.action_address().clone()), inventory));
+                }
+            }
+        }
+    }
     
-    let mut wish: Wish = element.entry()
-        .to_app_option()?
-        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Wish entry not found".into())))?;
+    Ok((None, None, TamagochiInventory::empty(owner_id.clone())))
+}
+
+// Applies `change` to the caller's newest inventory version and saves it; validation replays the same step
+fn change_inventory(change: InventoryChange) -> ExternResult<TamagochiInventory> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let now = sys_time()?.as_seconds_since_epoch();
+    let (inventory_hash, latest_action, previous) = get_my_inventory(&owner_id)?;
     
-    if wish.author_id != agent {
-        return Err(wasm_error!(WasmErrorInner::Guest("Only wish author can mark as fulfilled".into())));
+    let evidence_at = match &change {
+        InventoryChange::Reward { evidence, .. } => {
+            let record = get(evidence.clone(), GetOptions::default())?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Reward evidence not found".into())))?;
+            Some(record.action().timestamp().as_seconds_since_epoch())
+        }
+        _ => None,
+    };
+    let mut inventory = next_inventory(&previous, &change, now.div_euclid(SECONDS_PER_DAY), evidence_at)
+        .map_err(|e| wasm_error!(WasmErrorInner::Guest(e)))?;
+    inventory.previous = latest_action;
+    inventory.updated_at = now;
+    
+    match inventory_hash {
+        None => {
+            let hash = create_entry(EntryTypes::TamagochiInventory(inventory.clone()))?;
+            create_link(
+                inventory.owner_id.clone(),
+                hash,
+                LinkTypes::UserToInventory,
+                (),
+            )?;
+        }
+        Some(hash) => {
+            let record = get(hash.clone(), GetOptions::default())?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Inventory not found".into())))?;
+            update_entry(record.action_address().clone(), EntryTypes::TamagochiInventory(inventory.clone()))?;
+            create_link(
+                hash,
+                hash_entry(&EntryTypes::TamagochiInventory(inventory.clone()))?,
+                LinkTypes::InventoryUpdates,
+                (),
+            )?;
+        }
     }
+    Ok(inventory)
+}
+
+// Credits the caller's inventory for `evidence`; past the daily cap the activity goes unrewarded
+fn reward_activity(activity: tamagochi::Activity, evidence: ActionHash) -> ExternResult<()> {
+    let owner_id = agent_info()?.agent_latest_pubkey();
+    let today = sys_time()?.as_seconds_since_epoch().div_euclid(SECONDS_PER_DAY);
     
-    wish.fulfilled = true;
-    let _updated_hash = create_entry(EntryTypes::Wish(wish.clone()))?;
+    let (_, _, inventory) = get_my_inventory(&owner_id)?;
+    if inventory.rewards_day == today && inventory.rewards_today >= MAX_DAILY_REWARDS {
+        return Ok(());
+    }
     
-    Ok(wish)
+    change_inventory(InventoryChange::Reward { activity: activity.as_str().to_string(), evidence })?;
+    Ok(())
 }
 
-// ========== Tamagochi Visits ==========
+fn get_leaderboard_snapshot(hash: AnyLinkableHash) -> ExternResult<Option<LeaderboardSnapshot>> {
+    match get(hash, GetOptions::default())? {
+        Some(element) => Ok(element.entry().to_app_option::<LeaderboardSnapshot>()?),
+        None => Ok(None),
+    }
+}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct VisitTamagochiInput {
-    pub owner_id: AgentPubKey,
-    pub message: Option<String>,
+fn get_latest_leaderboard_snapshot(owner_id: &AgentPubKey) -> ExternResult<Option<LeaderboardSnapshot>> {
+    let links = get_links(owner_id.clone(), LinkTypes::UserToLeaderboardSnapshots, None)?;
+    match links.into_iter().max_by_key(|l| l.timestamp) {
+        Some(latest) => get_leaderboard_snapshot(latest.target),
+        None => Ok(None),
+    }
+}
+
+// Today's and yesterday's buckets, newest snapshot per owner
+fn get_bucket_leaderboard_snapshots(now: i64) -> ExternResult<Vec<LeaderboardSnapshot>> {
+    let today = now.div_euclid(SECONDS_PER_DAY);
+    let mut latest: HashMap<LinkTag, Link> = HashMap::new();
+    
+    for day in [today - 1, today] {
+        for link in get_links(leaderboard_bucket_path(day)?, LinkTypes::LeaderboardSnapshots, None)? {
+            match latest.get(&link.tag) {
+                Some(existing) if existing.timestamp >= link.timestamp => {}
+                _ => {
+                    latest.insert(link.tag.clone(), link);
+                }
+            }
+        }
+    }
+    
+    let mut snapshots = Vec::new();
+    for link in latest.into_values() {
+        if let Some(snapshot) = get_leaderboard_snapshot(link.target)? {
+            snapshots.push(snapshot);
+        }
+    }
+    Ok(snapshots)
 }
 
-#[hdk_extern]
-pub fn visit_tamagochi(input: VisitTamagochiInput) -> ExternResult<EntryHash> {
-    let visitor_id = agent_info()?.agent_latest_pubkey();
+// Publishes at most every LEADERBOARD_SNAPSHOT_SECONDS unless forced; opted-out owners are withdrawn instead
+fn refresh_leaderboard_snapshot(tamagochi_hash: &EntryHash, version: &ActionHash, tamagochi: &Tamagochi, force: bool) -> ExternResult<Option<EntryHash>> {
+    let owner_id = tamagochi.owner_id.clone();
+    let now = sys_time()?.as_seconds_since_epoch();
     
-    // Verify owner has tamagochi
-    let owner_tamagochi = get_tamagochi_for_user_internal(input.owner_id.clone())?;
-    if owner_tamagochi.is_none() {
-        return Err(wasm_error!(WasmErrorInner::Guest("User doesn't have a tamagochi".into())));
+    let prefs = get_preferences_of(&owner_id)?.1;
+    if !prefs.tamagochi_enabled {
+        withdraw_leaderboard_snapshots(&owner_id)?;
+        return Ok(None);
     }
     
-    let visit = TamagochiVisit {
-        visitor_id,
-        tamagochi_owner_id: input.owner_id.clone(),
-        message: input.message,
-        visited_at: sys_time()?.as_seconds_since_epoch(),
+    if !force {
+        if let Some(previous) = get_latest_leaderboard_snapshot(&owner_id)? {
+            if previous.tamagochi_hash == *tamagochi_hash
+                && now - previous.taken_at < LEADERBOARD_SNAPSHOT_SECONDS
+            {
+                return Ok(None);
+            }
+        }
+    }
+    
+    // Ubicación aproximada (~1 km) y solo si el dueño la comparte
+    let sharing = prefs.location_sharing_enabled;
+    let (lat, lon) = match get_user_by_agent(&owner_id)? {
+        Some(User { lat: Some(lat), lon: Some(lon), .. }) if sharing => {
+            (Some((lat * 100.0).round() / 100.0), Some((lon * 100.0).round() / 100.0))
+        }
+        _ => (None, None),
     };
     
-    let visit_hash = create_entry(EntryTypes::TamagochiVisit(visit.clone()))?;
+    let snapshot = LeaderboardSnapshot {
+        owner_id: owner_id.clone(),
+        tamagochi_hash: tamagochi_hash.clone(),
+        tamagochi_version: version.clone(),
+        tamagochi_name: tamagochi.name.clone(),
+        stage: tamagochi.stage.clone(),
+        level: tamagochi.level,
+        experience: tamagochi.experience,
+        born_at: tamagochi.born_at,
+        care_streak: tamagochi.care_streak,
+        last_care_day: tamagochi.last_care_day,
+        lat,
+        lon,
+        taken_at: now,
+    };
+    let snapshot_hash = create_entry(EntryTypes::LeaderboardSnapshot(snapshot))?;
     
-    // Link visitor to visit
+    withdraw_leaderboard_snapshots(&owner_id)?;
     create_link(
-        visitor_id,
-        visit_hash,
-        LinkTypes::UserToTamagochiVisits,
-        (),
+        leaderboard_bucket_path(now.div_euclid(SECONDS_PER_DAY))?,
+        snapshot_hash.clone(),
+        LinkTypes::LeaderboardSnapshots,
+        LinkTag::new(owner_id.get_raw_39().to_vec()),
     )?;
-    
-    // Link tamagochi owner to visit
     create_link(
-        input.owner_id,
-        visit_hash,
-        LinkTypes::TamagochiToVisits,
+        owner_id,
+        snapshot_hash.clone(),
+        LinkTypes::UserToLeaderboardSnapshots,
         (),
     )?;
     
-    Ok(visit_hash)
+    Ok(Some(snapshot_hash))
 }
 
-#[hdk_extern]
-pub fn get_tamagochi_visits(owner_id: AgentPubKey) -> ExternResult<Vec<TamagochiVisit>> {
-    let links = get_links(owner_id, LinkTypes::TamagochiToVisits, None)?;
-    let mut visits = Vec::new();
+// Removes the owner's snapshot links so the pet drops off every board
+fn withdraw_leaderboard_snapshots(owner_id: &AgentPubKey) -> ExternResult<()> {
+    let today = sys_time()?.as_seconds_since_epoch().div_euclid(SECONDS_PER_DAY);
+    let tag = LinkTag::new(owner_id.get_raw_39().to_vec());
     
-    for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?
-            .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Visit not found".into())))?;
-        
-        if let Some(Entry::App(entry_bytes)) = element.entry() {
-            let visit: TamagochiVisit = entry_bytes.try_into()?;
-            visits.push(visit);
+    for day in [today - 1, today] {
+        for link in get_links(leaderboard_bucket_path(day)?, LinkTypes::LeaderboardSnapshots, Some(tag.clone()))? {
+            delete_link(link.create_link_hash)?;
+        }
+    }
+    for link in get_links(owner_id.clone(), LinkTypes::UserToLeaderboardSnapshots, None)? {
+        delete_link(link.create_link_hash)?;
+    }
+    Ok(())
+}
+
+// Owner's living pet, adult in its stored version (the proposal cites it), simulated to now
+// and off breeding cooldown
+fn get_breedable_tamagochi(owner_id: &AgentPubKey, now: i64, whose: &str) -> ExternResult<(EntryHash, ActionHash, Tamagochi)> {
+    let (tamagochi_hash, _) = get_current_tamagochi(owner_id)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest(format!("{} tamagochi must be alive to breed", whose))))?;
+    let (version, mut tamagochi) = get_latest_tamagochi_version(&tamagochi_hash)?
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest(format!("{} tamagochi must be alive to breed", whose))))?;
+    
+    let adult = tamagochi.stage == tamagochi::Stage::Adult.as_str();
+    let outcome = tamagochi::simulate(&pet_state(&tamagochi), &[], now);
+    apply_pet_state(&mut tamagochi, outcome.state);
+    if !tamagochi.is_alive || !adult {
+        return Err(wasm_error!(WasmErrorInner::Guest(format!("{} tamagochi must be an adult to breed", whose))));
+    }
+    
+    let breedings = get_links(tamagochi_hash.clone(), LinkTypes::TamagochiToBreedings, None)?;
+    if let Some(last) = breedings.iter().map(|l| l.timestamp.as_seconds_since_epoch()).max() {
+        if now - last < BREEDING_COOLDOWN_SECONDS {
+            return Err(wasm_error!(WasmErrorInner::Guest(format!("{} tamagochi needs to rest before breeding again", whose))));
         }
     }
     
-    visits.sort_by_key(|v| std::cmp::Reverse(v.visited_at));
-    Ok(visits)
+    Ok((tamagochi_hash, version, tamagochi))
 }
 
-#[hdk_extern]
-pub fn get_tamagochi_for_user(user_id: AgentPubKey) -> ExternResult<Option<Tamagochi>> {
-    let links = get_links(user_id, LinkTypes::UserToTamagochi, None)?;
-    for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                if let Ok(tamagochi) = entry_bytes.try_into::<Tamagochi>() {
-                    return Ok(Some(tamagochi));
-                }
+// Same rule as the validation: a proposal or an accepted breeding on the caller's own
+// chain within the cooldown, even one the other side never answered
+fn is_resting(tamagochi_hash: &EntryHash, now: i64) -> ExternResult<bool> {
+    let filter = ChainQueryFilter::new()
+        .entry_type(UnitEntryTypes::BreedingProposal.try_into()?)
+        .entry_type(UnitEntryTypes::BreedingRecord.try_into()?)
+        .include_entries(true);
+    for record in query(filter)? {
+        if now - record.action().timestamp().as_seconds_since_epoch() >= BREEDING_COOLDOWN_SECONDS {
+            continue;
+        }
+        if let Some(proposal) = record.entry().to_app_option::<BreedingProposal>().ok().flatten() {
+            if proposal.proposer_tamagochi == *tamagochi_hash {
+                return Ok(true);
+            }
+        } else if let Some(breeding) = record.entry().to_app_option::<BreedingRecord>().ok().flatten() {
+            let proposal = get(breeding.proposal_hash, GetOptions::default())?
+                .and_then(|element| element.entry().to_app_option::<BreedingProposal>().ok().flatten());
+            if proposal.map_or(false, |p| p.partner_tamagochi == *tamagochi_hash) {
+                return Ok(true);
             }
         }
     }
-    Ok(None)
+    Ok(false)
 }
 
-fn get_tamagochi_for_user_internal(user_id: AgentPubKey) -> ExternResult<Option<Tamagochi>> {
-    get_tamagochi_for_user(user_id)
+fn achievement_info(achievement: &Achievement) -> AchievementInfo {
+    AchievementInfo {
+        id: achievement.id.to_string(),
+        name: achievement.name.to_string(),
+        description: achievement.description.to_string(),
+        required: achievement.required,
+    }
 }
 
-// ========== Profile Cover ==========
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct SetProfileCoverInput {
-    pub cover_type: String, // "tamagochi", "image", "video"
-    pub image_hash: Option<String>,
-    pub video_hash: Option<String>,
+// Original hash (None until the first save) and newest version; defaults otherwise
+fn get_preferences_of(owner_id: &AgentPubKey) -> ExternResult<(Option<EntryHash>, UserPreferences)> {
+    let links = get_links(owner_id.clone(), LinkTypes::UserToPreferences, None)?;
+    
+    // Only links and entries written by the owner count
+    if let Some(link) = links.into_iter().filter(|l| l.author == *owner_id).min_by_key(|l| l.timestamp) {
+        if let Some(prefs_hash) = link.target.into_entry_hash() {
+            let updates = get_links(prefs_hash.clone(), LinkTypes::PreferencesUpdates, None)?;
+            let latest_hash = match updates.into_iter().filter(|l| l.author == *owner_id).max_by_key(|l| l.timestamp) {
+                Some(latest) => latest.target,
+                None => prefs_hash.clone().into(),
+            };
+            if let Some(element) = get(latest_hash, GetOptions::default())? {
+                if let Some(prefs) = element.entry().to_app_option::<UserPreferences>().ok().flatten() {
+                    if prefs.owner_id == *owner_id {
+                        return Ok((Some(prefs_hash), prefs));
+                    }
+                }
+            }
+        }
+    }
+    
+    Ok((None, UserPreferences {
+        owner_id: owner_id.clone(),
+        app_color: "#1f7aec".to_string(),
+        tamagochi_enabled: true,
+        location_sharing_enabled: false,
+        language: integrity::default_language(),
+        distance_unit: integrity::default_distance_unit(),
+        notifications: integrity::NotificationSettings::default(),
+        default_currency: integrity::default_currency(),
+        profile_visibility: integrity::default_profile_visibility(),
+        updated_at: 0,
+    }))
 }
 
-#[hdk_extern]
-pub fn set_profile_cover(input: SetProfileCoverInput) -> ExternResult<EntryHash> {
-    let owner_id = agent_info()?.agent_latest_pubkey();
+fn save_preferences(prefs_hash: Option<EntryHash>, prefs: &mut UserPreferences) -> ExternResult<()> {
+    prefs.updated_at = sys_time()?.as_seconds_since_epoch();
     
-    // Delete old cover if exists
-    let old_links = get_links(owner_id, LinkTypes::UserToProfileCover, None)?;
-    for link in old_links {
-        delete_link(link.create_link_hash)?;
+    match prefs_hash {
+        None => {
+            let hash = create_entry(EntryTypes::UserPreferences(prefs.clone()))?;
+            create_link(
+                prefs.owner_id.clone(),
+                hash,
+                LinkTypes::UserToPreferences,
+                (),
+            )?;
+        }
+        Some(hash) => {
+            let record = get(hash.clone(), GetOptions::default())?
+                .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Preferences not found".into())))?;
+            update_entry(record.action_address().clone(), EntryTypes::UserPreferences(prefs.clone()))?;
+            create_link(
+                hash,
+                hash_entry(&EntryTypes::UserPreferences(prefs.clone()))?,
+                LinkTypes::PreferencesUpdates,
+                (),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Unlocks whatever the caller's chain now proves for `evidence`; returns the new ids
+fn award_achievements(evidence: AchievementEvidence) -> ExternResult<Vec<String>> {
+    let agent = agent_info()?.agent_latest_pubkey();
+    let unlocked = get_links(agent.clone(), LinkTypes::AgentToAchievements, None)?;
+    let pending: Vec<&Achievement> = integrity::ACHIEVEMENTS
+        .iter()
+        .filter(|a| a.evidence == evidence)
+        .filter(|a| !unlocked.iter().any(|l| l.tag.0 == a.id.as_bytes()))
+        .collect();
+    if pending.is_empty() {
+        return Ok(Vec::new());
     }
     
-    let cover = ProfileCover {
-        owner_id,
-        cover_type: input.cover_type,
-        image_hash: input.image_hash,
-        video_hash: input.video_hash,
-        updated_at: sys_time()?.as_seconds_since_epoch(),
-    };
-    
-    let cover_hash = create_entry(EntryTypes::ProfileCover(cover.clone()))?;
+    // Cadena local: no hace falta ir a la red
+    let mut proof = Vec::new();
+    let mut counted = HashSet::new();
+    for record in query(evidence_filter(evidence)?)? {
+        if let Some(key) = integrity::achievement_evidence_key(evidence, &record)? {
+            if counted.insert(key) {
+                proof.push(record.action_address().clone());
+            }
+        }
+    }
     
-    // Link user to cover
-    create_link(
-        owner_id,
-        cover_hash,
-        LinkTypes::UserToProfileCover,
-        (),
-    )?;
+    let now = sys_time()?.as_seconds_since_epoch();
+    let mut awarded = Vec::new();
+    for achievement in pending {
+        if (proof.len() as u32) < achievement.required {
+            continue;
+        }
+        let unlock = AchievementUnlock {
+            agent_id: agent.clone(),
+            achievement_id: achievement.id.to_string(),
+            evidence: proof[..achievement.required as usize].to_vec(),
+            unlocked_at: now,
+        };
+        let unlock_hash = create_entry(EntryTypes::AchievementUnlock(unlock))?;
+        create_link(
+            agent.clone(),
+            unlock_hash,
+            LinkTypes::AgentToAchievements,
+            LinkTag::new(achievement.id.as_bytes().to_vec()),
+        )?;
+        awarded.push(achievement.id.to_string());
+    }
+    Ok(awarded)
+}
+
+// Only the kind of record that can prove `evidence`, not the whole chain
+fn evidence_filter(evidence: AchievementEvidence) -> ExternResult<ChainQueryFilter> {
+    let filter = ChainQueryFilter::new().include_entries(true);
+    Ok(match evidence {
+        AchievementEvidence::Post => filter.entry_type(UnitEntryTypes::Post.try_into()?),
+        AchievementEvidence::Clap => filter.entry_type(UnitEntryTypes::PostClap.try_into()?),
+        AchievementEvidence::WishHelp => filter.entry_type(UnitEntryTypes::WishHelp.try_into()?),
+        AchievementEvidence::Review => filter.entry_type(UnitEntryTypes::Review.try_into()?),
+        AchievementEvidence::RideReward => filter.action_type(ActionType::CreateLink),
+        AchievementEvidence::AdultTamagochi => filter.entry_type(UnitEntryTypes::Tamagochi.try_into()?),
+    })
+}
+
+fn geo_cell_path(lat: f64, lon: f64) -> ExternResult<EntryHash> {
+    let lat_cell = (lat / GEO_CELL_DEGREES).floor() as i64;
+    let lon_cell = (lon / GEO_CELL_DEGREES).floor() as i64;
+    Path::from(format!("{}.{}.{}", GEO_ROOT, lat_cell, lon_cell)).path_entry_hash()
+}
+
+fn geo_tag(lat: f64, lon: f64) -> LinkTag {
+    let mut bytes = lat.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&lon.to_le_bytes());
+    LinkTag::new(bytes)
+}
+
+fn decode_geo_tag(tag: &LinkTag) -> Option<(f64, f64)> {
+    let bytes = tag.as_ref();
+    if bytes.len() != 16 {
+        return None;
+    }
+    let lat = f64::from_le_bytes(bytes[..8].try_into().ok()?);
+    let lon = f64::from_le_bytes(bytes[8..].try_into().ok()?);
+    Some((lat, lon))
+}
+
+// Moves the agent to the cell of (lat, lon); one cell per agent
+fn index_location(agent: &AgentPubKey, lat: f64, lon: f64) -> ExternResult<()> {
+    unindex_location(agent)?;
     
-    Ok(cover_hash)
+    let cell = geo_cell_path(lat, lon)?;
+    create_link(cell.clone(), agent.clone(), LinkTypes::GeoCellToAgents, geo_tag(lat, lon))?;
+    create_link(agent.clone(), cell, LinkTypes::AgentToGeoCell, geo_tag(lat, lon))?;
+    Ok(())
 }
 
-#[hdk_extern]
-pub fn get_profile_cover(user_id: AgentPubKey) -> ExternResult<Option<ProfileCover>> {
-    let links = get_links(user_id, LinkTypes::UserToProfileCover, None)?;
-    for link in links {
-        let entry_hash = link.target;
-        let element = get(entry_hash, GetOptions::default())?;
-        if let Some(element) = element {
-            if let Some(Entry::App(entry_bytes)) = element.entry() {
-                if let Ok(cover) = entry_bytes.try_into::<ProfileCover>() {
-                    return Ok(Some(cover));
+fn unindex_location(agent: &AgentPubKey) -> ExternResult<()> {
+    for link in get_links(agent.clone(), LinkTypes::AgentToGeoCell, None)? {
+        if let Some(cell) = link.target.clone().into_entry_hash() {
+            for cell_link in get_links(cell, LinkTypes::GeoCellToAgents, None)? {
+                if cell_link.target == agent.clone().into() {
+                    delete_link(cell_link.create_link_hash)?;
                 }
             }
         }
+        delete_link(link.create_link_hash)?;
     }
-    Ok(None)
+    Ok(())
 }
 
-// ========== Helper Functions ==========
+fn get_indexed_location(agent: &AgentPubKey) -> ExternResult<Option<(f64, f64)>> {
+    let links = get_links(agent.clone(), LinkTypes::AgentToGeoCell, None)?;
+    Ok(links.into_iter().max_by_key(|l| l.timestamp).and_then(|l| decode_geo_tag(&l.tag)))
+}
+
+// Indexed agents within `radius_km`, closest first
+fn nearby_agents(lat: f64, lon: f64, radius_km: f64) -> ExternResult<Vec<(AgentPubKey, f64)>> {
+    let radius_km = radius_km.min(MAX_GEO_RADIUS_KM);
+    let lat_span = radius_km / 111.0;
+    let lon_span = radius_km / (111.0 * lat.to_radians().cos().max(0.1));
+    let lat_cells = (lat_span / GEO_CELL_DEGREES).ceil() as i64;
+    let lon_cells = ((lon_span / GEO_CELL_DEGREES).ceil() as i64).min(8);
+    
+    let mut found: Vec<(AgentPubKey, f64)> = Vec::new();
+    for i in -lat_cells..=lat_cells {
+        for j in -lon_cells..=lon_cells {
+            let cell = geo_cell_path(lat + i as f64 * GEO_CELL_DEGREES, lon + j as f64 * GEO_CELL_DEGREES)?;
+            for link in get_links(cell, LinkTypes::GeoCellToAgents, None)? {
+                let (agent_lat, agent_lon) = match decode_geo_tag(&link.tag) {
+                    Some(coords) => coords,
+                    None => continue,
+                };
+                let agent = match link.target.into_agent_pub_key() {
+                    Some(agent) => agent,
+                    None => continue,
+                };
+                let km = calculate_distance(lat, lon, agent_lat, agent_lon);
+                if km <= radius_km && !found.iter().any(|(a, _)| *a == agent) {
+                    found.push((agent, km));
+                }
+            }
+        }
+    }
+    
+    found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(found)
+}
 
-fn hash_email(email: &str) -> ExternResult<String> {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(email.as_bytes());
-    Ok(hex::encode(hasher.finalize()))
+// Everyone the agent chatted with, clapped, got claps from or helped/was helped on a wish;
+// one entry per interaction, looking only at the latest MAX_INTERACTION_SCAN of each kind
+fn get_interaction_partners(agent: &AgentPubKey) -> ExternResult<Vec<AgentPubKey>> {
+    let mut partners = get_chats(())?;
+    
+    // Aplausos y ayudas recibidas en mis posts y deseos más recientes
+    let mut posts = get_links(agent.clone(), LinkTypes::UserToPosts, None)?;
+    posts.sort_by_key(|l| std::cmp::Reverse(l.timestamp));
+    for link in posts.into_iter().take(MAX_INTERACTION_SCAN) {
+        if let Some(post_hash) = link.target.into_entry_hash() {
+            for clap in get_links(post_hash, LinkTypes::PostToClaps, None)? {
+                partners.push(clap.author);
+            }
+        }
+    }
+    let mut wishes = get_links(agent.clone(), LinkTypes::UserToWishes, None)?;
+    wishes.sort_by_key(|l| std::cmp::Reverse(l.timestamp));
+    for link in wishes.into_iter().take(MAX_INTERACTION_SCAN) {
+        if let Some(wish_hash) = link.target.into_entry_hash() {
+            for help in get_links(wish_hash, LinkTypes::WishToHelpers, None)? {
+                partners.push(help.author);
+            }
+        }
+    }
+    
+    // Aplausos y ayudas dadas: están en la cadena local; las más recientes primero
+    let filter = ChainQueryFilter::new()
+        .entry_type(UnitEntryTypes::PostClap.try_into()?)
+        .entry_type(UnitEntryTypes::WishHelp.try_into()?)
+        .action_type(ActionType::Create)
+        .include_entries(true);
+    for record in query(filter)?.into_iter().rev().take(MAX_INTERACTION_SCAN) {
+        if let Some(clap) = record.entry().to_app_option::<PostClap>().ok().flatten() {
+            if let Some(post) = get_latest_post(&clap.post_hash)? {
+                partners.push(post.author_id);
+            }
+        } else if let Some(help) = record.entry().to_app_option::<WishHelp>().ok().flatten() {
+            if let Some(element) = get(help.wish_id, GetOptions::default())? {
+                if let Some(wish) = element.entry().to_app_option::<Wish>()? {
+                    partners.push(wish.author_id);
+                }
+            }
+        }
+    }
+    
+    partners.retain(|partner| partner != agent);
+    Ok(partners)
 }
 
-fn hash_password(password: &str) -> ExternResult<String> {
-    bcrypt::hash(password, bcrypt::DEFAULT_COST)
-        .map_err(|e| wasm_error!(WasmErrorInner::Guest(format!("Password hashing failed: {}", e))))
+// Whether `user` and `friend` are friends: both sides have linked
+fn has_friend(user: &AgentPubKey, friend: &AgentPubKey) -> ExternResult<bool> {
+    Ok(has_linked_friend(user, friend)? && has_linked_friend(friend, user)?)
 }
 
-fn verify_password(password: &str, hash: &str) -> bool {
-    bcrypt::verify(password, hash).unwrap_or(false)
+// One side only: `friend` is in `user`'s friend list
+fn has_linked_friend(user: &AgentPubKey, friend: &AgentPubKey) -> ExternResult<bool> {
+    let links = get_links(user.clone(), LinkTypes::UserToFriends, None)?;
+    Ok(links.iter().any(|l| l.target == friend.clone().into()))
 }
 
-fn validate_rut(rut: &str) -> bool {
-    let clean = rut.replace(".", "").replace("-", "").to_uppercase();
-    if !regex::Regex::new(r"^\d{7,8}[0-9K]$").unwrap().is_match(&clean) {
-        return false;
+fn link_friend(agent: &AgentPubKey, friend: &AgentPubKey) -> ExternResult<()> {
+    if !has_linked_friend(agent, friend)? {
+        create_link(agent.clone(), friend.clone(), LinkTypes::UserToFriends, ())?;
+    }
+    Ok(())
+}
+
+// Takes `friend` out of `user`'s list; either of the two may do it
+fn unlink_friend(user: &AgentPubKey, friend: &AgentPubKey) -> ExternResult<()> {
+    for link in get_links(user.clone(), LinkTypes::UserToFriends, None)? {
+        if link.target == friend.clone().into() {
+            delete_link(link.create_link_hash)?;
+        }
     }
+    Ok(())
+}
+
+// Only the recipient's own answer counts (the link is validated, this guards older data)
+fn has_response(request_hash: &ActionHash, recipient: &AgentPubKey) -> ExternResult<bool> {
+    let links = get_links(request_hash.clone(), LinkTypes::FriendRequestToResponse, None)?;
+    Ok(links.iter().any(|l| l.author == *recipient))
+}
+
+// Marks an incoming request as answered; returns the caller and the request
+fn respond_to_friend_request(request_hash: &ActionHash, response: &str) -> ExternResult<(AgentPubKey, FriendRequest)> {
+    let agent = agent_info()?.agent_latest_pubkey();
     
-    let body = &clean[..clean.len() - 1];
-    let dv = &clean[clean.len() - 1..];
+    let request = get(request_hash.clone(), GetOptions::default())?
+        .and_then(|element| element.entry().to_app_option::<FriendRequest>().ok().flatten())
+        .ok_or_else(|| wasm_error!(WasmErrorInner::Guest("Friend request not found".into())))?;
     
-    let mut sum = 0;
-    let mut multiplier = 2;
-    for ch in body.chars().rev() {
-        sum += ch.to_digit(10).unwrap() as i32 * multiplier;
-        multiplier = if multiplier == 7 { 2 } else { multiplier + 1 };
+    if request.recipient_id != agent {
+        return Err(wasm_error!(WasmErrorInner::Guest("Only the recipient can answer this request".into())));
+    }
+    if has_response(request_hash, &agent)? {
+        return Err(wasm_error!(WasmErrorInner::Guest("Already responded".into())));
     }
     
-    let remainder = 11 - (sum % 11);
-    let expected = match remainder {
-        11 => "0",
-        10 => "K",
-        _ => &remainder.to_string(),
-    };
+    create_link(
+        request_hash.clone(),
+        agent.clone(),
+        LinkTypes::FriendRequestToResponse,
+        LinkTag::new(response.as_bytes().to_vec()),
+    )?;
     
-    dv == expected
+    Ok((agent, request))
 }
 
-fn generate_reset_token() -> String {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(format!("{}{}", sys_time().unwrap().as_seconds_since_epoch(), rand::random::<u64>()).as_bytes());
-    hex::encode(&hasher.finalize()[..16])
+fn record_tamagochi_death(tamagochi: &Tamagochi, reason: &str, died_at: i64) -> ExternResult<EntryHash> {
+    let death = TamagochiDeath {
+        owner_id: tamagochi.owner_id.clone(),
+        tamagochi_name: tamagochi.name.clone(),
+        death_reason: reason.to_string(),
+        died_at,
+        age_seconds: died_at - tamagochi.born_at,
+        stage: tamagochi.stage.clone(),
+        level: tamagochi.level,
+        epitaph: None,
+    };
+    
+    let death_hash = create_entry(EntryTypes::TamagochiDeath(death))?;
+    
+    // Link user to death
+    create_link(
+        tamagochi.owner_id.clone(),
+        death_hash.clone(),
+        LinkTypes::UserToDeaths,
+        (),
+    )?;
+    
+    Ok(death_hash)
 }
 
-fn calculate_image_hash(bytes: &[u8]) -> String {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(bytes);
-    hex::encode(hasher.finalize())
+fn pet_state(tamagochi: &Tamagochi) -> tamagochi::PetState {
+    tamagochi::PetState {
+        stage: tamagochi::Stage::parse(&tamagochi.stage).unwrap_or(tamagochi::Stage::Egg),
+        energy: tamagochi.energy,
+        hunger: tamagochi.hunger,
+        hygiene: tamagochi.hygiene,
+        happiness: tamagochi.happiness,
+        experience: tamagochi.experience,
+        level: tamagochi.level,
+        born_at: tamagochi.born_at,
+        last_fed_at: tamagochi.last_fed_at,
+        last_cleaned_at: tamagochi.last_cleaned_at,
+        last_played_at: tamagochi.last_played_at,
+        // Entradas anteriores al motor: asumir simulado hasta el último cuidado
+        last_updated_at: if tamagochi.last_updated_at == 0 {
+            tamagochi.last_fed_at.max(tamagochi.last_cleaned_at)
+        } else {
+            tamagochi.last_updated_at
+        },
+        neglected_ticks: tamagochi.neglected_ticks,
+        care_streak: tamagochi.care_streak,
+        last_care_day: tamagochi.last_care_day,
+        growth: tamagochi.genome.as_ref().map(|g| g.to_engine().growth).unwrap_or_default(),
+        is_alive: tamagochi.is_alive,
+    }
 }
 
-fn format_chat_id(a: &AgentPubKey, b: &AgentPubKey) -> String {
-    let mut ids = vec![a.to_string(), b.to_string()];
-    ids.sort();
-    format!("chat_{}_{}", ids[0], ids[1])
+fn apply_pet_state(tamagochi: &mut Tamagochi, state: tamagochi::PetState) {
+    tamagochi.stage = state.stage.as_str().to_string();
+    tamagochi.energy = state.energy;
+    tamagochi.hunger = state.hunger;
+    tamagochi.hygiene = state.hygiene;
+    tamagochi.happiness = state.happiness;
+    tamagochi.experience = state.experience;
+    tamagochi.level = state.level;
+    tamagochi.born_at = state.born_at;
+    tamagochi.last_fed_at = state.last_fed_at;
+    tamagochi.last_cleaned_at = state.last_cleaned_at;
+    tamagochi.last_played_at = state.last_played_at;
+    tamagochi.last_updated_at = state.last_updated_at;
+    tamagochi.neglected_ticks = state.neglected_ticks;
+    tamagochi.care_streak = state.care_streak;
+    tamagochi.last_care_day = state.last_care_day;
+    tamagochi.is_alive = state.is_alive;
+}
+
+// Newest version of a post, following PostUpdates; None once the post is deleted
+fn get_latest_post(post_hash: &EntryHash) -> ExternResult<Option<Post>> {
+    let element = match get(post_hash.clone(), GetOptions::default())? {
+        Some(element) => element,
+        None => return Ok(None),
+    };
+    
+    let original = match element.entry().to_app_option::<Post>()? {
+        Some(post) => post,
+        None => return Ok(None),
+    };
+    
+    let updates = get_links(post_hash.clone(), LinkTypes::PostUpdates, None)?;
+    if let Some(latest) = updates.into_iter().filter(|l| l.author == original.author_id).max_by_key(|l| l.timestamp) {
+        if let Some(updated) = get(latest.target, GetOptions::default())? {
+            if let Some(post) = updated.entry().to_app_option::<Post>()? {
+                if post.author_id == original.author_id {
+                    return Ok(Some(post));
+                }
+            }
+        }
+    }
+    
+    Ok(Some(original))
 }
 
 fn get_user_by_email(email: &str) -> ExternResult<Option<User>> {
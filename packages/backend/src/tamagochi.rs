@@ -0,0 +1,1106 @@
+//! Tamagochi simulation engine.
+//!
+//! Pure stat math with no host calls: externs load the entry, convert it to a
+//! `PetState`, call `simulate` and persist whatever comes back. Time advances
+//! in whole ticks from `last_updated_at`, so calling it often never
+//! double-counts decay and calling it rarely never loses any.
+
+pub const TICK_SECONDS: i64 = 3_600;
+
+pub const MAX_STAT: u32 = 100;
+const HUNGER_DECAY_PER_TICK: u32 = 5;
+const HYGIENE_DECAY_PER_TICK: u32 = 3;
+const ENERGY_REGEN_PER_TICK: u32 = 5;
+const HAPPINESS_DECAY_PER_TICK: u32 = 1;
+const NEEDY_HAPPINESS_DECAY_PER_TICK: u32 = 3; // Extra cuando tiene hambre o está sucio
+const NEEDY_THRESHOLD: u32 = 30;
+const NEGLECT_TICKS: u32 = 24; // Un día seguido sin higiene ni felicidad
+pub const DAY_SECONDS: i64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Egg,
+    Baby,
+    Child,
+    Teen,
+    Adult,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Egg => "egg",
+            Stage::Baby => "baby",
+            Stage::Child => "child",
+            Stage::Teen => "teen",
+            Stage::Adult => "adult",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Stage> {
+        match value {
+            "egg" => Some(Stage::Egg),
+            "baby" => Some(Stage::Baby),
+            "child" => Some(Stage::Child),
+            "teen" => Some(Stage::Teen),
+            "adult" => Some(Stage::Adult),
+            _ => None,
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        match self {
+            Stage::Egg => 1,
+            Stage::Baby => 2,
+            Stage::Child => 3,
+            Stage::Teen => 4,
+            Stage::Adult => 5,
+        }
+    }
+
+    /// Natural lifespan from birth; pets that grow up live longer
+    pub fn lifespan_seconds(&self) -> i64 {
+        let days = match self {
+            Stage::Egg => 14,
+            Stage::Baby => 30,
+            Stage::Child => 60,
+            Stage::Teen => 90,
+            Stage::Adult => 180,
+        };
+        days * DAY_SECONDS
+    }
+
+    /// Highest stage `experience` reaches from an egg
+    pub fn for_experience(experience: u32) -> Stage {
+        let mut stage = Stage::Egg;
+        while let Some((next, required)) = stage.next() {
+            if experience < required {
+                break;
+            }
+            stage = next;
+        }
+        stage
+    }
+
+    // Next stage and the total experience needed to reach it
+    fn next(&self) -> Option<(Stage, u32)> {
+        match self {
+            Stage::Egg => Some((Stage::Baby, 100)),
+            Stage::Baby => Some((Stage::Child, 300)),
+            Stage::Child => Some((Stage::Teen, 600)),
+            Stage::Teen => Some((Stage::Adult, 1000)),
+            Stage::Adult => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PetState {
+    pub stage: Stage,
+    pub energy: u32,
+    pub hunger: u32, // 100 = lleno, 0 = muere de hambre
+    pub hygiene: u32,
+    pub happiness: u32,
+    pub experience: u32,
+    pub level: u32,
+    pub born_at: i64,
+    pub last_fed_at: i64,
+    pub last_cleaned_at: i64,
+    pub last_played_at: i64,
+    pub last_updated_at: i64, // Último tick simulado
+    pub neglected_ticks: u32, // Ticks seguidos con higiene y felicidad en 0
+    pub care_streak: u32, // Días seguidos con al menos un cuidado del dueño
+    pub last_care_day: i64, // Día (epoch / 86400) del último cuidado
+    pub growth: Growth, // Del genoma
+    pub is_alive: bool,
+}
+
+impl PetState {
+    pub fn new(now: i64) -> Self {
+        PetState {
+            stage: Stage::Egg,
+            energy: MAX_STAT,
+            hunger: MAX_STAT,
+            hygiene: MAX_STAT,
+            happiness: MAX_STAT,
+            experience: 0,
+            level: Stage::Egg.level(),
+            born_at: now,
+            last_fed_at: now,
+            last_cleaned_at: now,
+            last_played_at: now,
+            last_updated_at: now,
+            neglected_ticks: 0,
+            care_streak: 0,
+            last_care_day: 0,
+            growth: Growth::default(),
+            is_alive: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rarity::Common => "common",
+            Rarity::Uncommon => "uncommon",
+            Rarity::Rare => "rare",
+            Rarity::Legendary => "legendary",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+    Yellow,
+    Pink,
+    Blue,
+    Green,
+    Purple,
+    Gold,
+    Rainbow,
+}
+
+const COLOURS: [Colour; 7] = [
+    Colour::Yellow,
+    Colour::Pink,
+    Colour::Blue,
+    Colour::Green,
+    Colour::Purple,
+    Colour::Gold,
+    Colour::Rainbow,
+];
+
+impl Colour {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Colour::Yellow => "yellow",
+            Colour::Pink => "pink",
+            Colour::Blue => "blue",
+            Colour::Green => "green",
+            Colour::Purple => "purple",
+            Colour::Gold => "gold",
+            Colour::Rainbow => "rainbow",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Colour> {
+        COLOURS.iter().copied().find(|c| c.as_str() == value)
+    }
+
+    pub fn rarity(&self) -> Rarity {
+        match self {
+            Colour::Yellow | Colour::Pink | Colour::Blue => Rarity::Common,
+            Colour::Green | Colour::Purple => Rarity::Uncommon,
+            Colour::Gold => Rarity::Rare,
+            Colour::Rainbow => Rarity::Legendary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Species {
+    Blob,
+    Cat,
+    Bunny,
+    Bird,
+    Fox,
+    Dragon,
+    Phoenix,
+}
+
+const SPECIES: [Species; 7] = [
+    Species::Blob,
+    Species::Cat,
+    Species::Bunny,
+    Species::Bird,
+    Species::Fox,
+    Species::Dragon,
+    Species::Phoenix,
+];
+
+impl Species {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Species::Blob => "blob",
+            Species::Cat => "cat",
+            Species::Bunny => "bunny",
+            Species::Bird => "bird",
+            Species::Fox => "fox",
+            Species::Dragon => "dragon",
+            Species::Phoenix => "phoenix",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Species> {
+        SPECIES.iter().copied().find(|s| s.as_str() == value)
+    }
+
+    pub fn rarity(&self) -> Rarity {
+        match self {
+            Species::Blob | Species::Cat | Species::Bunny => Rarity::Common,
+            Species::Bird | Species::Fox => Rarity::Uncommon,
+            Species::Dragon => Rarity::Rare,
+            Species::Phoenix => Rarity::Legendary,
+        }
+    }
+}
+
+/// Percent bonus (or malus) on the gains of each stat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Growth {
+    pub experience: i32,
+    pub happiness: i32,
+    pub energy: i32,
+}
+
+pub const MIN_GROWTH: i32 = -20;
+pub const MAX_GROWTH: i32 = 30;
+pub const GROWTH_DRIFT: i32 = 5; // Variación al heredar; también el rango de los fundadores
+const MUTATION_ONE_IN: u64 = 10; // Probabilidad de subir un rasgo de rareza
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Genome {
+    pub colour: Colour,
+    pub species: Species,
+    pub growth: Growth,
+    pub generation: u32, // 0 = mascota creada, no criada
+}
+
+impl Default for Genome {
+    // Lo que tienen las mascotas anteriores a la genética
+    fn default() -> Self {
+        Genome {
+            colour: Colour::Yellow,
+            species: Species::Blob,
+            growth: Growth::default(),
+            generation: 0,
+        }
+    }
+}
+
+impl Genome {
+    /// Random common genome for pets created from scratch
+    pub fn founder(seed: &[u8]) -> Genome {
+        let mut rng = Rng::new(seed);
+        let colours: Vec<Colour> = COLOURS.iter().copied().filter(|c| c.rarity() == Rarity::Common).collect();
+        let species: Vec<Species> = SPECIES.iter().copied().filter(|s| s.rarity() == Rarity::Common).collect();
+        Genome {
+            colour: colours[rng.below(colours.len() as u64) as usize],
+            species: species[rng.below(species.len() as u64) as usize],
+            growth: Growth {
+                experience: rng.range(-GROWTH_DRIFT, GROWTH_DRIFT),
+                happiness: rng.range(-GROWTH_DRIFT, GROWTH_DRIFT),
+                energy: rng.range(-GROWTH_DRIFT, GROWTH_DRIFT),
+            },
+            generation: 0,
+        }
+    }
+
+    /// The rarest trait decides the tier
+    pub fn rarity(&self) -> Rarity {
+        self.colour.rarity().max(self.species.rarity())
+    }
+}
+
+/// Child genome of two parents; the same seed always gives the same child so
+/// validators can recompute it
+pub fn breed(a: &Genome, b: &Genome, seed: &[u8]) -> Genome {
+    let mut rng = Rng::new(seed);
+
+    let mut colour = if rng.below(2) == 0 { a.colour } else { b.colour };
+    if rng.below(MUTATION_ONE_IN) == 0 {
+        colour = mutate(&mut rng, colour.rarity(), &COLOURS, Colour::rarity).unwrap_or(colour);
+    }
+    let mut species = if rng.below(2) == 0 { a.species } else { b.species };
+    if rng.below(MUTATION_ONE_IN) == 0 {
+        species = mutate(&mut rng, species.rarity(), &SPECIES, Species::rarity).unwrap_or(species);
+    }
+
+    let mut inherit = |x: i32, y: i32| {
+        ((x + y) / 2 + rng.range(-GROWTH_DRIFT, GROWTH_DRIFT)).clamp(MIN_GROWTH, MAX_GROWTH)
+    };
+    let growth = Growth {
+        experience: inherit(a.growth.experience, b.growth.experience),
+        happiness: inherit(a.growth.happiness, b.growth.happiness),
+        energy: inherit(a.growth.energy, b.growth.energy),
+    };
+
+    Genome {
+        colour,
+        species,
+        growth,
+        generation: a.generation.max(b.generation) + 1,
+    }
+}
+
+// A random trait from the next tier up; None at legendary
+fn mutate<T: Copy>(rng: &mut Rng, from: Rarity, all: &[T], rarity: fn(&T) -> Rarity) -> Option<T> {
+    let next = match from {
+        Rarity::Common => Rarity::Uncommon,
+        Rarity::Uncommon => Rarity::Rare,
+        Rarity::Rare => Rarity::Legendary,
+        Rarity::Legendary => return None,
+    };
+    let candidates: Vec<T> = all.iter().copied().filter(|t| rarity(t) == next).collect();
+    Some(candidates[rng.below(candidates.len() as u64) as usize])
+}
+
+// In i64 and saturating, so no gain or percent can overflow
+fn with_growth(gain: u32, percent: i32) -> u32 {
+    ((gain as i64).saturating_mul(100 + percent as i64) / 100).clamp(0, u32::MAX as i64) as u32
+}
+
+// xorshift64 sembrado con FNV-1a; determinista en todas las plataformas
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: &[u8]) -> Rng {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in seed {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Rng(if hash == 0 { 1 } else { hash })
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        lo + self.below((hi - lo + 1) as u64) as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Food,
+    Toy,
+    Soap,
+    Medicine,
+}
+
+impl ItemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemKind::Food => "food",
+            ItemKind::Toy => "toy",
+            ItemKind::Soap => "soap",
+            ItemKind::Medicine => "medicine",
+        }
+    }
+}
+
+/// Catalogue entry; stat effects are deltas clamped to 0-100
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub kind: ItemKind,
+    pub price: u32, // Monedas en la tienda
+    pub hunger: i32,
+    pub hygiene: i32,
+    pub happiness: i32,
+    pub energy: i32,
+    pub experience: u32,
+}
+
+pub const CATALOGUE: &[Item] = &[
+    Item { id: "apple", name: "Manzana", kind: ItemKind::Food, price: 5, hunger: 20, hygiene: 0, happiness: 2, energy: 0, experience: 2 },
+    Item { id: "burger", name: "Hamburguesa", kind: ItemKind::Food, price: 12, hunger: 45, hygiene: -5, happiness: 5, energy: 0, experience: 4 },
+    Item { id: "cake", name: "Torta", kind: ItemKind::Food, price: 20, hunger: 25, hygiene: 0, happiness: 20, energy: 0, experience: 6 },
+    Item { id: "ball", name: "Pelota", kind: ItemKind::Toy, price: 15, hunger: -5, hygiene: -5, happiness: 20, energy: -10, experience: 8 },
+    Item { id: "puzzle", name: "Puzzle", kind: ItemKind::Toy, price: 25, hunger: 0, hygiene: 0, happiness: 15, energy: -5, experience: 15 },
+    Item { id: "soap", name: "Jabón", kind: ItemKind::Soap, price: 8, hunger: 0, hygiene: 60, happiness: 3, energy: 0, experience: 2 },
+    Item { id: "bubble_bath", name: "Baño de espuma", kind: ItemKind::Soap, price: 18, hunger: 0, hygiene: 100, happiness: 10, energy: 0, experience: 4 },
+    Item { id: "medicine", name: "Remedio", kind: ItemKind::Medicine, price: 30, hunger: 0, hygiene: 0, happiness: 5, energy: 20, experience: 2 },
+];
+
+pub fn find_item(id: &str) -> Option<&'static Item> {
+    CATALOGUE.iter().find(|item| item.id == id)
+}
+
+/// App activity that earns coins (and sometimes an item) for the pet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activity {
+    Post,
+    WishHelp,
+    Ride,
+}
+
+impl Activity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Activity::Post => "post",
+            Activity::WishHelp => "wish_help",
+            Activity::Ride => "ride",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Activity> {
+        match value {
+            "post" => Some(Activity::Post),
+            "wish_help" => Some(Activity::WishHelp),
+            "ride" => Some(Activity::Ride),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reward {
+    pub coins: u32,
+    pub item: Option<&'static str>,
+}
+
+pub fn reward(activity: Activity) -> Reward {
+    match activity {
+        Activity::Post => Reward { coins: 5, item: None },
+        Activity::WishHelp => Reward { coins: 15, item: Some("cake") },
+        Activity::Ride => Reward { coins: 10, item: Some("ball") },
+    }
+}
+
+/// Owner actions, applied at `now` after time has been advanced
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Feed,
+    Clean,
+    Play,
+    AppOpen, // Crecimiento automático al abrir la app
+    UseItem(&'static Item),
+    VisitorPet, // Un visitante aceptado lo acaricia
+    VisitorPlay, // Un visitante aceptado juega con él
+    SocialPlay, // La mascota del visitante gana experiencia por visitar
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeathCause {
+    Starvation,
+    Neglect,
+    OldAge,
+}
+
+impl DeathCause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeathCause::Starvation => "starvation",
+            DeathCause::Neglect => "neglect",
+            DeathCause::OldAge => "old_age",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Death {
+    pub cause: DeathCause,
+    pub at: i64, // Tick en que murió, no el momento de la llamada
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub state: PetState,
+    pub death: Option<Death>, // Solo si murió durante esta simulación
+    pub evolved: bool,
+}
+
+/// Most events one stored version may apply; bigger batches are split
+pub const MAX_EVENTS_PER_UPDATE: usize = 20;
+
+/// Most experience one update can add: every event the richest one, with the best genes
+pub fn max_experience_gain() -> u32 {
+    let richest = CATALOGUE.iter().map(|item| item.experience).max().unwrap_or(0).max(10); // Play y AppOpen dan 10
+    with_growth(richest, MAX_GROWTH) * MAX_EVENTS_PER_UPDATE as u32
+}
+
+pub fn simulate(state: &PetState, events: &[Event], now: i64) -> Outcome {
+    let mut pet = state.clone();
+    let start_stage = pet.stage;
+
+    let death = if pet.is_alive { advance(&mut pet, now) } else { None };
+
+    if pet.is_alive {
+        for event in events {
+            apply(&mut pet, *event, now);
+        }
+        evolve(&mut pet);
+    }
+
+    Outcome {
+        evolved: pet.stage != start_stage,
+        state: pet,
+        death,
+    }
+}
+
+fn advance(pet: &mut PetState, now: i64) -> Option<Death> {
+    let ticks = (now - pet.last_updated_at).max(0) / TICK_SECONDS;
+
+    for _ in 0..ticks {
+        pet.last_updated_at += TICK_SECONDS;
+        tick(pet);
+
+        let cause = if pet.hunger == 0 {
+            Some(DeathCause::Starvation)
+        } else if pet.neglected_ticks >= NEGLECT_TICKS {
+            Some(DeathCause::Neglect)
+        } else if pet.last_updated_at - pet.born_at >= pet.stage.lifespan_seconds() {
+            Some(DeathCause::OldAge)
+        } else {
+            None
+        };
+
+        if let Some(cause) = cause {
+            pet.is_alive = false;
+            return Some(Death {
+                cause,
+                at: pet.last_updated_at,
+            });
+        }
+    }
+
+    None
+}
+
+fn tick(pet: &mut PetState) {
+    pet.hunger = pet.hunger.saturating_sub(HUNGER_DECAY_PER_TICK);
+    pet.hygiene = pet.hygiene.saturating_sub(HYGIENE_DECAY_PER_TICK);
+    pet.energy = (pet.energy + with_growth(ENERGY_REGEN_PER_TICK, pet.growth.energy)).min(MAX_STAT);
+
+    let mut happiness_decay = HAPPINESS_DECAY_PER_TICK;
+    if pet.hunger < NEEDY_THRESHOLD || pet.hygiene < NEEDY_THRESHOLD {
+        happiness_decay += NEEDY_HAPPINESS_DECAY_PER_TICK;
+    }
+    pet.happiness = pet.happiness.saturating_sub(happiness_decay);
+
+    if pet.hygiene == 0 && pet.happiness == 0 {
+        pet.neglected_ticks += 1;
+    } else {
+        pet.neglected_ticks = 0;
+    }
+}
+
+fn apply(pet: &mut PetState, event: Event, now: i64) {
+    if matches!(event, Event::Feed | Event::Clean | Event::Play | Event::UseItem(_)) {
+        mark_cared(pet, now);
+    }
+
+    let (experience, happiness) = (pet.experience, pet.happiness);
+
+    match event {
+        Event::Feed => {
+            pet.hunger = (pet.hunger + 30).min(MAX_STAT);
+            pet.happiness = (pet.happiness + 5).min(MAX_STAT);
+            pet.last_fed_at = now;
+            pet.experience += 5;
+        }
+        Event::Clean => {
+            pet.hygiene = MAX_STAT;
+            pet.neglected_ticks = 0;
+            pet.happiness = (pet.happiness + 10).min(MAX_STAT);
+            pet.last_cleaned_at = now;
+            pet.experience += 3;
+        }
+        Event::Play => {
+            pet.energy = pet.energy.saturating_sub(10);
+            pet.happiness = (pet.happiness + 15).min(MAX_STAT);
+            pet.last_played_at = now;
+            pet.experience += 10;
+        }
+        Event::AppOpen => {
+            pet.experience += 10;
+            pet.hunger = (pet.hunger + 20).min(MAX_STAT);
+        }
+        Event::VisitorPet => {
+            pet.happiness = (pet.happiness + 5).min(MAX_STAT);
+            pet.experience += 2;
+        }
+        Event::VisitorPlay => {
+            pet.energy = pet.energy.saturating_sub(5);
+            pet.happiness = (pet.happiness + 10).min(MAX_STAT);
+            pet.last_played_at = now;
+            pet.experience += 4;
+        }
+        Event::SocialPlay => {
+            pet.happiness = (pet.happiness + 3).min(MAX_STAT);
+            pet.experience += 5;
+        }
+        Event::UseItem(item) => {
+            pet.hunger = adjust(pet.hunger, item.hunger);
+            pet.hygiene = adjust(pet.hygiene, item.hygiene);
+            pet.happiness = adjust(pet.happiness, item.happiness);
+            pet.energy = adjust(pet.energy, item.energy);
+            pet.experience += item.experience;
+            match item.kind {
+                ItemKind::Food => pet.last_fed_at = now,
+                ItemKind::Toy => pet.last_played_at = now,
+                ItemKind::Soap => pet.last_cleaned_at = now,
+                ItemKind::Medicine => pet.neglected_ticks = 0, // Cura el abandono
+            }
+            if pet.hygiene > 0 && pet.happiness > 0 {
+                pet.neglected_ticks = 0;
+            }
+        }
+    }
+
+    // Modificadores genéticos solo sobre lo ganado
+    if pet.experience > experience {
+        pet.experience = experience + with_growth(pet.experience - experience, pet.growth.experience);
+    }
+    if pet.happiness > happiness {
+        pet.happiness = (happiness + with_growth(pet.happiness - happiness, pet.growth.happiness)).min(MAX_STAT);
+    }
+}
+
+fn mark_cared(pet: &mut PetState, now: i64) {
+    let today = now.div_euclid(DAY_SECONDS);
+    pet.care_streak = streak_after_care(pet.care_streak, pet.last_care_day, today);
+    pet.last_care_day = today;
+}
+
+/// Streak after caring on `day`: same day keeps it, the next day adds one, a gap restarts it
+pub fn streak_after_care(care_streak: u32, last_care_day: i64, day: i64) -> u32 {
+    if care_streak > 0 && last_care_day == day {
+        care_streak
+    } else if care_streak > 0 && last_care_day == day - 1 {
+        care_streak + 1
+    } else {
+        1
+    }
+}
+
+/// Whether a saved streak can follow the previous one: untouched, or one care on a later day up to `today`
+pub fn follows_care_streak(previous: (u32, i64), next: (u32, i64), today: i64) -> bool {
+    let (care_streak, last_care_day) = previous;
+    let (next_streak, next_day) = next;
+    if next == previous {
+        return true;
+    }
+    next_day > last_care_day && next_day <= today && next_streak == streak_after_care(care_streak, last_care_day, next_day)
+}
+
+/// Streak as of `now`: a stored streak is broken once a whole day passes without care
+pub fn effective_care_streak(care_streak: u32, last_care_day: i64, now: i64) -> u32 {
+    if now.div_euclid(DAY_SECONDS) - last_care_day > 1 {
+        0
+    } else {
+        care_streak
+    }
+}
+
+fn adjust(stat: u32, delta: i32) -> u32 {
+    (stat as i32 + delta).clamp(0, MAX_STAT as i32) as u32
+}
+
+// Enough experience can skip several stages at once
+fn evolve(pet: &mut PetState) {
+    let reached = Stage::for_experience(pet.experience);
+    let evolved = reached > pet.stage;
+    if evolved {
+        pet.stage = reached;
+    }
+
+    pet.level = pet.stage.level();
+    if evolved {
+        pet.happiness = MAX_STAT; // Evolucionar los pone felices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = TICK_SECONDS;
+
+    #[test]
+    fn decays_per_elapsed_tick() {
+        let pet = PetState::new(0);
+        let outcome = simulate(&pet, &[], 4 * HOUR);
+
+        assert_eq!(outcome.state.hunger, 100 - 4 * HUNGER_DECAY_PER_TICK);
+        assert_eq!(outcome.state.hygiene, 100 - 4 * HYGIENE_DECAY_PER_TICK);
+        assert_eq!(outcome.state.happiness, 100 - 4 * HAPPINESS_DECAY_PER_TICK);
+        assert_eq!(outcome.state.last_updated_at, 4 * HOUR);
+        assert!(outcome.death.is_none());
+    }
+
+    #[test]
+    fn frequent_calls_match_a_single_call() {
+        let pet = PetState::new(0);
+        let once = simulate(&pet, &[], 10 * HOUR + 120).state;
+
+        let mut stepped = pet;
+        let mut now = 0;
+        while now < 10 * HOUR + 120 {
+            now = (now + 1_000).min(10 * HOUR + 120);
+            stepped = simulate(&stepped, &[], now).state;
+        }
+
+        assert_eq!(once, stepped);
+    }
+
+    #[test]
+    fn partial_ticks_carry_over() {
+        let pet = PetState::new(0);
+        let outcome = simulate(&pet, &[], HOUR - 1);
+
+        assert_eq!(outcome.state.hunger, 100);
+        assert_eq!(outcome.state.last_updated_at, 0);
+    }
+
+    #[test]
+    fn energy_regenerates_up_to_max() {
+        let mut pet = PetState::new(0);
+        pet.energy = 90;
+        let outcome = simulate(&pet, &[], 5 * HOUR);
+
+        assert_eq!(outcome.state.energy, 100);
+    }
+
+    #[test]
+    fn needy_pets_lose_happiness_faster() {
+        let mut pet = PetState::new(0);
+        pet.hygiene = 10;
+        let outcome = simulate(&pet, &[], HOUR);
+
+        assert_eq!(outcome.state.happiness, 100 - HAPPINESS_DECAY_PER_TICK - NEEDY_HAPPINESS_DECAY_PER_TICK);
+    }
+
+    #[test]
+    fn starves_at_the_tick_hunger_hits_zero() {
+        let mut pet = PetState::new(0);
+        pet.hunger = 12;
+        let outcome = simulate(&pet, &[Event::Feed], 10 * HOUR);
+
+        assert!(!outcome.state.is_alive);
+        assert_eq!(
+            outcome.death,
+            Some(Death { cause: DeathCause::Starvation, at: 3 * HOUR })
+        );
+        // Los eventos no reviven a una mascota muerta
+        assert_eq!(outcome.state.hunger, 0);
+    }
+
+    #[test]
+    fn dies_of_neglect_after_a_day_dirty_and_sad() {
+        let mut pet = PetState::new(0);
+        pet.hygiene = 0;
+        pet.happiness = 0;
+
+        // Solo abrir la app: no pasa hambre pero nadie lo limpia ni juega
+        let mut death = None;
+        let mut now = 0;
+        while death.is_none() && now < 48 * HOUR {
+            now += 4 * HOUR;
+            let outcome = simulate(&pet, &[Event::AppOpen], now);
+            pet = outcome.state;
+            death = outcome.death;
+        }
+
+        assert_eq!(
+            death,
+            Some(Death { cause: DeathCause::Neglect, at: NEGLECT_TICKS as i64 * HOUR })
+        );
+    }
+
+    #[test]
+    fn cleaning_resets_neglect() {
+        let mut pet = PetState::new(0);
+        pet.hygiene = 0;
+        pet.happiness = 0;
+        pet.neglected_ticks = NEGLECT_TICKS - 1;
+        let outcome = simulate(&pet, &[Event::Clean], 0);
+
+        assert_eq!(outcome.state.neglected_ticks, 0);
+        assert!(outcome.state.is_alive);
+    }
+
+    #[test]
+    fn dies_of_old_age_at_stage_lifespan() {
+        let mut pet = PetState::new(0);
+        pet.stage = Stage::Adult;
+        pet.last_updated_at = Stage::Adult.lifespan_seconds() - HOUR;
+        let outcome = simulate(&pet, &[], Stage::Adult.lifespan_seconds() + 5 * HOUR);
+
+        assert_eq!(
+            outcome.death,
+            Some(Death { cause: DeathCause::OldAge, at: Stage::Adult.lifespan_seconds() })
+        );
+    }
+
+    #[test]
+    fn grown_pets_outlive_eggs() {
+        assert!(Stage::Adult.lifespan_seconds() > Stage::Egg.lifespan_seconds());
+
+        let mut pet = PetState::new(0);
+        pet.stage = Stage::Teen;
+        pet.last_updated_at = Stage::Egg.lifespan_seconds();
+        let outcome = simulate(&pet, &[], Stage::Egg.lifespan_seconds() + HOUR);
+
+        assert!(outcome.state.is_alive);
+    }
+
+    #[test]
+    fn dead_pets_do_not_change() {
+        let mut pet = PetState::new(0);
+        pet.is_alive = false;
+        let outcome = simulate(&pet, &[Event::Feed, Event::Play], 50 * HOUR);
+
+        assert_eq!(outcome.state, pet);
+        assert!(outcome.death.is_none());
+    }
+
+    #[test]
+    fn events_apply_after_decay() {
+        let pet = PetState::new(0);
+        let outcome = simulate(&pet, &[Event::Feed], 2 * HOUR);
+
+        assert_eq!(outcome.state.hunger, 100);
+        assert_eq!(outcome.state.last_fed_at, 2 * HOUR);
+        assert_eq!(outcome.state.experience, 5);
+    }
+
+    #[test]
+    fn play_spends_energy() {
+        let pet = PetState::new(0);
+        let outcome = simulate(&pet, &[Event::Play, Event::Play], 0);
+
+        assert_eq!(outcome.state.energy, 80);
+        assert_eq!(outcome.state.experience, 20);
+    }
+
+    #[test]
+    fn evolves_through_several_stages() {
+        let mut pet = PetState::new(0);
+        pet.experience = 595;
+        pet.happiness = 40;
+        let outcome = simulate(&pet, &[Event::Feed], 0);
+
+        assert!(outcome.evolved);
+        assert_eq!(outcome.state.stage, Stage::Teen);
+        assert_eq!(outcome.state.level, 4);
+        assert_eq!(outcome.state.happiness, 100);
+    }
+
+    #[test]
+    fn adults_stay_adults() {
+        let mut pet = PetState::new(0);
+        pet.stage = Stage::Adult;
+        pet.experience = 5_000;
+        let outcome = simulate(&pet, &[Event::AppOpen], 0);
+
+        assert!(!outcome.evolved);
+        assert_eq!(outcome.state.level, 5);
+    }
+
+    #[test]
+    fn items_apply_clamped_effects() {
+        let mut pet = PetState::new(0);
+        pet.hunger = 90;
+        pet.energy = 5;
+        let burger = find_item("burger").unwrap();
+        let ball = find_item("ball").unwrap();
+        let outcome = simulate(&pet, &[Event::UseItem(burger), Event::UseItem(ball)], 0);
+
+        assert_eq!(outcome.state.hunger, 95);
+        assert_eq!(outcome.state.energy, 0);
+        assert_eq!(outcome.state.experience, burger.experience + ball.experience);
+        assert_eq!(outcome.state.last_played_at, 0);
+    }
+
+    #[test]
+    fn visits_cheer_up_both_pets() {
+        let mut host = PetState::new(0);
+        host.happiness = 50;
+        let host = simulate(&host, &[Event::VisitorPet, Event::VisitorPlay], 0).state;
+
+        assert_eq!(host.happiness, 65);
+        assert_eq!(host.energy, 95);
+        assert_eq!(host.experience, 6);
+
+        let visitor = simulate(&PetState::new(0), &[Event::SocialPlay], 0).state;
+        assert_eq!(visitor.experience, 5);
+    }
+
+    #[test]
+    fn medicine_cures_neglect() {
+        let mut pet = PetState::new(0);
+        pet.hygiene = 0;
+        pet.happiness = 0;
+        pet.neglected_ticks = NEGLECT_TICKS - 1;
+        let outcome = simulate(&pet, &[Event::UseItem(find_item("medicine").unwrap())], 0);
+
+        assert_eq!(outcome.state.neglected_ticks, 0);
+    }
+
+    #[test]
+    fn catalogue_ids_are_unique_and_rewards_exist() {
+        for (i, item) in CATALOGUE.iter().enumerate() {
+            assert!(CATALOGUE[i + 1..].iter().all(|other| other.id != item.id));
+        }
+        for activity in [Activity::Post, Activity::WishHelp, Activity::Ride] {
+            if let Some(id) = reward(activity).item {
+                assert!(find_item(id).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn care_streak_counts_consecutive_days() {
+        let day = DAY_SECONDS;
+        let mut pet = PetState::new(0);
+        for now in [10, 20, day + 5, 2 * day + 5] {
+            pet.last_updated_at = now; // Sin decaimiento: solo importa la racha
+            pet = simulate(&pet, &[Event::Feed], now).state;
+        }
+
+        assert_eq!(pet.care_streak, 3);
+        assert_eq!(pet.last_care_day, 2);
+    }
+
+    #[test]
+    fn care_streak_resets_after_a_missed_day() {
+        let day = DAY_SECONDS;
+        let mut pet = PetState::new(0);
+        pet.care_streak = 5;
+        pet.last_care_day = 0;
+        pet.last_updated_at = 3 * day;
+        let outcome = simulate(&pet, &[Event::Clean], 3 * day);
+
+        assert_eq!(outcome.state.care_streak, 1);
+        assert_eq!(effective_care_streak(5, 0, day + 1), 5);
+        assert_eq!(effective_care_streak(5, 0, 2 * day), 0);
+    }
+
+    #[test]
+    fn saved_streaks_rise_one_care_day_at_a_time() {
+        assert!(follows_care_streak((3, 10), (3, 10), 12));
+        assert!(follows_care_streak((3, 10), (4, 11), 12));
+        assert!(follows_care_streak((3, 10), (1, 12), 12));
+        assert!(!follows_care_streak((3, 10), (5, 11), 12));
+        assert!(!follows_care_streak((3, 10), (4, 10), 12));
+        assert!(!follows_care_streak((3, 10), (4, 13), 12));
+        assert!(!follows_care_streak((3, 10), (0, 10), 12));
+    }
+
+    #[test]
+    fn app_open_is_not_care() {
+        let outcome = simulate(&PetState::new(0), &[Event::AppOpen, Event::SocialPlay], 0);
+
+        assert_eq!(outcome.state.care_streak, 0);
+    }
+
+    fn parent(colour: Colour, species: Species, growth: i32) -> Genome {
+        Genome {
+            colour,
+            species,
+            growth: Growth { experience: growth, happiness: growth, energy: growth },
+            generation: 2,
+        }
+    }
+
+    #[test]
+    fn breeding_is_deterministic_per_seed() {
+        let a = parent(Colour::Pink, Species::Cat, 10);
+        let b = parent(Colour::Blue, Species::Fox, 0);
+
+        assert_eq!(breed(&a, &b, b"seed"), breed(&a, &b, b"seed"));
+        assert_eq!(breed(&a, &b, b"seed").generation, 3);
+    }
+
+    #[test]
+    fn children_inherit_or_mutate_upwards() {
+        let a = parent(Colour::Pink, Species::Cat, 10);
+        let b = parent(Colour::Blue, Species::Cat, 0);
+        let mut mutated = false;
+
+        for i in 0..500u32 {
+            let child = breed(&a, &b, &i.to_le_bytes());
+            if child.colour != a.colour && child.colour != b.colour {
+                assert_eq!(child.colour.rarity(), Rarity::Uncommon);
+                mutated = true;
+            }
+            assert!(child.species == Species::Cat || child.species.rarity() == Rarity::Uncommon);
+            assert!((5 - GROWTH_DRIFT..=5 + GROWTH_DRIFT).contains(&child.growth.experience));
+        }
+
+        assert!(mutated, "some of 500 children should mutate");
+    }
+
+    #[test]
+    fn one_update_cannot_raise_an_adult() {
+        assert!(max_experience_gain() < 1000);
+        assert_eq!(Stage::for_experience(999), Stage::Teen);
+        assert_eq!(Stage::for_experience(1000), Stage::Adult);
+    }
+
+    #[test]
+    fn extreme_growth_does_not_overflow() {
+        assert_eq!(with_growth(10, 20), 12);
+        assert_eq!(with_growth(u32::MAX, i32::MAX), u32::MAX);
+        assert_eq!(with_growth(u32::MAX, i32::MIN), 0);
+    }
+
+    #[test]
+    fn growth_stays_within_bounds() {
+        let a = parent(Colour::Rainbow, Species::Phoenix, MAX_GROWTH);
+
+        for i in 0..100u32 {
+            let child = breed(&a, &a, &i.to_le_bytes());
+            assert!(child.growth.experience <= MAX_GROWTH);
+            assert_eq!(child.rarity(), Rarity::Legendary);
+        }
+    }
+
+    #[test]
+    fn founders_are_common() {
+        for i in 0..50u32 {
+            let genome = Genome::founder(&i.to_le_bytes());
+            assert_eq!(genome.rarity(), Rarity::Common);
+            assert_eq!(genome.generation, 0);
+        }
+    }
+
+    #[test]
+    fn growth_scales_experience_gains() {
+        let mut pet = PetState::new(0);
+        pet.growth.experience = 30;
+        let outcome = simulate(&pet, &[Event::Play], 0);
+
+        assert_eq!(outcome.state.experience, 13); // 10 * 130%
+    }
+
+    #[test]
+    fn trait_names_round_trip() {
+        for colour in COLOURS {
+            assert_eq!(Colour::parse(colour.as_str()), Some(colour));
+        }
+        for species in SPECIES {
+            assert_eq!(Species::parse(species.as_str()), Some(species));
+        }
+    }
+
+    #[test]
+    fn stage_names_round_trip() {
+        for stage in [Stage::Egg, Stage::Baby, Stage::Child, Stage::Teen, Stage::Adult] {
+            assert_eq!(Stage::parse(stage.as_str()), Some(stage));
+        }
+        assert_eq!(Stage::parse("elder"), None);
+    }
+}
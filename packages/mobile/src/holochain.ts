@@ -14,20 +14,38 @@ export interface User {
   email: string;
   password_hash: string;
   name: string;
-  rut?: string;
+  rut_masked?: string; // e.g. "12.***.***-K"; the RUT itself is only stored hashed
   profile_image_hash?: string;
   id_card_image_hash?: string;
   is_verified: boolean;
+  verification_hash?: string;
   lat?: number;
   lon?: number;
   created_at: number;
 }
 
+export interface VerificationRequestItem {
+  request_hash: string;
+  request: {
+    user_id: string;
+    id_card_image_hash: string;
+    created_at: number;
+  };
+  attestation: {
+    verifier_id: string;
+    status: 'approved' | 'rejected';
+    reason: string | null;
+    rut_masked: string | null;
+    attested_at: number;
+  } | null;
+}
+
 export interface Post {
   author_id: string;
   text?: string;
   image_hashes: string[];
   created_at: number;
+  edited_at?: number;
   location?: {
     lat: number;
     lon: number;
@@ -35,6 +53,183 @@ export interface Post {
   };
 }
 
+export interface Story {
+  author_id: string;
+  text?: string;
+  image_hashes: string[];
+  video_hash?: string;
+  sticker_data: any[];
+  created_at: number;
+  expires_at: number;
+}
+
+export interface CommentTarget {
+  type: 'post' | 'product';
+  hash: string;
+}
+
+export interface ReportTarget {
+  type: 'post' | 'user' | 'product' | 'service' | 'message' | 'wish' | 'tamagochi_visit';
+  hash: string;
+}
+
+export interface Report {
+  target: ReportTarget;
+  reported_agent: string;
+  target_action: string | null;
+  reporter_id: string;
+  reason: 'spam' | 'inappropriate' | 'violence' | 'harassment' | 'fraud' | 'impersonation' | 'other';
+  description: string | null;
+  evidence_image_hashes: string[];
+  created_at: number;
+}
+
+export interface ShopItem {
+  id: string;
+  name: string;
+  kind: 'food' | 'toy' | 'soap' | 'medicine';
+  price: number;
+  hunger: number;
+  hygiene: number;
+  happiness: number;
+  energy: number;
+  experience: number;
+}
+
+export interface FriendRequest {
+  sender_id: string;
+  recipient_id: string;
+  message: string | null;
+  created_at: number;
+}
+
+export interface NotificationSettings {
+  messages: boolean;
+  friend_requests: boolean;
+  comments: boolean;
+  claps: boolean;
+  mentions: boolean;
+  tamagochi: boolean;
+}
+
+export interface UserPreferences {
+  owner_id: string;
+  app_color: string;
+  tamagochi_enabled: boolean;
+  location_sharing_enabled: boolean;
+  language: 'es' | 'en' | 'pt';
+  distance_unit: 'km' | 'mi';
+  notifications: NotificationSettings;
+  default_currency: string;
+  profile_visibility: 'public' | 'friends' | 'private';
+  updated_at: number;
+}
+
+export interface FriendSuggestion {
+  agent_id: string;
+  score: number;
+  mutual_friends: number;
+  distance_km: number | null;
+  interactions: number;
+  reason: string;
+}
+
+export interface AchievementInfo {
+  id: string;
+  name: string;
+  description: string;
+  required: number;
+}
+
+export interface UnlockedAchievement {
+  achievement: AchievementInfo;
+  unlock_hash: string;
+  unlocked_at: number;
+}
+
+export interface Genome {
+  colour: string;
+  species: string;
+  rarity: 'common' | 'uncommon' | 'rare' | 'legendary';
+  experience_growth: number;
+  happiness_growth: number;
+  energy_growth: number;
+  generation: number;
+}
+
+export interface BreedingProposal {
+  proposer_id: string;
+  partner_id: string;
+  proposer_tamagochi: string;
+  partner_tamagochi: string;
+  proposer_version: string;
+  partner_version: string;
+  egg_owner_id: string;
+  proposed_at: number;
+}
+
+export interface BreedingRecord {
+  proposal_hash: string;
+  proposer_id: string;
+  partner_id: string;
+  egg_owner_id: string;
+  genome: Genome;
+  bred_at: number;
+}
+
+export type LeaderboardMetric = 'level' | 'age' | 'care_streak';
+
+export type LeaderboardScope =
+  | { type: 'global' }
+  | { type: 'friends' }
+  | { type: 'nearby'; lat: number; lon: number; radius_km: number };
+
+export interface LeaderboardEntry {
+  rank: number;
+  owner_id: string;
+  tamagochi_hash: string;
+  tamagochi_name: string;
+  stage: string;
+  level: number;
+  age_seconds: number;
+  care_streak: number;
+  score: number;
+  snapshot_taken_at: number;
+}
+
+export interface TamagochiInventory {
+  owner_id: string;
+  coins: number;
+  items: Array<{ item_id: string; quantity: number }>;
+  rewards_day: number;
+  rewards_today: number;
+  last_reward_at: number;
+  previous: string | null;
+  change:
+    | { type: 'buy'; item_id: string; quantity: number }
+    | { type: 'use'; item_id: string }
+    | { type: 'reward'; activity: string; evidence: string }
+    | null;
+  updated_at: number;
+}
+
+export interface StickerPack {
+  creator_id: string;
+  name: string;
+  image_hashes: string[];
+  created_at: number;
+}
+
+export interface FeedCursor {
+  created_at: number;
+  post_hash: string;
+}
+
+export interface FeedPage {
+  posts: Array<Post & { hash: string }>;
+  nextCursor: FeedCursor | null;
+}
+
 export interface Product {
   seller_id: string;
   title: string;
@@ -186,7 +381,6 @@ class HolochainClient {
 
   async updateUserProfile(updates: {
     name?: string;
-    rut?: string;
     lat?: number;
     lon?: number;
   }): Promise<User> {
@@ -197,11 +391,54 @@ class HolochainClient {
     });
   }
 
-  async verifyUser(rut: string, idCardImageHash: string): Promise<User> {
+  // Submits a verification request; returns its hash. The verifier reads the RUT off the ID card
+  async verifyUser(idCardImageHash: string): Promise<string> {
     return this.call({
       zome_name: 'grip_zome',
       fn_name: 'verify_user',
-      payload: { rut, id_card_image_hash: idCardImageHash },
+      payload: { id_card_image_hash: idCardImageHash },
+    });
+  }
+
+  async getMyVerification(): Promise<VerificationRequestItem | null> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_my_verification',
+      payload: {},
+    });
+  }
+
+  async completeVerification(requestHash: string): Promise<User> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'complete_verification',
+      payload: requestHash,
+    });
+  }
+
+  // Verifier functions
+  async getPendingVerifications(): Promise<VerificationRequestItem[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_pending_verifications',
+      payload: {},
+    });
+  }
+
+  // `rut` is the one printed on the ID card; required to approve
+  async attestVerification(requestHash: string, approve: boolean, reason?: string, rut?: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'attest_verification',
+      payload: { request_hash: requestHash, approve, reason: reason || null, rut: rut || null },
+    });
+  }
+
+  async setRutKey(key: string): Promise<void> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'set_rut_key',
+      payload: key,
     });
   }
 
@@ -270,12 +507,49 @@ class HolochainClient {
     });
   }
 
-  async getFeed(limit?: number): Promise<Post[]> {
-    return this.call({
+  async getFeed(cursor?: FeedCursor | null, limit?: number): Promise<FeedPage> {
+    const result = await this.call({
       zome_name: 'grip_zome',
       fn_name: 'get_feed',
-      payload: limit || null,
+      payload: { cursor: cursor || null, limit: limit || null },
     });
+    return {
+      posts: (result?.items || []).map((item: any) => ({ ...item.post, hash: item.post_hash })),
+      nextCursor: result?.next_cursor || null,
+    };
+  }
+
+  async getHomeFeed(cursor?: FeedCursor | null, limit?: number): Promise<FeedPage> {
+    const result = await this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_home_feed',
+      payload: { cursor: cursor || null, limit: limit || null },
+    });
+    return {
+      posts: (result?.items || []).map((item: any) => ({ ...item.post, hash: item.post_hash })),
+      nextCursor: result?.next_cursor || null,
+    };
+  }
+
+  async getTrendingFeed(lat: number, lon: number, radiusKm: number, limit?: number): Promise<Array<Post & {
+    hash: string;
+    score: number;
+    distanceKm: number;
+  }>> {
+    const result = await this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_trending_feed',
+      payload: { lat, lon, radius_km: radiusKm, limit: limit || null },
+    });
+    if (Array.isArray(result)) {
+      return result.map((item: any) => ({
+        ...item.post,
+        hash: item.post_hash,
+        score: item.score,
+        distanceKm: item.distance_km,
+      }));
+    }
+    return [];
   }
 
   async getUserPosts(userId: string, limit?: number): Promise<Post[]> {
@@ -294,6 +568,26 @@ class HolochainClient {
     });
   }
 
+  async updatePost(postHash: string, text?: string | null, stickerData?: any[] | null): Promise<Post> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'update_post',
+      payload: {
+        post_hash: postHash,
+        text: text ?? null,
+        sticker_data: stickerData ?? null,
+      },
+    });
+  }
+
+  async deletePost(postHash: string): Promise<void> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'delete_post',
+      payload: postHash,
+    });
+  }
+
   // Product functions
   async createProduct(product: {
     title: string;
@@ -328,27 +622,25 @@ class HolochainClient {
   }
 
   async getProductComments(productId: string): Promise<any[]> {
-    return this.call({
-      zome_name: 'grip_zome',
-      fn_name: 'get_product_comments',
-      payload: productId,
-    });
+    const page = await this.getCommentThread({ type: 'product', hash: productId }, { depth: 1 });
+    return page.comments;
   }
 
-  async getCommentReplies(commentHash: string): Promise<any[]> {
+  // Ad Banner functions
+  async getActiveBanners(): Promise<any[]> {
     return this.call({
       zome_name: 'grip_zome',
-      fn_name: 'get_comment_replies',
-      payload: commentHash,
+      fn_name: 'get_active_banners',
+      payload: {},
     });
   }
 
-  // Ad Banner functions
-  async getActiveBanners(): Promise<any[]> {
+  // Moderators only; the banner shows up in getActiveBanners once approved
+  async approveAdBanner(bannerHash: string): Promise<string> {
     return this.call({
       zome_name: 'grip_zome',
-      fn_name: 'get_active_banners',
-      payload: {},
+      fn_name: 'approve_ad_banner',
+      payload: bannerHash,
     });
   }
 
@@ -425,17 +717,49 @@ class HolochainClient {
   }
 
   // Tamagochi Visit functions
-  async visitTamagochi(ownerId: string, message?: string): Promise<string> {
+  async visitTamagochi(
+    ownerId: string,
+    message?: string,
+    interaction?: 'pet' | 'play' | 'gift',
+    giftItemId?: string
+  ): Promise<string> {
     return this.call({
       zome_name: 'grip_zome',
       fn_name: 'visit_tamagochi',
       payload: {
         owner_id: ownerId,
         message: message || null,
+        interaction: interaction || null,
+        gift_item_id: giftItemId || null,
       },
     });
   }
 
+  async getPendingVisits(): Promise<Array<{ visit_hash: string; visit: any }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_pending_visits',
+      payload: {},
+    });
+  }
+
+  async respondToVisit(visitHash: string, accept: boolean): Promise<any | null> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'respond_to_visit',
+      payload: { visit_hash: visitHash, accept },
+    });
+  }
+
+  // Grows the visitor's pet for interactions the owners accepted; null if there was nothing new
+  async collectVisitPlay(): Promise<any | null> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'collect_visit_play',
+      payload: {},
+    });
+  }
+
   async getTamagochiVisits(ownerId: string): Promise<any[]> {
     return this.call({
       zome_name: 'grip_zome',
@@ -562,7 +886,7 @@ class HolochainClient {
     });
   }
 
-  async getPostClaps(postHash: string): Promise<number> {
+  async getPostClaps(postHash: string): Promise<{ total: number; mine: number }> {
     return this.call({
       zome_name: 'grip_zome',
       fn_name: 'get_post_claps',
@@ -615,22 +939,213 @@ class HolochainClient {
   }
 
   async getPostComments(postHash: string): Promise<any[]> {
+    const page = await this.getCommentThread({ type: 'post', hash: postHash }, { depth: 1 });
+    return page.comments;
+  }
+
+  async getCommentReplies(target: CommentTarget, commentHash: string): Promise<any[]> {
+    const page = await this.getCommentThread(target, { parentCommentHash: commentHash, depth: 1 });
+    return page.comments;
+  }
+
+  // Threaded comments (posts and products); nodes are flattened to { ...comment, hash, reply_count, replies }
+  async getCommentThread(
+    target: CommentTarget,
+    options: {
+      parentCommentHash?: string;
+      cursor?: { created_at: number; comment_hash: string } | null;
+      limit?: number;
+      depth?: number;
+    } = {}
+  ): Promise<{ comments: any[]; nextCursor: { created_at: number; comment_hash: string } | null }> {
+    const result = await this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_comment_thread',
+      payload: {
+        target,
+        parent_comment_hash: options.parentCommentHash || null,
+        cursor: options.cursor || null,
+        limit: options.limit || null,
+        depth: options.depth ?? null,
+      },
+    });
+    const flatten = (node: any): any => ({
+      ...node.comment,
+      hash: node.comment_hash,
+      reply_count: node.reply_count,
+      replies: (node.replies || []).map(flatten),
+    });
+    return {
+      comments: (result?.comments || []).map(flatten),
+      nextCursor: result?.next_cursor || null,
+    };
+  }
+
+  async updateComment(commentHash: string, text: string): Promise<any> {
     return this.call({
       zome_name: 'grip_zome',
-      fn_name: 'get_post_comments',
-      payload: postHash,
+      fn_name: 'update_comment',
+      payload: { comment_hash: commentHash, text },
     });
   }
 
-  async getCommentReplies(commentHash: string): Promise<any[]> {
+  async deleteComment(commentHash: string): Promise<void> {
     return this.call({
       zome_name: 'grip_zome',
-      fn_name: 'get_comment_replies',
+      fn_name: 'delete_comment',
       payload: commentHash,
     });
   }
 
-  // Post report functions
+  // Story functions
+  async createStory(
+    text: string | null,
+    imageHashes: string[],
+    videoHash: string | null,
+    stickerData: any[]
+  ): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'create_story',
+      payload: {
+        text: text || null,
+        image_hashes: imageHashes,
+        video_hash: videoHash || null,
+        sticker_data: stickerData,
+      },
+    });
+  }
+
+  async getFriendStories(): Promise<Array<{
+    author_id: string;
+    stories: Array<{ story_hash: string; story: Story }>;
+  }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_friend_stories',
+      payload: {},
+    });
+  }
+
+  async viewStory(storyHash: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'view_story',
+      payload: storyHash,
+    });
+  }
+
+  async getStoryViews(storyHash: string): Promise<Array<{
+    story_hash: string;
+    viewer_id: string;
+    viewed_at: number;
+  }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_story_views',
+      payload: storyHash,
+    });
+  }
+
+  // Sticker pack functions
+  async createStickerPack(name: string, imageHashes: string[]): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'create_sticker_pack',
+      payload: { name, image_hashes: imageHashes },
+    });
+  }
+
+  async getStickerPacks(): Promise<Array<{ pack_hash: string; pack: StickerPack }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_sticker_packs',
+      payload: {},
+    });
+  }
+
+  async getUserStickerPacks(userId: string): Promise<Array<{ pack_hash: string; pack: StickerPack }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_user_sticker_packs',
+      payload: userId,
+    });
+  }
+
+  // Hashtag and mention functions
+  async getPostsByHashtag(tag: string, cursor?: FeedCursor | null, limit?: number): Promise<FeedPage> {
+    const result = await this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_posts_by_hashtag',
+      payload: { tag, cursor: cursor || null, limit: limit || null },
+    });
+    return {
+      posts: (result?.items || []).map((item: any) => ({ ...item.post, hash: item.post_hash })),
+      nextCursor: result?.next_cursor || null,
+    };
+  }
+
+  async getTrendingHashtags(windowHours?: number): Promise<Array<{ tag: string; count: number }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_trending_hashtags',
+      payload: windowHours || null,
+    });
+  }
+
+  async getMyMentions(cursor?: { created_at: number; target_hash: string } | null, limit?: number): Promise<{
+    items: Array<{
+      target_hash: string;
+      kind: 'post' | 'comment';
+      context: CommentTarget;
+      author_id: string;
+      text: string;
+      created_at: number;
+    }>;
+    next_cursor: { created_at: number; target_hash: string } | null;
+  }> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_my_mentions',
+      payload: { cursor: cursor || null, limit: limit || null },
+    });
+  }
+
+  // Report functions
+  async report(
+    target: ReportTarget,
+    reason: string,
+    description?: string,
+    evidenceImageHashes?: string[]
+  ): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'report',
+      payload: {
+        target,
+        reason,
+        description: description || null,
+        evidence_image_hashes: evidenceImageHashes || [],
+      },
+    });
+  }
+
+  async getReports(target: ReportTarget): Promise<Report[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_reports',
+      payload: target,
+    });
+  }
+
+  async getAgentReports(agent: string): Promise<Report[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_agent_reports',
+      payload: agent,
+    });
+  }
+
   async reportPost(postHash: string, reason: string, description?: string): Promise<string> {
     return this.call({
       zome_name: 'grip_zome',
@@ -643,6 +1158,56 @@ class HolochainClient {
     });
   }
 
+  // Role functions
+  async getMyRoles(): Promise<Array<'admin' | 'moderator' | 'verifier'>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_my_roles',
+      payload: {},
+    });
+  }
+
+  async grantRole(agent: string, role: 'admin' | 'moderator' | 'verifier'): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'grant_role',
+      payload: { agent, role },
+    });
+  }
+
+  async revokeRole(agent: string, role: 'admin' | 'moderator' | 'verifier'): Promise<void> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'revoke_role',
+      payload: { agent, role },
+    });
+  }
+
+  // Moderation functions (moderators only)
+  async getModerationQueue(): Promise<Array<{
+    target: ReportTarget;
+    post: Post | null;
+    reported_agent: string | null;
+    agent_report_count: number;
+    report_count: number;
+    reports: Report[];
+    queued_at: number;
+  }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_moderation_queue',
+      payload: {},
+    });
+  }
+
+  async resolveReport(target: ReportTarget, action: 'keep' | 'hide' | 'remove', note?: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'resolve_report',
+      payload: { target, action, note: note || null },
+    });
+  }
+
   // User blocking functions
   async blockUser(userId: string): Promise<string> {
     return this.call({
@@ -668,6 +1233,14 @@ class HolochainClient {
     });
   }
 
+  async isBlockedBy(userId: string): Promise<boolean> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'is_blocked_by',
+      payload: userId,
+    });
+  }
+
   async getBlockedUsers(): Promise<string[]> {
     return this.call({
       zome_name: 'grip_zome',
@@ -749,6 +1322,163 @@ class HolochainClient {
     });
   }
 
+  // Tamagochi item functions
+  async getItemCatalogue(): Promise<ShopItem[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_item_catalogue',
+      payload: {},
+    });
+  }
+
+  async getInventory(): Promise<TamagochiInventory> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_inventory',
+      payload: {},
+    });
+  }
+
+  async buyItem(itemId: string, quantity: number = 1): Promise<TamagochiInventory> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'buy_item',
+      payload: { item_id: itemId, quantity },
+    });
+  }
+
+  async useItem(itemId: string): Promise<any> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'use_item',
+      payload: itemId,
+    });
+  }
+
+  async claimRideReward(rideHash: string): Promise<TamagochiInventory> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'claim_ride_reward',
+      payload: rideHash,
+    });
+  }
+
+  // Achievement functions
+  async getAchievementCatalogue(): Promise<AchievementInfo[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_achievement_catalogue',
+      payload: {},
+    });
+  }
+
+  async getAchievements(agentId: string): Promise<UnlockedAchievement[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_achievements',
+      payload: agentId,
+    });
+  }
+
+  async checkAchievements(): Promise<string[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'check_achievements',
+      payload: {},
+    });
+  }
+
+  // Breeding functions
+  async proposeBreeding(partnerId: string, eggOwnerId?: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'propose_breeding',
+      payload: { partner_id: partnerId, egg_owner_id: eggOwnerId ?? null },
+    });
+  }
+
+  async getBreedingProposals(): Promise<Array<{ proposal_hash: string; proposal: BreedingProposal }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_breeding_proposals',
+      payload: {},
+    });
+  }
+
+  async respondToBreeding(proposalHash: string, accept: boolean): Promise<string | null> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'respond_to_breeding',
+      payload: { proposal_hash: proposalHash, accept },
+    });
+  }
+
+  async getMyEggs(): Promise<Array<{ breeding_hash: string; breeding: BreedingRecord }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_my_eggs',
+      payload: {},
+    });
+  }
+
+  async hatchEgg(breedingHash: string, name: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'hatch_egg',
+      payload: { breeding_hash: breedingHash, name },
+    });
+  }
+
+  // Leaderboard functions
+  async getLeaderboard(metric: LeaderboardMetric, scope: LeaderboardScope, limit?: number): Promise<LeaderboardEntry[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_leaderboard',
+      payload: { metric, scope, limit: limit ?? null },
+    });
+  }
+
+  async publishLeaderboardSnapshot(): Promise<string | null> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'publish_leaderboard_snapshot',
+      payload: {},
+    });
+  }
+
+  // Graveyard functions
+  async getGraveyard(ownerId: string): Promise<Array<{ death_hash: string; death: any; memorial_count: number }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_graveyard',
+      payload: ownerId,
+    });
+  }
+
+  async setEpitaph(deathHash: string, epitaph: string): Promise<any> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'set_epitaph',
+      payload: { death_hash: deathHash, epitaph },
+    });
+  }
+
+  async leaveMemorial(deathHash: string, message: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'leave_memorial',
+      payload: { death_hash: deathHash, message },
+    });
+  }
+
+  async getMemorials(deathHash: string): Promise<Array<{ author_id: string; message: string; created_at: number }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_memorials',
+      payload: deathHash,
+    });
+  }
+
   // Ghost mode functions
   async setGhostMode(enabled: boolean): Promise<User> {
     return this.call({
@@ -775,6 +1505,38 @@ class HolochainClient {
     });
   }
 
+  async sendFriendRequest(recipientId: string, message?: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'send_friend_request',
+      payload: { recipient_id: recipientId, message: message ?? null },
+    });
+  }
+
+  async getPendingRequests(): Promise<Array<{ request_hash: string; request: FriendRequest }>> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_pending_requests',
+      payload: {},
+    });
+  }
+
+  async acceptFriendRequest(requestHash: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'accept_friend_request',
+      payload: requestHash,
+    });
+  }
+
+  async declineFriendRequest(requestHash: string): Promise<string> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'decline_friend_request',
+      payload: requestHash,
+    });
+  }
+
   async removeFriend(friendId: string): Promise<string> {
     return this.call({
       zome_name: 'grip_zome',
@@ -791,6 +1553,14 @@ class HolochainClient {
     });
   }
 
+  async getFriendSuggestions(limit: number = 20): Promise<FriendSuggestion[]> {
+    return this.call({
+      zome_name: 'grip_zome',
+      fn_name: 'get_friend_suggestions',
+      payload: limit,
+    });
+  }
+
   async isFriend(userId: string): Promise<boolean> {
     return this.call({
       zome_name: 'grip_zome',
@@ -800,7 +1570,7 @@ class HolochainClient {
   }
 
   // User preferences functions
-  async getUserPreferences(): Promise<any> {
+  async getUserPreferences(): Promise<UserPreferences | null> {
     return this.call({
       zome_name: 'grip_zome',
       fn_name: 'get_user_preferences',
@@ -812,7 +1582,12 @@ class HolochainClient {
     app_color?: string;
     tamagochi_enabled?: boolean;
     location_sharing_enabled?: boolean;
-  }): Promise<any> {
+    language?: 'es' | 'en' | 'pt';
+    distance_unit?: 'km' | 'mi';
+    notifications?: NotificationSettings;
+    default_currency?: string;
+    profile_visibility?: 'public' | 'friends' | 'private';
+  }): Promise<UserPreferences> {
     return this.call({
       zome_name: 'grip_zome',
       fn_name: 'update_user_preferences',
@@ -1,5 +1,5 @@
 import { create } from 'zustand';
-import { holochainClient, Post as HolochainPost } from './holochain';
+import { holochainClient, Post as HolochainPost, UserPreferences } from './holochain';
 import * as Location from 'expo-location';
 
 export type UserProfile = {
@@ -257,6 +257,8 @@ type AuthState = {
   setAppColor: (color: string) => Promise<void>;
   setTamagochiEnabled: (enabled: boolean) => Promise<void>;
   setLocationSharingEnabled: (enabled: boolean) => Promise<void>;
+  preferences: UserPreferences | null;
+  updatePreferences: (changes: Partial<Omit<UserPreferences, 'owner_id' | 'updated_at'>>) => Promise<void>;
   loadUserPreferences: () => Promise<void>;
   // Location
   getUserLocation: (userId: string) => Promise<{ lat: number; lon: number } | null>;
@@ -339,7 +341,7 @@ export const useAuthStore = create<AuthState>((set, get) => ({
             id: agentId,
             email: user.email,
             name: user.name,
-            rut: user.rut,
+            rut: user.rut_masked,
             isVerified: user.is_verified,
             profileImageHash: user.profile_image_hash,
             idCardImageHash: user.id_card_image_hash,
@@ -364,7 +366,7 @@ export const useAuthStore = create<AuthState>((set, get) => ({
             id: agentId,
             email: user.email,
             name: user.name,
-            rut: user.rut,
+            rut: user.rut_masked,
             isVerified: user.is_verified,
             profileImageHash: user.profile_image_hash,
             idCardImageHash: user.id_card_image_hash,
@@ -418,7 +420,6 @@ export const useAuthStore = create<AuthState>((set, get) => ({
       
       const holochainUpdates: any = {};
       if (updates.name) holochainUpdates.name = updates.name;
-      if (updates.rut) holochainUpdates.rut = updates.rut;
       if (updates.lat !== undefined) holochainUpdates.lat = updates.lat;
       if (updates.lon !== undefined) holochainUpdates.lon = updates.lon;
       
@@ -431,7 +432,7 @@ export const useAuthStore = create<AuthState>((set, get) => ({
               ...s.currentUser,
               ...updates,
               name: updatedUser.name,
-              rut: updatedUser.rut,
+              rut: updatedUser.rut_masked,
               lat: updatedUser.lat,
               lon: updatedUser.lon,
             },
@@ -496,7 +497,7 @@ export const useAuthStore = create<AuthState>((set, get) => ({
   loadFeed: async () => {
     try {
       set({ loadingFeed: true });
-      const posts = await holochainClient.getFeed(50);
+      const { posts } = await holochainClient.getFeed(null, 50);
       
       // Convert Holochain posts to app posts
       const appPosts: Post[] = await Promise.all(
@@ -1207,12 +1208,8 @@ export const useAuthStore = create<AuthState>((set, get) => ({
   
   addFriend: async (userId: string) => {
     try {
-      await holochainClient.addFriend(userId);
-      set((s) => {
-        const updated = new Set(s.friends);
-        updated.add(userId);
-        return { friends: updated };
-      });
+      // Solo envía la solicitud; será amigo cuando el otro acepte
+      await holochainClient.sendFriendRequest(userId);
     } catch (error) {
       console.error('Add friend error:', error);
       throw error;
@@ -1294,11 +1291,29 @@ export const useAuthStore = create<AuthState>((set, get) => ({
     }
   },
   
+  preferences: null,
+  
+  updatePreferences: async (changes) => {
+    try {
+      const prefs = await holochainClient.updateUserPreferences(changes);
+      set({
+        preferences: prefs,
+        appColor: prefs.app_color,
+        tamagochiEnabled: prefs.tamagochi_enabled,
+        locationSharingEnabled: prefs.location_sharing_enabled,
+      });
+    } catch (error) {
+      console.error('Update preferences error:', error);
+      throw error;
+    }
+  },
+  
   loadUserPreferences: async () => {
     try {
       const prefs = await holochainClient.getUserPreferences();
       if (prefs) {
         set({
+          preferences: prefs,
           appColor: prefs.app_color || '#1f7aec',
           tamagochiEnabled: prefs.tamagochi_enabled !== false,
           locationSharingEnabled: prefs.location_sharing_enabled || false,